@@ -0,0 +1,13 @@
+-- Import checkpoints (取り込みの再開位置, パイプラインとファイルごとに1行) --
+CREATE TABLE IF NOT EXISTS import_checkpoints (
+    pipeline TEXT NOT NULL,
+    path TEXT NOT NULL,
+    file_size BIGINT NOT NULL,
+    file_modified_ms BIGINT NOT NULL,
+    byte_offset BIGINT NOT NULL DEFAULT 0,
+    line_number BIGINT NOT NULL DEFAULT 0,
+    batches BIGINT NOT NULL DEFAULT 0,
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (pipeline, path)
+);
@@ -0,0 +1,6 @@
+-- Nozomi index table (1行が1つの .nozomi ファイルに対応する) --
+CREATE TABLE IF NOT EXISTS nozomi_indexes (
+    name TEXT PRIMARY KEY,
+    gallery_ids INTEGER[] NOT NULL,
+    imported_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
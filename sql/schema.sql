@@ -1,49 +1,91 @@
+-- Extensions (pg_trgm backs the prefix / substring matching of /suggest)
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
 -- Languages table
 CREATE TABLE IF NOT EXISTS languages (id SERIAL PRIMARY KEY, name TEXT NOT NULL UNIQUE, local_name TEXT, url TEXT);
 CREATE INDEX IF NOT EXISTS idx_languages_name ON languages(name);
+CREATE INDEX IF NOT EXISTS idx_languages_name_trgm ON languages USING gin (name gin_trgm_ops);
 
 -- Galleries table
 CREATE TABLE IF NOT EXISTS galleries (id SERIAL PRIMARY KEY, gallery_id INTEGER NOT NULL UNIQUE, title TEXT NOT NULL, date TIMESTAMPTZ NOT NULL, type TEXT NOT NULL, external_id TEXT NOT NULL, scene_indexes INTEGER[] NOT NULL DEFAULT '{}', related_ids TEXT[] NOT NULL DEFAULT '{}', japanese_title TEXT, language_id INTEGER REFERENCES languages(id), translation_group_id TEXT[] NOT NULL DEFAULT '{}', video TEXT, videofilename TEXT, gallery_url TEXT, date_published DATE, blocked BOOLEAN NOT NULL DEFAULT FALSE, files JSONB NOT NULL DEFAULT '[]');
 CREATE INDEX IF NOT EXISTS idx_galleries_gallery_id ON galleries(gallery_id);
 CREATE INDEX IF NOT EXISTS idx_galleries_language_id ON galleries(language_id);
+-- Translations as they appear in the source gallery (name, language_localname, url, galleryid)
+ALTER TABLE galleries ADD COLUMN IF NOT EXISTS languages JSONB NOT NULL DEFAULT '[]';
+-- Transaction that last inserted or updated the gallery (with its junction rows). Incremental exports read the rows changed since their previous snapshot
+ALTER TABLE galleries ADD COLUMN IF NOT EXISTS change_xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+CREATE INDEX IF NOT EXISTS idx_galleries_change_xid ON galleries(change_xid);
+
+-- Title search (title is split into words by the simple parser, japanese_title into character bigrams)
+CREATE OR REPLACE FUNCTION title_bigrams(input TEXT) RETURNS TEXT[] LANGUAGE SQL IMMUTABLE PARALLEL SAFE AS $$
+    SELECT COALESCE(array_agg(DISTINCT substr(t.s, i, 2)), '{}')
+    FROM (SELECT regexp_replace(lower(COALESCE(input, '')), '\s+', '', 'g') AS s) t, generate_series(1, length(t.s) - 1) AS i
+$$;
+ALTER TABLE galleries ADD COLUMN IF NOT EXISTS title_tsv tsvector GENERATED ALWAYS AS (setweight(to_tsvector('simple', title), 'A') || setweight(array_to_tsvector(title_bigrams(japanese_title)), 'B')) STORED;
+CREATE INDEX IF NOT EXISTS idx_galleries_title_tsv ON galleries USING gin (title_tsv);
+CREATE INDEX IF NOT EXISTS idx_galleries_title_trgm ON galleries USING gin (title gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_galleries_japanese_title_trgm ON galleries USING gin (japanese_title gin_trgm_ops);
 
 -- Tags table
 CREATE TABLE IF NOT EXISTS tags (id SERIAL PRIMARY KEY, name TEXT NOT NULL, url TEXT NOT NULL, male BOOLEAN NOT NULL DEFAULT FALSE, female BOOLEAN NOT NULL DEFAULT FALSE, UNIQUE(name, male, female));
 CREATE INDEX IF NOT EXISTS idx_tags_name_male_female ON tags(name, male, female);
+CREATE INDEX IF NOT EXISTS idx_tags_name_trgm ON tags USING gin (name gin_trgm_ops);
+-- Search namespace of a tag (same rule as Namespace::from_tag_flags). Both-gendered tags have no namespace (NULL)
+CREATE OR REPLACE FUNCTION tag_namespace(male BOOLEAN, female BOOLEAN) RETURNS TEXT LANGUAGE SQL IMMUTABLE PARALLEL SAFE AS $$
+    SELECT CASE WHEN male AND female THEN NULL WHEN female THEN 'female' WHEN male THEN 'male' ELSE 'tag' END
+$$;
 
 -- Artists table
 CREATE TABLE IF NOT EXISTS artists (id SERIAL PRIMARY KEY, artist TEXT NOT NULL UNIQUE, url TEXT NOT NULL);
 CREATE INDEX IF NOT EXISTS idx_artists_artist ON artists(artist);
+CREATE INDEX IF NOT EXISTS idx_artists_artist_trgm ON artists USING gin (artist gin_trgm_ops);
 
 -- Groups table
 CREATE TABLE IF NOT EXISTS groups (id SERIAL PRIMARY KEY, "group" TEXT NOT NULL UNIQUE, url TEXT NOT NULL);
 CREATE INDEX IF NOT EXISTS idx_groups_group ON groups("group");
+CREATE INDEX IF NOT EXISTS idx_groups_group_trgm ON groups USING gin ("group" gin_trgm_ops);
 
 -- Characters table
 CREATE TABLE IF NOT EXISTS characters (id SERIAL PRIMARY KEY, character TEXT NOT NULL UNIQUE, url TEXT NOT NULL);
 CREATE INDEX IF NOT EXISTS idx_characters_character ON characters(character);
+CREATE INDEX IF NOT EXISTS idx_characters_character_trgm ON characters USING gin (character gin_trgm_ops);
 
 -- Parodies table
 CREATE TABLE IF NOT EXISTS parodies (id SERIAL PRIMARY KEY, parody TEXT NOT NULL UNIQUE, url TEXT NOT NULL);
 CREATE INDEX IF NOT EXISTS idx_parodies_parody ON parodies(parody);
+CREATE INDEX IF NOT EXISTS idx_parodies_parody_trgm ON parodies USING gin (parody gin_trgm_ops);
 
--- Junction tables
+-- Junction tables (position is the index of the related entity in the source gallery)
 CREATE TABLE IF NOT EXISTS gallery_tags (gallery_id INTEGER NOT NULL REFERENCES galleries(id) ON DELETE CASCADE, tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE, PRIMARY KEY (gallery_id, tag_id));
+ALTER TABLE gallery_tags ADD COLUMN IF NOT EXISTS position INTEGER NOT NULL DEFAULT 0;
 CREATE INDEX IF NOT EXISTS idx_gallery_tags_gallery_id ON gallery_tags(gallery_id);
 CREATE INDEX IF NOT EXISTS idx_gallery_tags_tag_id ON gallery_tags(tag_id);
 
 CREATE TABLE IF NOT EXISTS gallery_artists (gallery_id INTEGER NOT NULL REFERENCES galleries(id) ON DELETE CASCADE, artist_id INTEGER NOT NULL REFERENCES artists(id) ON DELETE CASCADE, PRIMARY KEY (gallery_id, artist_id));
+ALTER TABLE gallery_artists ADD COLUMN IF NOT EXISTS position INTEGER NOT NULL DEFAULT 0;
 CREATE INDEX IF NOT EXISTS idx_gallery_artists_gallery_id ON gallery_artists(gallery_id);
 CREATE INDEX IF NOT EXISTS idx_gallery_artists_artist_id ON gallery_artists(artist_id);
 
 CREATE TABLE IF NOT EXISTS gallery_groups (gallery_id INTEGER NOT NULL REFERENCES galleries(id) ON DELETE CASCADE, group_id INTEGER NOT NULL REFERENCES groups(id) ON DELETE CASCADE, PRIMARY KEY (gallery_id, group_id));
+ALTER TABLE gallery_groups ADD COLUMN IF NOT EXISTS position INTEGER NOT NULL DEFAULT 0;
 CREATE INDEX IF NOT EXISTS idx_gallery_groups_gallery_id ON gallery_groups(gallery_id);
 CREATE INDEX IF NOT EXISTS idx_gallery_groups_group_id ON gallery_groups(group_id);
 
 CREATE TABLE IF NOT EXISTS gallery_characters (gallery_id INTEGER NOT NULL REFERENCES galleries(id) ON DELETE CASCADE, character_id INTEGER NOT NULL REFERENCES characters(id) ON DELETE CASCADE, PRIMARY KEY (gallery_id, character_id));
+ALTER TABLE gallery_characters ADD COLUMN IF NOT EXISTS position INTEGER NOT NULL DEFAULT 0;
 CREATE INDEX IF NOT EXISTS idx_gallery_characters_gallery_id ON gallery_characters(gallery_id);
 CREATE INDEX IF NOT EXISTS idx_gallery_characters_character_id ON gallery_characters(character_id);
 
 CREATE TABLE IF NOT EXISTS gallery_parodies (gallery_id INTEGER NOT NULL REFERENCES galleries(id) ON DELETE CASCADE, parody_id INTEGER NOT NULL REFERENCES parodies(id) ON DELETE CASCADE, PRIMARY KEY (gallery_id, parody_id));
+ALTER TABLE gallery_parodies ADD COLUMN IF NOT EXISTS position INTEGER NOT NULL DEFAULT 0;
 CREATE INDEX IF NOT EXISTS idx_gallery_parodies_gallery_id ON gallery_parodies(gallery_id);
 CREATE INDEX IF NOT EXISTS idx_gallery_parodies_parody_id ON gallery_parodies(parody_id);
+
+-- Suggestion entries with precomputed gallery counts (/suggest reads this instead of aggregating the junction tables).
+-- Eventually consistent: refreshed at the end of every import, ingest and replay (also when they fail) and by `hitomi refresh-suggest`.
+-- ns is the search namespace (tag_namespace), and both-gendered tags are left out like in /search.
+CREATE MATERIALIZED VIEW IF NOT EXISTS suggest_entries AS SELECT tag_namespace(e.male, e.female) AS ns, e.name, COALESCE(c.count, 0) AS gallery_count FROM tags e LEFT JOIN (SELECT tag_id, COUNT(*) AS count FROM gallery_tags GROUP BY tag_id) c ON c.tag_id = e.id WHERE tag_namespace(e.male, e.female) IS NOT NULL UNION ALL SELECT 'artist', e.artist, COALESCE(c.count, 0) FROM artists e LEFT JOIN (SELECT artist_id, COUNT(*) AS count FROM gallery_artists GROUP BY artist_id) c ON c.artist_id = e.id UNION ALL SELECT 'group', e."group", COALESCE(c.count, 0) FROM groups e LEFT JOIN (SELECT group_id, COUNT(*) AS count FROM gallery_groups GROUP BY group_id) c ON c.group_id = e.id UNION ALL SELECT 'character', e.character, COALESCE(c.count, 0) FROM characters e LEFT JOIN (SELECT character_id, COUNT(*) AS count FROM gallery_characters GROUP BY character_id) c ON c.character_id = e.id UNION ALL SELECT 'series', e.parody, COALESCE(c.count, 0) FROM parodies e LEFT JOIN (SELECT parody_id, COUNT(*) AS count FROM gallery_parodies GROUP BY parody_id) c ON c.parody_id = e.id UNION ALL SELECT 'language', e.name, COALESCE(c.count, 0) FROM languages e LEFT JOIN (SELECT language_id, COUNT(*) AS count FROM galleries GROUP BY language_id) c ON c.language_id = e.id;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_suggest_entries_ns_name ON suggest_entries(ns, name);
+-- Short inputs (too short for trigrams) are matched by prefix on this btree
+CREATE INDEX IF NOT EXISTS idx_suggest_entries_prefix ON suggest_entries(ns, lower(name) text_pattern_ops);
+CREATE INDEX IF NOT EXISTS idx_suggest_entries_name_trgm ON suggest_entries USING gin (name gin_trgm_ops);
@@ -0,0 +1,192 @@
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json
+};
+use sea_orm::{DbErr, RuntimeErr};
+use serde::Serialize;
+use crate::framing::StreamError;
+use crate::search::parser::ParseError;
+use sqlparser::parser::ParserError;
+
+/// API のエラーコード (クライアントが分岐に使う)
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// リクエストのフィールドが不正
+    InvalidRequest,
+    /// SQL の構文エラー
+    InvalidSql,
+    /// 検索クエリの構文エラー
+    InvalidSearchQuery,
+    /// gallery_id 以外を返す SQL
+    ForbiddenProjection,
+    /// SELECT 以外の文、許可されていないテーブル・関数など
+    ForbiddenStatement,
+    /// カーソルが不正、またはリクエストと矛盾している
+    InvalidCursor,
+    NotFound,
+    /// statement_timeout に達した
+    Timeout,
+    /// `/sets` の葉が読み込める件数の上限を超えた
+    TooManyRows,
+    DbError,
+    Internal,
+}
+
+impl ErrorCode {
+    /// JSON と同じ snake_case の名前
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InvalidRequest => "invalid_request",
+            Self::InvalidSql => "invalid_sql",
+            Self::InvalidSearchQuery => "invalid_search_query",
+            Self::ForbiddenProjection => "forbidden_projection",
+            Self::ForbiddenStatement => "forbidden_statement",
+            Self::InvalidCursor => "invalid_cursor",
+            Self::NotFound => "not_found",
+            Self::Timeout => "timeout",
+            Self::TooManyRows => "too_many_rows",
+            Self::DbError => "db_error",
+            Self::Internal => "internal",
+        }
+    }
+}
+
+/// エラー位置 (1 始まり)
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorPosition {
+    pub line: u64,
+    pub column: u64,
+}
+
+/// 全エンドポイント共通のエラー
+/// `{"code": "...", "message": "...", "position": {"line": 1, "column": 8}}` の JSON として返す
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<ErrorPosition>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { status, code, message: message.into(), position: None }
+    }
+
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, ErrorCode::InvalidRequest, message)
+    }
+
+    pub fn forbidden_statement(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, ErrorCode::ForbiddenStatement, message)
+    }
+
+    pub fn invalid_cursor(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, ErrorCode::InvalidCursor, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, ErrorCode::NotFound, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::Internal, message)
+    }
+
+    /// sqlparser のエラーから位置情報付きのエラーを作る
+    pub fn invalid_sql(err: &ParserError) -> Self {
+        let message = err.to_string();
+        Self {
+            position: parse_parser_position(&message),
+            ..Self::new(StatusCode::BAD_REQUEST, ErrorCode::InvalidSql, format!("Failed to parse SQL: {}", message))
+        }
+    }
+
+    /// DB エラーを分類する (statement_timeout によるキャンセルは timeout)
+    pub fn db_error(context: &str, err: &DbErr) -> Self {
+        if is_statement_timeout(err) {
+            Self::new(StatusCode::GATEWAY_TIMEOUT, ErrorCode::Timeout, format!("{}: statement timeout exceeded", context))
+        } else {
+            Self::new(StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::DbError, format!("{}: {}", context, err))
+        }
+    }
+
+    pub fn with_position(mut self, position: ErrorPosition) -> Self {
+        self.position = Some(position);
+        self
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// バイナリストリームの end フレームに載せるエラー
+impl From<ApiError> for StreamError {
+    fn from(err: ApiError) -> Self {
+        Self { code: err.code.as_str().to_string(), message: err.message }
+    }
+}
+
+/// 検索クエリは1行なので、文字オフセットを列番号 (1 始まり) にする
+impl From<ParseError> for ApiError {
+    fn from(err: ParseError) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, ErrorCode::InvalidSearchQuery, format!("Invalid search query: {}", err.message))
+            .with_position(ErrorPosition { line: 1, column: err.position as u64 + 1 })
+    }
+}
+
+/// ボディの JSON が不正な場合も同じ形式で返す
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), ErrorCode::InvalidRequest, rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::new(rejection.status(), ErrorCode::InvalidRequest, rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(rejection.status(), ErrorCode::InvalidRequest, rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
+
+/// PostgreSQL の query_canceled (57014) かどうか
+pub fn is_statement_timeout(err: &DbErr) -> bool {
+    match err {
+        DbErr::Query(RuntimeErr::SqlxError(e)) | DbErr::Exec(RuntimeErr::SqlxError(e)) => e
+            .as_database_error()
+            .and_then(|e| e.code())
+            .is_some_and(|code| code == "57014"),
+        _ => false,
+    }
+}
+
+/// sqlparser のメッセージ末尾の "at Line: 1, Column: 8" を取り出す
+fn parse_parser_position(message: &str) -> Option<ErrorPosition> {
+    let (_, location) = message.rsplit_once(" at Line: ")?;
+    let (line, column) = location.split_once(", Column: ")?;
+    Some(ErrorPosition {
+        line: line.trim().parse().ok()?,
+        column: column.trim().parse().ok()?,
+    })
+}
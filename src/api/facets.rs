@@ -0,0 +1,58 @@
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::StatusCode,
+    Json
+};
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::perform_sql::{begin_read_only, is_only_gallery_id_returned};
+use crate::api::sql_validator::validate_read_only_query;
+use crate::domain::dto::FacetsRequest;
+use crate::domain::facet::FacetsResponse;
+use crate::mapper::facets_mapper;
+use crate::search::{compiler, parser};
+use sea_orm::DatabaseConnection;
+
+pub async fn facets(
+    State(db): State<DatabaseConnection>,
+    payload: Result<Json<FacetsRequest>, JsonRejection>,
+) -> Result<Json<FacetsResponse>, ApiError> {
+
+    let Json(payload) = payload?;
+    payload.validate()?;
+
+    // 結果セットを galleries の行として取り出すクエリ
+    // (`sql` は /sql と同じく AST から組み立て直したものを埋め込む)
+    let (matched_sql, values) = match (payload.normalized_sql()?, &payload.search) {
+        (Some(sql), _) => {
+            validate_read_only_query(&sql)?;
+            if !is_only_gallery_id_returned(&sql) {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    ErrorCode::ForbiddenProjection,
+                    "SQL must return only 'gallery_id' column.",
+                ));
+            }
+            (format!("SELECT g.id, g.language_id, g.type FROM galleries g WHERE g.gallery_id IN ({})", sql), vec![])
+        }
+        (None, Some(search)) => {
+            let query = parser::parse(search)?;
+            let mut values = Vec::new();
+            let condition = compiler::compile_condition(&query, &mut values);
+            (format!("SELECT g.id, g.language_id, g.type FROM galleries g WHERE {}", condition), values)
+        }
+        (None, None) => unreachable!("validated above"),
+    };
+
+    let limits = payload.resolved_limits();
+    let stmt = facets_mapper::build_facets_statement(&matched_sql, values, &limits);
+
+    let txn = begin_read_only(&db, payload.statement_timeout())
+        .await
+        .map_err(|err| ApiError::db_error("Failed to start transaction", &err))?;
+    let response = facets_mapper::find_facets(&txn, stmt, &limits)
+        .await
+        .map_err(|err| ApiError::db_error("Facet aggregation error", &err))?;
+    txn.rollback().await.ok();
+
+    Ok(Json(response))
+}
@@ -0,0 +1,62 @@
+use axum::{
+    extract::{rejection::PathRejection, Path, State},
+    http::{header, HeaderMap},
+    response::Response,
+    body::Body
+};
+use crate::api::error::ApiError;
+use crate::api::state::FbsDatabase;
+use crate::fbs::compress::CompressType;
+use crate::mapper::fbs_galleries_mapper;
+
+pub async fn get_fbs_gallery(
+    State(FbsDatabase(db)): State<FbsDatabase>,
+    gallery_id: Result<Path<i32>, PathRejection>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+
+    let Path(gallery_id) = gallery_id?;
+    let row = fbs_galleries_mapper::find_fbs_gallery(&db, gallery_id)
+        .await
+        .map_err(|err| ApiError::db_error("Failed to fetch FlatBuffers", &err))?
+        .ok_or_else(|| ApiError::not_found(format!("Gallery {} not found.", gallery_id)))?;
+
+    let compress_type = CompressType::from_name(&row.compress_type)
+        .ok_or_else(|| ApiError::internal(format!("Unknown compress_type: {}", row.compress_type)))?;
+
+    let response = Response::builder()
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::VARY, "accept-encoding");
+
+    // zstd を受け付けるクライアントには圧縮済みのバイト列をそのまま返す
+    let (response, body) = if compress_type == CompressType::Zstd && accepts_encoding(&headers, "zstd") {
+        (response.header(header::CONTENT_ENCODING, "zstd"), row.data)
+    } else {
+        let data = compress_type.decompress(row.data)
+            .map_err(|err| ApiError::internal(format!("Failed to decompress gallery {}: {}", gallery_id, err)))?;
+        (response, data)
+    };
+
+    response
+        .body(Body::from(body))
+        .map_err(|err| ApiError::internal(format!("Failed to build response: {}", err)))
+}
+
+/// Accept-Encoding に指定の encoding が含まれているか (q=0 は拒否とみなす)
+pub fn accepts_encoding(headers: &HeaderMap, encoding: &str) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            let rejected = parts.any(|p| {
+                p.strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            name.eq_ignore_ascii_case(encoding) && !rejected
+        })
+}
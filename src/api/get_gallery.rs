@@ -0,0 +1,24 @@
+use axum::{
+    extract::{rejection::PathRejection, Path, State},
+    Json
+};
+use crate::api::error::ApiError;
+use crate::domain::gallery::Gallery;
+use crate::mapper::galleries_mapper;
+use sea_orm::DatabaseConnection;
+
+pub async fn get_gallery(
+    State(db): State<DatabaseConnection>,
+    gallery_id: Result<Path<i32>, PathRejection>,
+) -> Result<Json<Gallery>, ApiError> {
+
+    let Path(gallery_id) = gallery_id?;
+    let gallery = galleries_mapper::find_gallery_by_gallery_id(&db, gallery_id)
+        .await
+        .map_err(|err| ApiError::db_error("Failed to fetch gallery", &err))?;
+
+    match gallery {
+        Some(gallery) => Ok(Json(gallery)),
+        None => Err(ApiError::not_found(format!("Gallery {} not found.", gallery_id))),
+    }
+}
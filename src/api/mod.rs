@@ -1 +1,12 @@
 pub mod perform_sql;
+pub mod get_gallery;
+pub mod get_fbs_gallery;
+pub mod search;
+pub mod suggest;
+pub mod facets;
+pub mod refresh_index;
+pub mod sql_validator;
+pub mod state;
+pub mod error;
+pub mod sets;
+pub mod server;
\ No newline at end of file
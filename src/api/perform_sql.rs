@@ -1,89 +1,367 @@
 use axum::{
-    extract::State,
+    extract::{rejection::JsonRejection, State},
     response::Response,
     body::Body,Json
 };
+use bytes::Bytes;
+use futures::stream::BoxStream;
 use futures::StreamExt;
-use crate::domain::dto::SQLRequest;
-use sea_orm::{DatabaseConnection, DbBackend, Statement, StreamTrait};
+use http_body_util::StreamBody;
+use http_body::Frame;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::state::FbsDatabase;
+use crate::domain::cursor::{Cursor, Keyset};
+use crate::domain::dto::{Hydrate, OutputFormat, SQLRequest};
+use crate::fbs::compress::CompressType;
+use crate::framing::{PayloadKind, StreamEnd};
+use crate::framing::encoder::{encode_chunk, encode_end, encode_header, encode_ids, push_fbs_record};
+use crate::mapper::fbs_galleries_mapper;
+use crate::nozomi;
+use crate::api::sql_validator::validate_read_only_query;
+use sea_orm::{AccessMode, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, DbErr, Statement, StreamTrait, TransactionTrait};
+use std::time::Duration;
 use sqlparser::dialect::PostgreSqlDialect;
 use sqlparser::parser::Parser;
 use sqlparser::ast::{Statement as ParserStatement, SelectItem, Expr};
 
 pub async fn perform_sql(
     State(db): State<DatabaseConnection>,
-    Json(payload): Json<SQLRequest>,
-) -> Result<Response, (axum::http::StatusCode, String)> {
+    State(FbsDatabase(fbs_db)): State<FbsDatabase>,
+    payload: Result<Json<SQLRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+
+    let Json(payload) = payload?;
+    payload.validate()?;
 
     let batch_size = payload.batch_size as usize;
-    let sql = payload.build_paginated_query()
-        .map_err(|err| (axum::http::StatusCode::BAD_REQUEST, err))?;
+    let keyset = payload.keyset()?;
+
+    // キーセットページネーションでは LIMIT を外側のクエリに付ける
+    let sql = match keyset {
+        None => payload.build_paginated_query(),
+        Some(_) => payload.build_inner_query(),
+    }?;
+
+    validate_read_only_query(&sql)?;
 
     let is_valid = is_only_gallery_id_returned(&sql);
 
     if !is_valid {
-        return Err((
+        return Err(ApiError::new(
             axum::http::StatusCode::BAD_REQUEST,
-            "SQL must return only 'gallery_id' column.".to_string(),
+            ErrorCode::ForbiddenProjection,
+            "SQL must return only 'gallery_id' column.",
         ));
     };
 
-    let stmt = Statement::from_string(DbBackend::Postgres, sql);
+    let stmt = match &keyset {
+        None => Statement::from_string(DbBackend::Postgres, sql),
+        Some(keyset) => build_keyset_statement(&sql, keyset, payload.limit),
+    };
 
-    let query_stream = match db.stream_raw(stmt).await {
-        Ok(stream) => stream,
-        Err(err) => {
-            return Err((
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("SQL execution error: {}", err),
-            ))
-        }
+    let options = StreamOptions {
+        batch_size,
+        hydrate: payload.hydrate,
+        statement_timeout: payload.statement_timeout(),
+        keyset,
+        settings: vec![],
+        format: payload.format,
     };
 
-    let byte_stream = query_stream
-    .chunks(batch_size)
-    .map(move |batch| {
-        // batch は Vec<Result<QueryResult, DbErr>> 型になる
-        
-        // メモリ効率のため、あらかじめバッファを確保（i32 = 4 bytes）
-        let mut buffer = Vec::with_capacity(batch.len() * 4);
-
-        for row_result in batch {
-            match row_result {
-                Ok(row) => {
-                    let gallery_id: i32 = row.try_get_by_index(0).unwrap_or_default();
-                    // リトルエンディアンでバッファに追加
-                    buffer.extend_from_slice(&gallery_id.to_le_bytes());
-                }
+    stream_gallery_ids(db, fbs_db, stmt, options).await
+}
+
+/// gallery_id のストリームの出力方法
+pub struct StreamOptions {
+    pub batch_size: usize,
+    pub hydrate: Option<Hydrate>,
+    pub statement_timeout: Duration,
+    /// 指定した場合、クエリは2列目にソートキーのテキスト表現を返す必要がある
+    /// (最後の行から end フレームの next_cursor を作る)
+    pub keyset: Option<Keyset>,
+    /// トランザクション内だけで有効にする設定 (`set_config(name, value, true)`)
+    pub settings: Vec<(&'static str, String)>,
+    pub format: OutputFormat,
+}
+
+/// ユーザーのクエリを (ソートキー, gallery_id) のキーセットページネーションで包む
+///
+/// `SELECT g.gallery_id, <sort_key>::text FROM galleries g WHERE g.gallery_id IN (<query>)
+///  AND (<sort_key>, g.gallery_id) < ($1, $2) ORDER BY <sort_key> DESC, g.gallery_id DESC LIMIT <limit>`
+pub fn build_keyset_statement(inner_sql: &str, keyset: &Keyset, limit: Option<u32>) -> Statement {
+    let expr = keyset.sort_by.expr();
+    let direction = keyset.order.keyword();
+
+    let mut values: Vec<sea_orm::Value> = Vec::new();
+    let mut sql = format!(
+        "SELECT g.gallery_id, ({expr})::text AS sort_key FROM galleries g WHERE g.gallery_id IN ({inner_sql})"
+    );
+    if let Some(after) = &keyset.after {
+        sql.push_str(&format!(
+            " AND ({expr}, g.gallery_id) {} ($1::{}, $2)",
+            keyset.order.next_operator(),
+            keyset.sort_by.sql_type(),
+        ));
+        values.push(after.key.clone().into());
+        values.push(after.gallery_id.into());
+    }
+    sql.push_str(&format!(" ORDER BY {expr} {direction}, g.gallery_id {direction}"));
+    if let Some(limit) = limit {
+        sql.push_str(&format!(" LIMIT {}", limit));
+    }
+
+    Statement::from_sql_and_values(DbBackend::Postgres, sql, values)
+}
+
+/// gallery_id を1列だけ返すクエリを実行し、結果をバイナリストリームとして返す
+/// (`/sql` と `/search` で共通のエンコーディング、形式は `crate::framing` を参照)
+///
+/// ストリームの途中で発生したエラーは接続を切らずに end フレームで返す。
+pub async fn stream_gallery_ids(
+    db: DatabaseConnection,
+    fbs_db: DatabaseConnection,
+    stmt: Statement,
+    options: StreamOptions,
+) -> Result<Response, ApiError> {
+
+    let StreamOptions { batch_size, hydrate, statement_timeout, keyset, settings, format } = options;
+    let has_cursor = keyset.is_some();
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<Vec<i32>, DbErr>>(4);
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel::<Result<(), DbErr>>();
+    let (cursor_tx, cursor_rx) = tokio::sync::oneshot::channel::<Option<Cursor>>();
+
+    // トランザクションをレスポンスのストリームより長く生かすため、別タスクで実行して
+    // バッチ単位でチャンネルに流す
+    tokio::spawn(async move {
+        let txn = match begin_read_only(&db, statement_timeout).await {
+            Ok(txn) => txn,
+            Err(err) => {
+                ready_tx.send(Err(err)).ok();
+                return;
+            }
+        };
+        for (name, value) in settings {
+            let stmt = Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT set_config($1, $2, true)",
+                vec![name.into(), value.into()],
+            );
+            if let Err(err) = txn.execute_raw(stmt).await {
+                ready_tx.send(Err(err)).ok();
+                return;
+            }
+        }
+
+        {
+            let query_stream = match txn.stream_raw(stmt).await {
+                Ok(stream) => stream,
                 Err(err) => {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Row retrieval error: {}", err),
-                    ));
+                    ready_tx.send(Err(err)).ok();
+                    return;
                 }
+            };
+            ready_tx.send(Ok(())).ok();
+
+            let mut last_row = None;
+            let mut completed = true;
+            let mut batches = query_stream.chunks(batch_size);
+            while let Some(batch) = batches.next().await {
+                // batch は Vec<Result<QueryResult, DbErr>> 型になる
+                let ids = batch.into_iter()
+                    .map(|row_result| match row_result {
+                        Ok(row) => {
+                            let gallery_id = row.try_get_by_index::<i32>(0).unwrap_or_default();
+                            if keyset.is_some() {
+                                last_row = Some((row.try_get_by_index::<String>(1).unwrap_or_default(), gallery_id));
+                            }
+                            Ok(gallery_id)
+                        }
+                        Err(err) => Err(err),
+                    })
+                    .collect::<Result<Vec<i32>, DbErr>>();
+
+                let failed = ids.is_err();
+                if tx.send(ids).await.is_err() || failed {
+                    completed = false;
+                    break;
+                }
+            }
+
+            // 最後まで返せた場合のみ次のページのカーソルを渡す
+            let cursor = match (&keyset, last_row) {
+                (Some(keyset), Some((key, gallery_id))) if completed => Some(keyset.next_cursor(key, gallery_id)),
+                _ => None,
+            };
+            cursor_tx.send(cursor).ok();
+        }
+
+        // 読み取り専用なのでコミットは不要
+        txn.rollback().await.ok();
+    });
+
+    match ready_rx.await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => return Err(ApiError::db_error("SQL execution error", &err)),
+        Err(_) => return Err(ApiError::internal("SQL execution task terminated unexpectedly.")),
+    }
+
+    let id_batches = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|ids| (ids, rx))
+    })
+    .map(|ids| ids.map_err(|err| ApiError::db_error("Row retrieval error", &err)))
+    .boxed();
+
+    match format {
+        OutputFormat::Framed => {
+            build_id_stream_response(id_batches, fbs_db, batch_size, hydrate, has_cursor.then_some(cursor_rx))
+        }
+        OutputFormat::Nozomi => build_nozomi_response(id_batches, batch_size),
+    }
+}
+
+/// メモリ上で求めた gallery_id の列を `stream_gallery_ids` と同じ形式で返す
+/// (`next_cursor` は end フレームに載せる)
+pub fn stream_id_list(
+    fbs_db: DatabaseConnection,
+    ids: Vec<i32>,
+    batch_size: usize,
+    hydrate: Option<Hydrate>,
+    next_cursor: Option<Cursor>,
+) -> Result<Response, ApiError> {
+    let batches: Vec<Result<Vec<i32>, ApiError>> = ids.chunks(batch_size).map(|ids| Ok(ids.to_vec())).collect();
+    let cursor_rx = next_cursor.map(|cursor| {
+        let (cursor_tx, cursor_rx) = tokio::sync::oneshot::channel();
+        cursor_tx.send(Some(cursor)).ok();
+        cursor_rx
+    });
+    build_id_stream_response(futures::stream::iter(batches).boxed(), fbs_db, batch_size, hydrate, cursor_rx)
+}
+
+/// gallery_id のバッチを `.nozomi` と同じビッグエンディアンの i32 列として返す
+///
+/// フレームが無いので、途中でエラーが起きた場合は接続を切る (カーソルも返せない)。
+fn build_nozomi_response(
+    id_batches: BoxStream<'static, Result<Vec<i32>, ApiError>>,
+    batch_size: usize,
+) -> Result<Response, ApiError> {
+
+    let frames = id_batches.map(|ids| {
+        ids.map(|ids| Frame::data(Bytes::from(nozomi::encode(&ids))))
+            .map_err(|err| std::io::Error::other(err.message))
+    });
+
+    Response::builder()
+        .header("X-Batch-Size", batch_size.to_string())
+        .header("X-Format", "nozomi")
+        .header("Content-Type", "application/octet-stream")
+        .body(Body::new(StreamBody::new(frames)))
+        .map_err(|err| ApiError::internal(format!("Failed to build response: {}", err)))
+}
+
+/// gallery_id のバッチをフレームに包んでレスポンスにする
+/// (`cursor_rx` を渡した場合は、最後まで返せたときに次のページのカーソルを end フレームに載せる)
+fn build_id_stream_response(
+    id_batches: BoxStream<'static, Result<Vec<i32>, ApiError>>,
+    fbs_db: DatabaseConnection,
+    batch_size: usize,
+    hydrate: Option<Hydrate>,
+    cursor_rx: Option<tokio::sync::oneshot::Receiver<Option<Cursor>>>,
+) -> Result<Response, ApiError> {
+
+    // (行数, chunk のペイロード) のストリーム
+    let (payload, chunks) = match hydrate {
+        None => (PayloadKind::Ids, id_batches.map(|ids| ids.map(|ids| (ids.len(), encode_ids(&ids)))).boxed()),
+        Some(Hydrate::Fbs) => (PayloadKind::Fbs, id_batches.then(move |ids| {
+            let fbs_db = fbs_db.clone();
+            async move {
+                let ids = ids?;
+                let rows = ids.len();
+                hydrate_fbs(&fbs_db, ids).await.map(|data| (rows, data))
+            }
+        }).boxed()),
+    };
+
+    // header, chunk..., end の順に送る。エラーが起きたらそこまでの行数とエラーを end に載せて終える
+    let body = futures::stream::unfold(Some((chunks, 0u64, cursor_rx)), |state| async move {
+        let (mut chunks, total_rows, cursor_rx) = state?;
+        match chunks.next().await {
+            Some(Ok((rows, data))) => {
+                Some((encode_chunk(rows as u32, &data), Some((chunks, total_rows + rows as u64, cursor_rx))))
+            }
+            Some(Err(err)) => {
+                Some((encode_end(&StreamEnd { total_rows, error: Some(err.into()), next_cursor: None }), None))
+            }
+            None => {
+                // id のチャンネルが閉じた時点でカーソルは送信済み
+                let next_cursor = match cursor_rx {
+                    Some(cursor_rx) => cursor_rx.await.ok().flatten().map(|cursor| cursor.encode()),
+                    None => None,
+                };
+                Some((encode_end(&StreamEnd { total_rows, error: None, next_cursor }), None))
             }
         }
-        
-        // まとまったバッファを一つの塊として流す
-        Ok::<_, std::io::Error>(buffer)
     });
+    let frames = futures::stream::once(async move { encode_header(payload) })
+        .chain(body)
+        .map(|data| Ok::<_, std::io::Error>(Frame::data(Bytes::from(data))));
 
     Response::builder()
         .header("X-Batch-Size", batch_size.to_string())
+        .header("X-Hydrate", match payload {
+            PayloadKind::Ids => "none",
+            PayloadKind::Fbs => "fbs",
+        })
         .header("Content-Type", "application/octet-stream")
-        .body(Body::from_stream(byte_stream))
-        .map_err(|err| (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to build response: {}", err),
-        ))
+        .body(Body::new(StreamBody::new(frames)))
+        .map_err(|err| ApiError::internal(format!("Failed to build response: {}", err)))
 }
 
+/// READ ONLY トランザクションを開始し、statement_timeout を設定する
+pub async fn begin_read_only(db: &DatabaseConnection, statement_timeout: Duration) -> Result<DatabaseTransaction, DbErr> {
+    let txn = db.begin_with_config(None, Some(AccessMode::ReadOnly)).await?;
+    txn.execute_unprepared(&format!("SET LOCAL statement_timeout = {}", statement_timeout.as_millis())).await?;
+    Ok(txn)
+}
+
+/// gallery_id のバッチを fbs_galleries と突き合わせ、FlatBuffers のレコード列にする (chunk のペイロード)
+///
+/// レコードは `gallery_id (i32 LE) | length (u32 LE) | FlatBuffers` で、SQL の結果と同じ順序で並ぶ。
+/// fbs_galleries に存在しない gallery_id は length = 0 のレコードになる。
+async fn hydrate_fbs(fbs_db: &DatabaseConnection, ids: Vec<i32>) -> Result<Vec<u8>, ApiError> {
+    let rows: std::collections::HashMap<i32, fbs_galleries_mapper::FbsGalleryRow> =
+        fbs_galleries_mapper::find_fbs_galleries(fbs_db, ids.clone())
+            .await
+            .map_err(|err| ApiError::db_error("FlatBuffers retrieval error", &err))?
+            .into_iter()
+            .map(|row| (row.gallery_id, row))
+            .collect();
+
+    let mut buffer = Vec::new();
+    for gallery_id in ids {
+        let data = match rows.get(&gallery_id) {
+            Some(row) => {
+                let compress_type = CompressType::from_name(&row.compress_type)
+                    .ok_or_else(|| ApiError::internal(format!("Unknown compress_type: {}", row.compress_type)))?;
+                compress_type.decompress(row.data.clone())
+                    .map_err(|err| ApiError::internal(format!("Failed to decompress gallery {}: {}", gallery_id, err)))?
+            }
+            None => vec![],
+        };
+        push_fbs_record(&mut buffer, gallery_id, &data);
+    }
+    Ok(buffer)
+}
+
+/// SQL が gallery_id の1列だけを返すか (パースできない SQL は false)
 pub fn is_only_gallery_id_returned(sql: &str) -> bool {
     let dialect = PostgreSqlDialect {};
-    let ast = Parser::parse_sql(&dialect, sql).unwrap();
+    let Ok(ast) = Parser::parse_sql(&dialect, sql) else {
+        return false;
+    };
 
-    if let ParserStatement::Query(query) = &ast[0] {
-        if let sqlparser::ast::SetExpr::Select(select) = &*query.body {
+    if let [ParserStatement::Query(query)] = ast.as_slice()
+        && let sqlparser::ast::SetExpr::Select(select) = &*query.body {
             if select.projection.len() != 1 {
                 return false;
             }
@@ -98,6 +376,5 @@ pub fn is_only_gallery_id_returned(sql: &str) -> bool {
                 _ => false,
             };
         }
-    }
     false
 }
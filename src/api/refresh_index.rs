@@ -0,0 +1,34 @@
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    Json
+};
+use crate::api::error::ApiError;
+use crate::api::state::SearchIndex;
+use crate::domain::dto::{RefreshIndexRequest, RefreshIndexResponse};
+use crate::search::bitmap_index::Refresh;
+use sea_orm::DatabaseConnection;
+
+/// インポート後にビットマップインデックスを読み直す (サーバーを再起動せずに反映する)
+pub async fn refresh_index(
+    State(db): State<DatabaseConnection>,
+    State(SearchIndex(search_index)): State<SearchIndex>,
+    payload: Result<Json<RefreshIndexRequest>, JsonRejection>,
+) -> Result<Json<RefreshIndexResponse>, ApiError> {
+
+    let Json(payload) = payload?;
+    let search_index = search_index
+        .ok_or_else(|| ApiError::not_found("The bitmap index is not enabled (set BITMAP_INDEX=1)."))?;
+
+    let refresh = match (payload.full, payload.gallery_ids) {
+        (true, Some(_)) => return Err(ApiError::invalid_request("'full' cannot be combined with 'gallery_ids'.")),
+        (true, None) => Refresh::Full,
+        (false, Some(gallery_ids)) => Refresh::Galleries(gallery_ids),
+        (false, None) => Refresh::Incremental,
+    };
+
+    let (stats, elapsed_ms) = search_index.refresh(&db, refresh)
+        .await
+        .map_err(|err| ApiError::db_error("Failed to refresh the bitmap index", &err))?;
+
+    Ok(Json(RefreshIndexResponse { stats, elapsed_ms }))
+}
@@ -0,0 +1,61 @@
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    response::Response,
+    Json
+};
+use crate::api::error::ApiError;
+use crate::api::perform_sql::{stream_gallery_ids, stream_id_list, StreamOptions};
+use crate::api::state::{FbsDatabase, SearchIndex};
+use crate::domain::cursor::{format_date_key, parse_date_key};
+use crate::domain::dto::{OutputFormat, SearchMode, SearchRequest};
+use crate::search::{compiler, parser, title};
+use sea_orm::DatabaseConnection;
+
+pub async fn search(
+    State(db): State<DatabaseConnection>,
+    State(FbsDatabase(fbs_db)): State<FbsDatabase>,
+    State(SearchIndex(search_index)): State<SearchIndex>,
+    payload: Result<Json<SearchRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+
+    let Json(payload) = payload?;
+    payload.validate()?;
+    let keyset = payload.keyset()?;
+
+    let stmt = match payload.mode {
+        SearchMode::Query => {
+            let query = parser::parse(&payload.query)?;
+
+            // ビットマップインデックスで評価できる場合は DB に問い合わせない (SQL と同じく新しい順)
+            let after = keyset.as_ref()
+                .and_then(|keyset| keyset.after.as_ref())
+                .and_then(|cursor| Some((parse_date_key(&cursor.key)?, cursor.gallery_id as u32)));
+            let page = search_index.as_ref()
+                .and_then(|index| index.search(&query, after, payload.offset as usize, payload.limit as usize));
+            if let Some(page) = page {
+                let next_cursor = keyset.as_ref().zip(page.last()).and_then(|(keyset, (date, gallery_id))| {
+                    Some(keyset.next_cursor(format_date_key(*date)?, *gallery_id as i32))
+                });
+                let ids = page.into_iter().map(|(_, gallery_id)| gallery_id as i32).collect();
+                return stream_id_list(fbs_db, ids, payload.batch_size as usize, payload.hydrate, next_cursor);
+            }
+
+            match &keyset {
+                Some(keyset) => compiler::build_search_keyset_statement(&query, keyset, payload.limit),
+                None => compiler::build_search_statement(&query, payload.limit, payload.offset),
+            }
+        }
+        SearchMode::Title => title::build_title_search_statement(&payload.query, payload.fuzzy, payload.limit, payload.offset),
+    };
+
+    let options = StreamOptions {
+        batch_size: payload.batch_size as usize,
+        hydrate: payload.hydrate,
+        statement_timeout: payload.statement_timeout(),
+        keyset,
+        settings: title::local_settings(payload.mode == SearchMode::Title && payload.fuzzy),
+        format: OutputFormat::Framed,
+    };
+
+    stream_gallery_ids(db, fbs_db, stmt, options).await
+}
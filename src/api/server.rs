@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use axum::{routing::{get, post}, Router};
+use sea_orm::{ConnectOptions, Database};
+use std::env;
+use std::sync::Arc;
+use crate::api::perform_sql::perform_sql;
+use crate::api::get_gallery::get_gallery;
+use crate::api::get_fbs_gallery::get_fbs_gallery;
+use crate::api::search::search;
+use crate::api::suggest::suggest;
+use crate::api::facets::facets;
+use crate::api::refresh_index::refresh_index;
+use crate::api::sets::sets;
+use crate::api::state::{AppState, FbsDatabase, SearchIndex};
+use crate::search::bitmap_index::SharedBitmapIndex;
+
+/// サーバーの起動設定
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub port: u16,
+    pub database_url: String,
+    /// fbs_galleries が別 DB にある場合に指定する
+    pub database_url_binary: Option<String>,
+    /// 起動時にビットマップインデックスを構築し、検索に使う
+    pub bitmap_index: bool,
+}
+
+impl ServerConfig {
+    /// SERVER_PORT / DATABASE_URL / DATABASE_URL_BINARY / BITMAP_INDEX から読む
+    pub fn from_env() -> Result<Self> {
+        let port = env::var("SERVER_PORT").context("Failed to get SERVER_PORT")?;
+        Ok(Self {
+            port: port.parse().with_context(|| format!("Invalid SERVER_PORT: {}", port))?,
+            database_url: env::var("DATABASE_URL").context("DATABASE_URL is not set")?,
+            database_url_binary: env::var("DATABASE_URL_BINARY").ok(),
+            bitmap_index: matches!(env::var("BITMAP_INDEX").as_deref(), Ok("1") | Ok("true")),
+        })
+    }
+}
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(|| async { "Hello, World!" }))
+        .route("/sql", post(perform_sql))
+        .route("/search", post(search))
+        .route("/suggest", get(suggest))
+        .route("/facets", post(facets))
+        .route("/sets", post(sets))
+        .route("/index/refresh", post(refresh_index))
+        .route("/galleries/{gallery_id}", get(get_gallery))
+        .route("/fbs/{gallery_id}", get(get_fbs_gallery))
+        .with_state(state)
+}
+
+pub async fn serve(config: ServerConfig) -> Result<()> {
+    let mut opt = ConnectOptions::new(&config.database_url).to_owned();
+    opt.max_connections(100);
+    let db = Database::connect(opt).await.context("Failed to connect to database")?;
+
+    let fbs_db = match config.database_url_binary {
+        Some(database_url_binary) if database_url_binary != config.database_url => {
+            let mut opt = ConnectOptions::new(&database_url_binary).to_owned();
+            opt.max_connections(100);
+            Database::connect(opt).await.context("Failed to connect to binary database")?
+        }
+        _ => db.clone(),
+    };
+
+    let search_index = if config.bitmap_index {
+        let index = SharedBitmapIndex::build(&db).await.context("Failed to build bitmap index")?;
+        let stats = index.stats();
+        println!("Bitmap index built: {} galleries, {} terms", stats.galleries, stats.terms);
+        SearchIndex(Some(Arc::new(index)))
+    } else {
+        SearchIndex(None)
+    };
+
+    let state = AppState { db, fbs_db: FbsDatabase(fbs_db), search_index };
+
+    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", config.port)).await
+        .with_context(|| format!("Failed to bind port {}", config.port))?;
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::StatusCode,
+    response::Response,
+    Json
+};
+use futures::StreamExt;
+use roaring::RoaringBitmap;
+use crate::api::error::{ApiError, ErrorCode};
+use crate::api::perform_sql::{begin_read_only, is_only_gallery_id_returned, stream_id_list};
+use crate::api::sql_validator::validate_read_only_query;
+use crate::api::state::{FbsDatabase, SearchIndex};
+use crate::domain::dto::{SQLRequest, SetsRequest, MAX_SET_OPERAND_ROWS};
+use crate::domain::set_expr::SetOperand;
+use crate::search::bitmap_index::SharedBitmapIndex;
+use crate::search::parser::{self, Namespace, Query};
+use crate::search::compiler;
+use sea_orm::{DatabaseConnection, DbBackend, StreamTrait, Statement, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 式木の葉をそれぞれ gallery_id の集合として評価し、ビットマップ演算で組み合わせて返す
+///
+/// 結果は `/search` のビットマップ評価と同じく gallery_id の降順。
+/// 葉が MAX_SET_OPERAND_ROWS 件を超える場合は読み込みを止めてエラーにする。
+pub async fn sets(
+    State(db): State<DatabaseConnection>,
+    State(FbsDatabase(fbs_db)): State<FbsDatabase>,
+    State(SearchIndex(search_index)): State<SearchIndex>,
+    payload: Result<Json<SetsRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+
+    let Json(payload) = payload?;
+    payload.validate()?;
+
+    // 葉は互いに独立しているので並行に評価する
+    let operands = payload.expr.operands();
+    let statement_timeout = payload.statement_timeout();
+    let results = futures::future::try_join_all(
+        operands.iter().map(|operand| evaluate_operand(&db, search_index.as_ref(), *operand, statement_timeout))
+    ).await?;
+
+    let universe = match payload.expr.needs_universe() {
+        true => Some(load_universe(&db, search_index.as_ref(), statement_timeout).await?),
+        false => None,
+    };
+
+    let ids = payload.expr.combine(&results, universe.as_ref())
+        .iter()
+        .rev()
+        .skip(payload.offset as usize)
+        .take(payload.limit as usize)
+        .map(|gallery_id| gallery_id as i32)
+        .collect();
+
+    stream_id_list(fbs_db, ids, payload.batch_size as usize, payload.hydrate, None)
+}
+
+async fn evaluate_operand(
+    db: &DatabaseConnection,
+    search_index: Option<&Arc<SharedBitmapIndex>>,
+    operand: SetOperand<'_>,
+    statement_timeout: Duration,
+) -> Result<RoaringBitmap, ApiError> {
+    match operand {
+        SetOperand::Sql(request) => evaluate_sql(db, request).await,
+        SetOperand::Term(term) => {
+            let query = parser::parse(term)?;
+            if !matches!(&query, Query::Term(term) if term.namespace != Namespace::Title) {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    ErrorCode::InvalidSearchQuery,
+                    format!("'{}' is not a single namespace:value term.", term),
+                ));
+            }
+
+            if let Some(bitmap) = search_index.and_then(|index| index.evaluate(&query)) {
+                return match bitmap.len() > MAX_SET_OPERAND_ROWS {
+                    true => Err(too_many_rows()),
+                    false => Ok(bitmap),
+                };
+            }
+            let mut values = Vec::new();
+            let condition = compiler::compile_condition(&query, &mut values);
+            let sql = format!("SELECT g.gallery_id FROM galleries g WHERE {}", condition);
+            let stmt = Statement::from_sql_and_values(DbBackend::Postgres, sql, values);
+            collect_gallery_ids(db, stmt, statement_timeout, Some(MAX_SET_OPERAND_ROWS)).await
+        }
+    }
+}
+
+/// `/sql` と同じ検証をしてから実行する
+async fn evaluate_sql(db: &DatabaseConnection, request: &SQLRequest) -> Result<RoaringBitmap, ApiError> {
+    let sql = request.build_paginated_query()?;
+    validate_read_only_query(&sql)?;
+    if !is_only_gallery_id_returned(&sql) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::ForbiddenProjection,
+            "SQL must return only 'gallery_id' column.",
+        ));
+    }
+    collect_gallery_ids(db, Statement::from_string(DbBackend::Postgres, sql), request.statement_timeout(), Some(MAX_SET_OPERAND_ROWS)).await
+}
+
+async fn load_universe(
+    db: &DatabaseConnection,
+    search_index: Option<&Arc<SharedBitmapIndex>>,
+    statement_timeout: Duration,
+) -> Result<RoaringBitmap, ApiError> {
+    match search_index {
+        Some(index) => Ok(index.all()),
+        None => {
+            // 全ギャラリーは葉ではないので件数の上限を設けない
+            let stmt = Statement::from_sql_and_values(DbBackend::Postgres, "SELECT gallery_id FROM galleries", Vec::<Value>::new());
+            collect_gallery_ids(db, stmt, statement_timeout, None).await
+        }
+    }
+}
+
+/// `max_rows` を超えたら読み込みを止めてエラーにする
+async fn collect_gallery_ids(
+    db: &DatabaseConnection,
+    stmt: Statement,
+    statement_timeout: Duration,
+    max_rows: Option<u64>,
+) -> Result<RoaringBitmap, ApiError> {
+    let txn = begin_read_only(db, statement_timeout)
+        .await
+        .map_err(|err| ApiError::db_error("Failed to start transaction", &err))?;
+
+    let mut bitmap = RoaringBitmap::new();
+    {
+        let mut rows = txn.stream_raw(stmt)
+            .await
+            .map_err(|err| ApiError::db_error("Query execution error", &err))?;
+        while let Some(row) = rows.next().await {
+            let gallery_id = row
+                .and_then(|row| row.try_get::<i32>("", "gallery_id"))
+                .map_err(|err| ApiError::db_error("Row fetch error", &err))?;
+            bitmap.insert(gallery_id as u32);
+            if max_rows.is_some_and(|max_rows| bitmap.len() > max_rows) {
+                return Err(too_many_rows());
+            }
+        }
+    }
+    txn.rollback().await.ok();
+    Ok(bitmap)
+}
+
+fn too_many_rows() -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        ErrorCode::TooManyRows,
+        format!("A sub-query matched more than {} galleries.", MAX_SET_OPERAND_ROWS),
+    )
+}
@@ -0,0 +1,213 @@
+use std::ops::ControlFlow;
+use sqlparser::ast::{Expr, ObjectName, Query, SetExpr, Statement, Visit, Visitor};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use crate::api::error::ApiError;
+
+/// `/sql` で参照を許可するテーブル
+pub const ALLOWED_TABLES: &[&str] = &[
+    "galleries",
+    "languages",
+    "tags",
+    "artists",
+    "groups",
+    "characters",
+    "parodies",
+    "gallery_tags",
+    "gallery_artists",
+    "gallery_groups",
+    "gallery_characters",
+    "gallery_parodies",
+    "nozomi_indexes",
+];
+
+/// `/sql` で呼び出しを許可する関数 (副作用がなく、すぐに終わるもののみ)
+pub const ALLOWED_FUNCTIONS: &[&str] = &[
+    "count", "sum", "min", "max", "avg",
+    "bool_and", "bool_or", "every", "array_agg",
+    "lower", "upper", "length", "char_length", "trim", "btrim", "ltrim", "rtrim",
+    "substring", "substr", "replace", "position", "strpos", "starts_with", "concat",
+    "coalesce", "nullif", "greatest", "least", "abs",
+    "array_length", "array_position", "cardinality", "unnest",
+    "jsonb_array_length",
+    "date_trunc", "date_part", "to_char", "now",
+];
+
+/// SQL が副作用のない単一の SELECT であることを検証する
+///
+/// - 文はちょうど1つで、SELECT (クエリ) であること
+/// - データ変更を含む CTE、`SELECT ... INTO`、`FOR UPDATE` などのロックを含まないこと
+/// - 参照するテーブルと関数が許可リストに含まれていること
+pub fn validate_read_only_query(sql: &str) -> Result<(), ApiError> {
+    let dialect = PostgreSqlDialect {};
+    let statements = Parser::parse_sql(&dialect, sql)
+        .map_err(|err| ApiError::invalid_sql(&err))?;
+
+    if statements.len() != 1 {
+        return Err(ApiError::forbidden_statement(format!(
+            "Exactly one statement is allowed, but {} were given.", statements.len()
+        )));
+    }
+
+    let mut validator = ReadOnlyValidator { scopes: Vec::new() };
+    match statements.visit(&mut validator) {
+        ControlFlow::Continue(()) => Ok(()),
+        ControlFlow::Break(message) => Err(ApiError::forbidden_statement(message)),
+    }
+}
+
+/// 走査中のクエリ1つ分の CTE の状態
+///
+/// CTE の名前はそのクエリの中でしか参照できない。RECURSIVE でない WITH では、
+/// 各 CTE からはそれより前に定義された CTE しか見えない (後の名前は実テーブルを指す)。
+/// WITH の定義はクエリの中で最初に走査されるので、直下のクエリを `defined` 個
+/// 読み終えた時点で先頭から `defined` 個の CTE が定義済みになる。
+struct QueryScope {
+    /// WITH で定義された CTE の名前 (定義順)
+    ctes: Vec<String>,
+    recursive: bool,
+    /// 読み終えた直下のクエリの数
+    defined: usize,
+}
+
+impl QueryScope {
+    fn visible(&self) -> &[String] {
+        if self.recursive {
+            &self.ctes
+        } else {
+            &self.ctes[..self.defined.min(self.ctes.len())]
+        }
+    }
+}
+
+/// 走査中のクエリをネストの深さ順に積んだスタックを持つ
+struct ReadOnlyValidator {
+    scopes: Vec<QueryScope>,
+}
+
+impl ReadOnlyValidator {
+    fn is_cte(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.visible().iter().any(|visible| visible == name))
+    }
+
+    /// `name` または `public.name` が許可されたテーブルか、参照できる CTE かを判定する
+    fn check_table(&self, schema: Option<&str>, name: &str, display: &dyn std::fmt::Display) -> ControlFlow<String> {
+        let name = name.to_lowercase();
+        let allowed = match schema.map(str::to_lowercase).as_deref() {
+            // スキーマを付けた名前は CTE を指さない
+            Some("public") => ALLOWED_TABLES.contains(&name.as_str()),
+            Some(_) => false,
+            None => ALLOWED_TABLES.contains(&name.as_str()) || self.is_cte(&name),
+        };
+        if allowed {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(format!("Table '{}' is not allowed.", display))
+        }
+    }
+
+    /// `TABLE name` は ObjectName を持たず `pre_visit_relation` を通らないので、ここで検証する
+    fn check_table_exprs(&self, body: &SetExpr) -> ControlFlow<String> {
+        match body {
+            SetExpr::Table(table) => match &table.table_name {
+                Some(name) => self.check_table(table.schema_name.as_deref(), name, table),
+                None => ControlFlow::Break(format!("Table '{}' is not allowed.", table)),
+            },
+            SetExpr::SetOperation { left, right, .. } => {
+                self.check_table_exprs(left)?;
+                self.check_table_exprs(right)
+            }
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+impl Visitor for ReadOnlyValidator {
+    type Break = String;
+
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<Self::Break> {
+        // CTE 内の INSERT / UPDATE / DELETE もここで弾かれる
+        match statement {
+            Statement::Query(_) => ControlFlow::Continue(()),
+            _ => ControlFlow::Break("Only SELECT statements are allowed.".to_string()),
+        }
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        if !query.locks.is_empty() {
+            return ControlFlow::Break("Locking clauses (FOR UPDATE / FOR SHARE) are not allowed.".to_string());
+        }
+        check_set_expr(&query.body)?;
+
+        let scope = match &query.with {
+            Some(with) => QueryScope {
+                ctes: with.cte_tables.iter().map(|cte| cte.alias.name.value.to_lowercase()).collect(),
+                recursive: with.recursive,
+                defined: 0,
+            },
+            None => QueryScope { ctes: Vec::new(), recursive: false, defined: 0 },
+        };
+        self.scopes.push(scope);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        // 本体の TABLE 句からは、このクエリの CTE がすべて見える
+        self.check_table_exprs(&query.body)?;
+        self.scopes.pop();
+        // 親のクエリの CTE の定義を読み終えたら、後に続く CTE と本体から参照できるようにする
+        if let Some(parent) = self.scopes.last_mut() {
+            parent.defined += 1;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<Self::Break> {
+        let parts: Option<Vec<&str>> = relation.0.iter()
+            .map(|part| part.as_ident().map(|ident| ident.value.as_str()))
+            .collect();
+        match parts.as_deref() {
+            Some([name]) => self.check_table(None, name, relation),
+            Some([schema, name]) => self.check_table(Some(schema), name, relation),
+            _ => ControlFlow::Break(format!("Table '{}' is not allowed.", relation)),
+        }
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        if let Expr::Function(function) = expr {
+            let name = relation_name(&function.name);
+            if !name.as_deref().is_some_and(|name| ALLOWED_FUNCTIONS.contains(&name)) {
+                return ControlFlow::Break(format!("Function '{}' is not allowed.", function.name));
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn check_set_expr(body: &SetExpr) -> ControlFlow<String> {
+    match body {
+        SetExpr::Select(select) if select.into.is_some() => {
+            ControlFlow::Break("SELECT ... INTO is not allowed.".to_string())
+        }
+        SetExpr::SetOperation { left, right, .. } => {
+            check_set_expr(left)?;
+            check_set_expr(right)
+        }
+        SetExpr::Insert(_) | SetExpr::Update(_) | SetExpr::Delete(_) | SetExpr::Merge(_) => {
+            ControlFlow::Break("Data-modifying statements are not allowed.".to_string())
+        }
+        _ => ControlFlow::Continue(()),
+    }
+}
+
+/// `name` または `public.name` を小文字の名前に変換する (それ以外のスキーマは None)
+fn relation_name(name: &ObjectName) -> Option<String> {
+    let parts: Vec<String> = name.0.iter()
+        .map(|part| part.as_ident().map(|ident| ident.value.to_lowercase()))
+        .collect::<Option<_>>()?;
+    match parts.as_slice() {
+        [name] => Some(name.clone()),
+        [schema, name] if schema == "public" => Some(name.clone()),
+        _ => None,
+    }
+}
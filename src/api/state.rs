@@ -0,0 +1,38 @@
+use axum::extract::FromRef;
+use sea_orm::DatabaseConnection;
+use std::sync::Arc;
+use crate::search::bitmap_index::SharedBitmapIndex;
+
+/// FlatBuffers 用 DB (fbs_galleries) への接続
+/// リレーショナル DB と区別するために newtype にしている
+#[derive(Clone)]
+pub struct FbsDatabase(pub DatabaseConnection);
+
+/// メモリ上のビットマップインデックス (BITMAP_INDEX で有効にしない場合は None)
+#[derive(Clone, Default)]
+pub struct SearchIndex(pub Option<Arc<SharedBitmapIndex>>);
+
+#[derive(Clone)]
+pub struct AppState {
+    pub db: DatabaseConnection,
+    pub fbs_db: FbsDatabase,
+    pub search_index: SearchIndex,
+}
+
+impl FromRef<AppState> for DatabaseConnection {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
+impl FromRef<AppState> for FbsDatabase {
+    fn from_ref(state: &AppState) -> Self {
+        state.fbs_db.clone()
+    }
+}
+
+impl FromRef<AppState> for SearchIndex {
+    fn from_ref(state: &AppState) -> Self {
+        state.search_index.clone()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,23 @@
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    Json
+};
+use crate::api::error::ApiError;
+use crate::domain::dto::{SuggestRequest, Suggestion};
+use crate::mapper::suggest_mapper;
+use sea_orm::DatabaseConnection;
+
+pub async fn suggest(
+    State(db): State<DatabaseConnection>,
+    params: Result<Query<SuggestRequest>, QueryRejection>,
+) -> Result<Json<Vec<Suggestion>>, ApiError> {
+
+    let Query(params) = params?;
+    let namespace = params.validate()?;
+
+    let suggestions = suggest_mapper::find_suggestions(&db, namespace, &params.value(), params.limit)
+        .await
+        .map_err(|err| ApiError::db_error("Failed to fetch suggestions", &err))?;
+
+    Ok(Json(suggestions))
+}
@@ -0,0 +1,47 @@
+use hitomi_server_rs::nozomi::export;
+use anyhow::{Context, Result};
+use sea_orm::{ConnectOptions, Database};
+use std::env;
+use std::path::Path;
+use std::time::Instant;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // 1. DATABASE_URL を取得
+    let database_url = env::var("DATABASE_URL")
+        .with_context(|| "DATABASE_URL is not set")?;
+
+    // 2. DB接続
+    let mut opt = ConnectOptions::new(&database_url).to_owned();
+    opt.max_connections(5);
+    opt.connect_timeout(std::time::Duration::from_secs(10));
+    opt.acquire_timeout(std::time::Duration::from_secs(10));
+    opt.set_schema_search_path("public");
+    let db = Database::connect(opt).await
+        .context("Failed to connect to database")?;
+
+    println!("Connected to database");
+
+    // 3. 引数: [出力ディレクトリ] [--full]
+    let args: Vec<String> = env::args().skip(1).collect();
+    let full = args.iter().any(|arg| arg == "--full");
+    let out_dir = args.iter()
+        .find(|arg| !arg.starts_with("--"))
+        .map(String::as_str)
+        .unwrap_or("data/nozomi/");
+    let out_dir = Path::new(out_dir);
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create directory: {:?}", out_dir))?;
+
+    println!("Exporting to: {:?} ({})", out_dir, if full { "full" } else { "incremental" });
+    let started = Instant::now();
+    let stats = export::export(&db, out_dir, full).await
+        .context("Failed to export nozomi indexes")?;
+
+    println!(
+        "Export completed: {} galleries ({} changed), {} files written, {} files removed in {:?}",
+        stats.galleries, stats.changed, stats.written, stats.removed, started.elapsed()
+    );
+
+    Ok(())
+}
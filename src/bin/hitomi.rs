@@ -0,0 +1,292 @@
+use hitomi_server_rs::api::server::{self, ServerConfig};
+use hitomi_server_rs::import::{ImportMode, Loader};
+use hitomi_server_rs::import::dead_letter::{DeadLetterWriter, Stage, DEFAULT_DEAD_LETTER_DIR};
+use hitomi_server_rs::pipeline::{self, analyze, bench, import_fbs, normalize, resp_to_json, ImportOptions};
+use hitomi_server_rs::pipeline::compression::Compression;
+use hitomi_server_rs::pipeline::ingest::{self, IngestDeadLetters};
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// スクレイピング結果の変換・DB への取り込み・API サーバー
+#[derive(Parser)]
+#[command(name = "hitomi", version)]
+struct Cli {
+    /// 処理できなかったレコードの出力先
+    #[arg(long, global = true, env = "DEAD_LETTER_DIR", default_value = DEFAULT_DEAD_LETTER_DIR)]
+    dead_letter_dir: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// RESP (スクレイピング結果) を raw JSON に変換する
+    RespToJson {
+        /// 入力ファイル、ディレクトリ、または glob パターン
+        #[arg(short, long, num_args = 1.., default_value = pipeline::RESP_JSON_DIR)]
+        input: Vec<String>,
+        /// 出力ディレクトリ
+        #[arg(short, long, default_value = pipeline::RAW_JSON_DIR)]
+        output: PathBuf,
+        /// 出力の圧縮形式 (none / zstd / gzip)
+        #[arg(long, default_value = "none")]
+        compress: Compression,
+        /// 出力済みのファイルも上書きする
+        #[arg(long)]
+        force: bool,
+    },
+    /// raw JSON を正規化した Gallery JSON にする
+    Normalize {
+        /// 入力ファイル、ディレクトリ、または glob パターン
+        #[arg(short, long, num_args = 1.., default_value = pipeline::RAW_JSON_DIR)]
+        input: Vec<String>,
+        /// 出力ディレクトリ
+        #[arg(short, long, default_value = pipeline::NORMALIZED_JSON_DIR)]
+        output: PathBuf,
+        /// 出力の圧縮形式 (none / zstd / gzip)
+        #[arg(long, default_value = "none")]
+        compress: Compression,
+        /// 出力済みのファイルも上書きする
+        #[arg(long)]
+        force: bool,
+    },
+    /// raw JSON のスキーマを分析する
+    Analyze {
+        /// 入力ファイル、ディレクトリ、または glob パターン
+        #[arg(short, long, num_args = 1.., default_value = pipeline::RAW_JSON_DIR)]
+        input: Vec<String>,
+        /// ファイルごとのスキーマの出力ディレクトリ
+        #[arg(short, long, default_value = pipeline::SCHEMA_DIR)]
+        output: PathBuf,
+        /// マージしたスキーマの出力先
+        #[arg(long, default_value = pipeline::MERGED_SCHEMA_PATH)]
+        merged: PathBuf,
+        /// 出力済みのファイルも上書きする
+        #[arg(long)]
+        force: bool,
+    },
+    /// Gallery JSON をリレーショナルテーブルに取り込む
+    Import {
+        /// 入力ファイル、ディレクトリ、または glob パターン
+        #[arg(short, long, num_args = 1.., default_value = pipeline::NORMALIZED_JSON_DIR)]
+        input: Vec<String>,
+        #[arg(long, env = "DATABASE_URL")]
+        database_url: String,
+        #[command(flatten)]
+        import: ImportArgs,
+        /// 取り込み後に参照されなくなったタグなどを削除する
+        #[arg(long)]
+        sweep: bool,
+    },
+    /// Gallery JSON を fbs_galleries に取り込む
+    ImportFbs {
+        /// 入力ファイル、ディレクトリ、または glob パターン
+        #[arg(short, long, num_args = 1.., default_value = pipeline::NORMALIZED_JSON_DIR)]
+        input: Vec<String>,
+        #[arg(long, env = "DATABASE_URL_BINARY")]
+        database_url: String,
+        #[command(flatten)]
+        import: ImportArgs,
+    },
+    /// RESP を中間ファイル無しでリレーショナルテーブルと fbs_galleries に取り込む
+    Ingest {
+        /// RESP の入力ファイル、ディレクトリ、または glob パターン
+        #[arg(short, long, num_args = 1.., default_value = pipeline::RESP_JSON_DIR)]
+        input: Vec<String>,
+        #[arg(long, env = "DATABASE_URL")]
+        database_url: String,
+        /// fbs_galleries が別 DB にある場合に指定する (省略時は DATABASE_URL)
+        #[arg(long, env = "DATABASE_URL_BINARY")]
+        database_url_binary: Option<String>,
+        /// fbs_galleries には取り込まない
+        #[arg(long)]
+        no_fbs: bool,
+        #[command(flatten)]
+        import: ImportArgs,
+        /// 取り込み後に参照されなくなったタグなどを削除する
+        #[arg(long)]
+        sweep: bool,
+    },
+    /// insert と COPY で取り込みにかかる時間を比べる (計測のたびにテーブルを作り直す)
+    BenchImport {
+        /// 入力ファイル、ディレクトリ、または glob パターン
+        #[arg(short, long, num_args = 1.., default_value = pipeline::NORMALIZED_JSON_DIR)]
+        input: Vec<String>,
+        #[arg(long, env = "DATABASE_URL")]
+        database_url: String,
+        /// fbs_galleries が別 DB にある場合に指定する (省略時は DATABASE_URL)
+        #[arg(long, env = "DATABASE_URL_BINARY")]
+        database_url_binary: Option<String>,
+        /// fbs_galleries への取り込みは計測しない
+        #[arg(long)]
+        no_fbs: bool,
+    },
+    /// サジェストのギャラリー数を集計し直す (import / ingest は最後に自動で行う)
+    RefreshSuggest {
+        #[arg(long, env = "DATABASE_URL")]
+        database_url: String,
+        /// 集計の前に参照されなくなったタグなどを削除する
+        #[arg(long)]
+        sweep: bool,
+    },
+    /// API サーバーを起動する
+    Serve {
+        #[arg(long, env = "SERVER_PORT")]
+        port: u16,
+        #[arg(long, env = "DATABASE_URL")]
+        database_url: String,
+        /// fbs_galleries が別 DB にある場合に指定する
+        #[arg(long, env = "DATABASE_URL_BINARY")]
+        database_url_binary: Option<String>,
+        /// 起動時にビットマップインデックスを構築し、検索に使う
+        #[arg(long, env = "BITMAP_INDEX")]
+        bitmap_index: bool,
+    },
+    /// resp-to-json → normalize → analyze → import (→ import-fbs) を順に実行する
+    Pipeline {
+        /// RESP の入力ファイル、ディレクトリ、または glob パターン
+        #[arg(short, long, num_args = 1.., default_value = pipeline::RESP_JSON_DIR)]
+        input: Vec<String>,
+        #[arg(long, default_value = pipeline::RAW_JSON_DIR)]
+        raw_dir: PathBuf,
+        #[arg(long, default_value = pipeline::NORMALIZED_JSON_DIR)]
+        normalized_dir: PathBuf,
+        #[arg(long, default_value = pipeline::SCHEMA_DIR)]
+        schema_dir: PathBuf,
+        #[arg(long, default_value = pipeline::MERGED_SCHEMA_PATH)]
+        merged_schema: PathBuf,
+        /// 出力の圧縮形式 (none / zstd / gzip)
+        #[arg(long, default_value = "none")]
+        compress: Compression,
+        #[arg(long, env = "DATABASE_URL")]
+        database_url: String,
+        /// 指定した場合は fbs_galleries にも取り込む
+        #[arg(long, env = "DATABASE_URL_BINARY")]
+        database_url_binary: Option<String>,
+        #[command(flatten)]
+        import: ImportArgs,
+        /// 取り込み後に参照されなくなったタグなどを削除する
+        #[arg(long)]
+        sweep: bool,
+        /// 出力済みのファイルも上書きする
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// 既存のデータがある DB への取り込み方
+    #[arg(long, default_value = "upsert")]
+    mode: ImportMode,
+    /// チェックポイントを無視して最初から取り込む
+    #[arg(long)]
+    restart: bool,
+    /// バッチの書き込み方 (insert / copy)
+    #[arg(long, default_value = "insert")]
+    loader: Loader,
+}
+
+impl ImportArgs {
+    fn options(&self) -> ImportOptions {
+        ImportOptions { mode: self.mode, restart: self.restart, loader: self.loader }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let dead_letter_dir = cli.dead_letter_dir;
+
+    match cli.command {
+        Command::RespToJson { input, output, compress, force } => {
+            let inputs = pipeline::resolve_inputs(&input, "jsonl")?;
+            resp_to_json::run(&inputs, &output, compress, force, &DeadLetterWriter::in_dir(&dead_letter_dir, Stage::RespToJson))
+        }
+        Command::Normalize { input, output, compress, force } => {
+            let inputs = pipeline::resolve_inputs(&input, "jsonl")?;
+            normalize::run(&inputs, &output, compress, force, &DeadLetterWriter::in_dir(&dead_letter_dir, Stage::NormalizeJson))
+        }
+        Command::Analyze { input, output, merged, force } => {
+            let inputs = pipeline::resolve_inputs(&input, "jsonl")?;
+            analyze::run(&inputs, &output, &merged, force)
+        }
+        Command::Import { input, database_url, import, sweep } => {
+            let inputs = pipeline::resolve_inputs(&input, "json")?;
+            let db = pipeline::connect(&database_url, 20).await?;
+            let dead_letters = DeadLetterWriter::in_dir(&dead_letter_dir, Stage::ImportToDb);
+            pipeline::import::run(&db, &inputs, import.options(), sweep, &dead_letters).await?;
+            Ok(())
+        }
+        Command::ImportFbs { input, database_url, import } => {
+            let inputs = pipeline::resolve_inputs(&input, "json")?;
+            let db = pipeline::connect(&database_url, 20).await?;
+            let dead_letters = Arc::new(DeadLetterWriter::in_dir(&dead_letter_dir, Stage::ImportBinToDb));
+            import_fbs::run(&db, &inputs, import.options(), dead_letters).await?;
+            Ok(())
+        }
+        Command::Ingest { input, database_url, database_url_binary, no_fbs, import, sweep } => {
+            let inputs = pipeline::resolve_inputs(&input, "jsonl")?;
+            let db = pipeline::connect(&database_url, 20).await?;
+            let fbs_db = match database_url_binary {
+                _ if no_fbs => None,
+                Some(database_url_binary) if database_url_binary != database_url => Some(pipeline::connect(&database_url_binary, 20).await?),
+                _ => Some(db.clone()),
+            };
+            let dead_letters = Arc::new(IngestDeadLetters::in_dir(&dead_letter_dir));
+            ingest::run(&db, fbs_db.as_ref(), &inputs, import.options(), sweep, dead_letters).await
+        }
+        Command::BenchImport { input, database_url, database_url_binary, no_fbs } => {
+            let inputs = pipeline::resolve_inputs(&input, "json")?;
+            let db = pipeline::connect(&database_url, 20).await?;
+            let fbs_db = match database_url_binary {
+                _ if no_fbs => None,
+                Some(database_url_binary) if database_url_binary != database_url => Some(pipeline::connect(&database_url_binary, 20).await?),
+                _ => Some(db.clone()),
+            };
+            bench::run(&db, fbs_db.as_ref(), &inputs, &dead_letter_dir).await?;
+            Ok(())
+        }
+        Command::RefreshSuggest { database_url, sweep } => {
+            let db = pipeline::connect(&database_url, 5).await?;
+            let result = if sweep { pipeline::import::sweep_orphaned_entities(&db).await } else { Ok(()) };
+            pipeline::import::refresh_suggest_entries(&db, result).await
+        }
+        Command::Serve { port, database_url, database_url_binary, bitmap_index } => {
+            server::serve(ServerConfig { port, database_url, database_url_binary, bitmap_index }).await
+        }
+        Command::Pipeline {
+            input, raw_dir, normalized_dir, schema_dir, merged_schema, compress,
+            database_url, database_url_binary, import, sweep, force,
+        } => {
+            println!("== resp-to-json ==");
+            let inputs = pipeline::resolve_inputs(&input, "jsonl")?;
+            resp_to_json::run(&inputs, &raw_dir, compress, force, &DeadLetterWriter::in_dir(&dead_letter_dir, Stage::RespToJson))?;
+
+            // 後の段階は前の段階の出力ディレクトリを読む
+            println!("== normalize ==");
+            let raw_inputs = pipeline::resolve_inputs(&[raw_dir.to_string_lossy().into_owned()], "jsonl")?;
+            normalize::run(&raw_inputs, &normalized_dir, compress, force, &DeadLetterWriter::in_dir(&dead_letter_dir, Stage::NormalizeJson))?;
+
+            println!("== analyze ==");
+            analyze::run(&raw_inputs, &schema_dir, &merged_schema, force)?;
+
+            println!("== import ==");
+            let normalized_inputs = pipeline::resolve_inputs(&[normalized_dir.to_string_lossy().into_owned()], "json")?;
+            let db = pipeline::connect(&database_url, 20).await?;
+            let dead_letters = DeadLetterWriter::in_dir(&dead_letter_dir, Stage::ImportToDb);
+            pipeline::import::run(&db, &normalized_inputs, import.options(), sweep, &dead_letters).await?;
+
+            if let Some(database_url_binary) = database_url_binary {
+                println!("== import-fbs ==");
+                let fbs_db = pipeline::connect(&database_url_binary, 20).await?;
+                let dead_letters = Arc::new(DeadLetterWriter::in_dir(&dead_letter_dir, Stage::ImportBinToDb));
+                import_fbs::run(&fbs_db, &normalized_inputs, import.options(), dead_letters).await?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use hitomi_server_rs::nozomi;
+use anyhow::{Context, Result};
+use sea_orm::{ConnectOptions, Database, ConnectionTrait, Statement};
+use std::env;
+use std::path::{Path, PathBuf};
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// .nozomi ファイルを nozomi_indexes に取り込む
+///
+/// nozomi_indexes だけを書き、タグなどの関連テーブルは変えないので、サジェストのギャラリー数は集計し直さない。
+#[tokio::main]
+async fn main() -> Result<()> {
+    // 1. DATABASE_URL を取得
+    let database_url = env::var("DATABASE_URL")
+        .with_context(|| "DATABASE_URL is not set")?;
+
+    // 2. DB接続
+    let mut opt = ConnectOptions::new(&database_url).to_owned();
+    opt.max_connections(20);
+    opt.connect_timeout(std::time::Duration::from_secs(10));
+    opt.acquire_timeout(std::time::Duration::from_secs(10));
+    opt.set_schema_search_path("public");
+    let db = Database::connect(opt).await
+        .context("Failed to connect to database")?;
+
+    println!("Connected to database");
+
+    // 2.5. テーブル作成 (既存のインデックスは上書きするので削除しない)
+    println!("Creating tables...");
+    create_tables(&db).await?;
+    println!("Tables created successfully");
+
+    // 3. .nozomi ファイルを集める
+    let args: Vec<String> = env::args().collect();
+    let root = PathBuf::from(args.get(1).map(String::as_str).unwrap_or("data/nozomi/"));
+    let mut files = Vec::new();
+    if root.is_file() {
+        files.push(root.clone());
+    } else {
+        collect_nozomi_files(&root, &mut files)?;
+    }
+
+    // ファイル名順にソート
+    files.sort();
+
+    let total_size: u64 = files.iter()
+        .map(|path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+        .sum();
+    println!("Reading {} files from: {:?}", files.len(), root);
+
+    // プログレスバーの設定
+    let pb = ProgressBar::new(total_size);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+        .unwrap()
+        .progress_chars("#>-"));
+
+    // 順次処理 (1ファイル = 1行)
+    let name_root = if root.is_file() { root.parent().unwrap_or(Path::new("")) } else { root.as_path() };
+    for path in files {
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if let Err(e) = import_nozomi_file(&db, name_root, &path).await {
+            pb.println(format!("Failed to import {:?}: {:?}", path, e));
+        }
+        pb.inc(size);
+    }
+
+    pb.finish_with_message("All imports completed");
+    println!("Import completed successfully");
+
+    Ok(())
+}
+
+async fn create_tables(db: &sea_orm::DatabaseConnection) -> Result<()> {
+    let schema = include_str!("../../sql/nozomi_schema.sql");
+    let statements: Vec<&str> = schema
+        .split(';')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    for sql in statements {
+        db.execute_raw(Statement::from_string(
+            sea_orm::DbBackend::Postgres,
+            sql.to_string(),
+        ))
+        .await
+        .with_context(|| format!("Failed to execute: {}", sql))?;
+    }
+
+    Ok(())
+}
+
+/// ディレクトリを再帰的に走査して `.nozomi` ファイルを集める
+fn collect_nozomi_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {:?}", dir))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_nozomi_files(&path, files)?;
+        } else if path.extension().and_then(|s| s.to_str()) == Some(nozomi::EXTENSION) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+async fn import_nozomi_file(
+    db: &sea_orm::DatabaseConnection,
+    root: &Path,
+    path: &Path,
+) -> Result<()> {
+    let name = nozomi::index_name(root, path)
+        .with_context(|| format!("Invalid index path: {:?}", path))?;
+    let gallery_ids = nozomi::read_file(path)
+        .with_context(|| format!("Failed to read nozomi file: {:?}", path))?;
+
+    db.execute_raw(Statement::from_sql_and_values(
+        sea_orm::DbBackend::Postgres,
+        "INSERT INTO nozomi_indexes (name, gallery_ids) VALUES ($1, $2) \
+         ON CONFLICT (name) DO UPDATE SET gallery_ids = EXCLUDED.gallery_ids, imported_at = now()",
+        vec![name.into(), gallery_ids.into()],
+    ))
+    .await
+    .with_context(|| format!("Failed to upsert nozomi index: {:?}", path))?;
+
+    Ok(())
+}
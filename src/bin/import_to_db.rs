@@ -1,14 +1,8 @@
-use hitomi_server_rs::domain::gallery::Gallery;
-use hitomi_server_rs::mapper::galleries_mapper;
-use anyhow::{Context, Result};
-use sea_orm::{ConnectOptions, Database, ConnectionTrait, Statement};
+use hitomi_server_rs::import::{self, Loader};
+use hitomi_server_rs::import::dead_letter::{DeadLetterWriter, Stage};
+use hitomi_server_rs::pipeline::{self, ImportOptions};
+use anyhow::{anyhow, Context, Result};
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
-use indicatif::{ProgressBar, ProgressStyle};
-
-const BATCH_SIZE: usize = 500;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -17,176 +11,29 @@ async fn main() -> Result<()> {
         .with_context(|| "DATABASE_URL is not set")?;
 
     // 2. DB接続
-    let mut opt = ConnectOptions::new(&database_url).to_owned();
-    opt.max_connections(20);
-    opt.connect_timeout(std::time::Duration::from_secs(10));
-    opt.acquire_timeout(std::time::Duration::from_secs(10));
-    opt.set_schema_search_path("public");
-    let db = Database::connect(opt).await
-        .context("Failed to connect to database")?;
-
+    let db = pipeline::connect(&database_url, 20).await?;
     println!("Connected to database");
 
-    // 2.4. テーブル削除 (スキーマ変更のため)
-    println!("Dropping tables...");
-    drop_tables(&db).await?;
-
-    // 2.5. テーブル作成
-    println!("Creating tables...");
-    create_tables(&db).await?;
-    println!("Tables created successfully");
-
-    // 3. JSONLファイルパスを取得して処理
-    let args: Vec<String> = env::args().collect();
-
-    if args.len() > 1 {
-        let jsonl_path = &args[1];
-        println!("Reading from: {}", jsonl_path);
-        let path = Path::new(jsonl_path);
-        let metadata = std::fs::metadata(path)?;
-        let pb = ProgressBar::new(metadata.len());
-        pb.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-            .unwrap()
-            .progress_chars("#>-"));
-        
-        import_jsonl_to_db(&db, path, pb.clone()).await?;
-        pb.finish_with_message("Import completed");
-    } else {
-        let dir_path = Path::new("data/normalized_json/");
-        let mut entries: Vec<_> = std::fs::read_dir(dir_path)?
-            .filter_map(|e| e.ok())
-            .collect();
-
-        // ファイル名順にソート
-        entries.sort_by_key(|e| e.path());
-
-        // 全ファイルサイズの合計を計算
-        let total_size: u64 = entries.iter()
-            .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("json"))
-            .map(|e| e.metadata().map(|m| m.len()).unwrap_or(0))
-            .sum();
-
-        // プログレスバーの設定
-        let pb = ProgressBar::new(total_size);
-        pb.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-            .unwrap()
-            .progress_chars("#>-"));
-
-        // 順次処理
-        for entry in entries {
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                import_jsonl_to_db(&db, &path, pb.clone()).await?;
-            }
-        }
-        
-        pb.finish_with_message("All imports completed");
-    }
-
-    println!("Import completed successfully");
-
-    Ok(())
-}
-
-async fn create_tables(db: &sea_orm::DatabaseConnection) -> Result<()> {
-    let schema = include_str!("../../sql/schema.sql");
-    let statements: Vec<&str> = schema
-        .split(';')
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .collect();
-
-    for sql in statements {
-        db.execute_raw(Statement::from_string(
-            sea_orm::DbBackend::Postgres,
-            sql.to_string(),
-        ))
-        .await
-        .with_context(|| format!("Failed to execute: {}", sql))?;
-    }
-
-    Ok(())
-}
-
-async fn drop_tables(db: &sea_orm::DatabaseConnection) -> Result<()> {
-    let statements = vec![
-        "DROP TABLE IF EXISTS gallery_files CASCADE",
-        "DROP TABLE IF EXISTS files CASCADE",
-        "DROP TABLE IF EXISTS gallery_tags CASCADE",
-        "DROP TABLE IF EXISTS gallery_artists CASCADE",
-        "DROP TABLE IF EXISTS gallery_groups CASCADE",
-        "DROP TABLE IF EXISTS gallery_characters CASCADE",
-        "DROP TABLE IF EXISTS gallery_parodies CASCADE",
-        "DROP TABLE IF EXISTS galleries CASCADE",
-        "DROP TABLE IF EXISTS languages CASCADE",
-        "DROP TABLE IF EXISTS tags CASCADE",
-        "DROP TABLE IF EXISTS artists CASCADE",
-        "DROP TABLE IF EXISTS groups CASCADE",
-        "DROP TABLE IF EXISTS characters CASCADE",
-        "DROP TABLE IF EXISTS parodies CASCADE",
-    ];
-
-    for sql in statements {
-        db.execute_raw(Statement::from_string(
-            sea_orm::DbBackend::Postgres,
-            sql.to_string(),
-        ))
-        .await
-        .with_context(|| format!("Failed to execute: {}", sql))?;
-    }
-
-    Ok(())
-}
-
-async fn import_jsonl_to_db(
-    db: &sea_orm::DatabaseConnection,
-    jsonl_path: &Path,
-    pb: ProgressBar,
-) -> Result<()> {
-    let file = File::open(jsonl_path)
-        .with_context(|| format!("Failed to open file: {:?}", jsonl_path))?;
-    
-    // プログレスバーでラップ
-    let reader = BufReader::new(pb.wrap_read(file));
-
-    let mut line_number = 0;
-    let mut chunk = Vec::with_capacity(BATCH_SIZE);
-
-    for line in reader.lines() {
-        line_number += 1;
-        let line = line.context("Failed to read line")?;
-        
-        // 空行はスキップ
-        if line.trim().is_empty() {
-            continue;
-        }
-
-        // JSONをパース
-        match serde_json::from_str::<Gallery>(&line) {
-            Ok(gallery) => {
-                chunk.push(gallery);
-                if chunk.len() >= BATCH_SIZE {
-                    if let Err(e) = galleries_mapper::insert_many_galleries(db, std::mem::take(&mut chunk)).await {
-                        pb.println(format!("Failed to insert chunk at line {}: {:?}", line_number, e));
-                    }
-                }
-            }
-            Err(e) => {
-                pb.println(format!("Failed to parse JSON at line {}: {}", line_number, e));
-            }
-        }
-    }
-
-    if !chunk.is_empty() {
-        if let Err(e) = galleries_mapper::insert_many_galleries(db, chunk).await {
-            pb.println(format!("Failed to insert remaining chunk: {:?}", e));
+    // 3. 取り込みモード (--mode=append|upsert|rebuild, 既定は upsert)
+    let (mode, mut args) = import::parse_mode_arg(env::args().collect()).map_err(|e| anyhow!(e))?;
+    // --sweep の場合は取り込み後に参照されなくなったタグなどを削除する
+    let sweep = import::take_flag(&mut args, "--sweep");
+    // --restart の場合はチェックポイントを無視して最初から取り込む
+    let restart = import::take_flag(&mut args, "--restart");
+    // --copy の場合は COPY (バイナリ形式) で一時テーブルに送ってからマージする
+    let loader = if import::take_flag(&mut args, "--copy") { Loader::Copy } else { Loader::Insert };
+
+    // 4. JSONLファイルパス (省略時は data/normalized_json/ の全ファイル)
+    let inputs = match args.get(1) {
+        Some(jsonl_path) => {
+            println!("Reading from: {}", jsonl_path);
+            pipeline::resolve_inputs(std::slice::from_ref(jsonl_path), "json")?
         }
-    }
-
-    // 個別のファイル完了ログも削除または必要なら pb.println で出力
-    // pb.println(format!("Finished: {:?} (Success: {}, Failed: {})", jsonl_path, success_count, error_count));
+        None => pipeline::resolve_inputs(&[pipeline::NORMALIZED_JSON_DIR.to_string()], "json")?,
+    };
 
+    // 処理できなかったレコードの出力先
+    let dead_letters = DeadLetterWriter::for_stage(Stage::ImportToDb);
+    pipeline::import::run(&db, &inputs, ImportOptions { mode, restart, loader }, sweep, &dead_letters).await?;
     Ok(())
 }
@@ -0,0 +1,166 @@
+use hitomi_server_rs::fbs::compress::CompressType;
+use hitomi_server_rs::import::{self, ImportMode};
+use hitomi_server_rs::import::dead_letter::{self, DeadLetter, DeadLetterWriter, RejectKind, Rejection, Stage};
+use hitomi_server_rs::import::record;
+use hitomi_server_rs::import::session::ImportSession;
+use hitomi_server_rs::mapper::{fbs_galleries_mapper, galleries_mapper};
+use hitomi_server_rs::pipeline;
+use anyhow::{anyhow, Context, Result};
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// デッドレターの JSONL を、記録された段階でもう一度処理する
+///
+/// 使い方: replay_dead_letters <dead_letter.jsonl> [--mode=append|upsert] [--output=<path>]
+/// - resp_to_json / normalize_json: 出力を新しい JSONL に書く (既定は次の段階の入力ディレクトリ)
+/// - import_to_db / import_bin_to_db: DATABASE_URL / DATABASE_URL_BINARY に1件ずつ書き込む
+///
+/// 再び失敗したレコードは `<入力>.retry.jsonl` に書く。
+#[tokio::main]
+async fn main() -> Result<()> {
+    // 1. 引数を取得
+    let (mode, args) = import::parse_mode_arg(env::args().collect()).map_err(|e| anyhow!(e))?;
+    if mode == ImportMode::Rebuild {
+        anyhow::bail!("--mode=rebuild cannot be used for replay");
+    }
+    let output = args.iter().find_map(|arg| arg.strip_prefix("--output=")).map(PathBuf::from);
+    let input = args.iter().skip(1).find(|arg| !arg.starts_with("--"))
+        .context("Usage: replay_dead_letters <dead_letter.jsonl> [--mode=append|upsert] [--output=<path>]")?;
+    let input = Path::new(input);
+
+    // 2. デッドレターを読み込む
+    let letters = dead_letter::read_dead_letters(input)
+        .with_context(|| format!("Failed to read dead letters: {:?}", input))?;
+    println!("Replaying {} records from {:?}", letters.len(), input);
+
+    let retry = DeadLetterWriter::new(input.with_extension("retry.jsonl"));
+    let mut replayed = 0;
+
+    // 3. 段階ごとに処理する
+    for stage in [Stage::RespToJson, Stage::NormalizeJson, Stage::ImportToDb, Stage::ImportBinToDb] {
+        let letters: Vec<&DeadLetter> = letters.iter().filter(|letter| letter.stage == stage).collect();
+        if letters.is_empty() {
+            continue;
+        }
+        println!("{}: {} records", stage.name(), letters.len());
+        replayed += match stage {
+            Stage::RespToJson | Stage::NormalizeJson => replay_to_file(stage, &letters, output.as_deref(), &retry)?,
+            Stage::ImportToDb => replay_to_db(&letters, mode, &retry).await?,
+            Stage::ImportBinToDb => replay_to_fbs_db(&letters, mode, &retry).await?,
+        };
+    }
+
+    println!("Replayed {} records, {} rejected again", replayed, retry.count());
+    if retry.count() > 0 {
+        println!("Rejected records were written to {:?}", retry.path());
+    }
+
+    Ok(())
+}
+
+/// 失敗したレコードを、元の位置情報のまま書き直す
+fn reject_again(retry: &DeadLetterWriter, letter: &DeadLetter, rejection: Rejection) -> Result<()> {
+    eprintln!("Rejected again (line {} of {}): {}", letter.line_number, letter.source, rejection);
+    retry.write(&DeadLetter::new(Path::new(&letter.source), letter.line_number, letter.stage, rejection, letter.raw.clone()))?;
+    Ok(())
+}
+
+fn replay_to_file(stage: Stage, letters: &[&DeadLetter], output: Option<&Path>, retry: &DeadLetterWriter) -> Result<u64> {
+    // 次の段階が読み込むディレクトリに、既存のファイルと重ならない名前で書く
+    let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+    let output = match (output, stage) {
+        (Some(output), _) => output.to_path_buf(),
+        (None, Stage::RespToJson) => PathBuf::from(format!("data/raw_json/replayed-{}.jsonl", timestamp)),
+        (None, _) => PathBuf::from(format!("data/normalized_json/replayed-{}.json", timestamp)),
+    };
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut writer = BufWriter::new(File::create(&output)
+        .with_context(|| format!("Failed to create output file: {:?}", output))?);
+
+    let mut replayed = 0;
+    for letter in letters {
+        let result = match stage {
+            Stage::RespToJson => record::resp_to_raw_json(&letter.raw),
+            _ => record::normalize_raw_json(letter.raw.as_bytes()).map(Some),
+        };
+        match result {
+            Ok(Some(json)) => {
+                writer.write_all(json.as_bytes())?;
+                writer.write_all(b"\n")?;
+                replayed += 1;
+            }
+            // 404 は元の段階と同じく読み飛ばす
+            Ok(None) => replayed += 1,
+            Err(rejection) => reject_again(retry, letter, rejection)?,
+        }
+    }
+    writer.flush()?;
+    println!("Wrote {:?}", output);
+    Ok(replayed)
+}
+
+async fn replay_to_db(letters: &[&DeadLetter], mode: ImportMode, retry: &DeadLetterWriter) -> Result<u64> {
+    let db = connect("DATABASE_URL").await?;
+    let result = replay_galleries(&db, letters, mode, retry).await;
+    // 途中で失敗しても、それまでに書き込んだ分をサジェストに反映する
+    pipeline::import::refresh_suggest_entries(&db, result).await
+}
+
+async fn replay_galleries(db: &DatabaseConnection, letters: &[&DeadLetter], mode: ImportMode, retry: &DeadLetterWriter) -> Result<u64> {
+    let session = ImportSession::new();
+    let mut replayed = 0;
+    for letter in letters {
+        let result = match record::parse_gallery(&letter.raw) {
+            Ok(gallery) => {
+                let gallery_id = gallery.gallery_id;
+                galleries_mapper::insert_many_galleries(db, vec![gallery], mode, &session).await
+                    .map_err(|e| Rejection::new(RejectKind::Insert, Some(gallery_id), e.to_string()))
+            }
+            Err(rejection) => Err(rejection),
+        };
+        match result {
+            Ok(()) => replayed += 1,
+            Err(rejection) => reject_again(retry, letter, rejection)?,
+        }
+    }
+    Ok(replayed)
+}
+
+async fn replay_to_fbs_db(letters: &[&DeadLetter], mode: ImportMode, retry: &DeadLetterWriter) -> Result<u64> {
+    let db = connect("DATABASE_URL_BINARY").await?;
+    let compress_type_id = fbs_galleries_mapper::get_or_create_compress_type(&db, CompressType::Zstd.name()).await?;
+    let mut replayed = 0;
+    for letter in letters {
+        let compressed = record::parse_gallery(&letter.raw)
+            .and_then(|gallery| Ok((gallery.gallery_id, record::compress_gallery(&gallery)?)));
+        let result = match compressed {
+            Ok((gallery_id, data)) => {
+                fbs_galleries_mapper::insert_fbs_batch(&db, vec![(gallery_id, data, compress_type_id)], mode).await
+                    .map_err(|e| Rejection::new(RejectKind::Insert, Some(gallery_id), e.to_string()))
+            }
+            Err(rejection) => Err(rejection),
+        };
+        match result {
+            Ok(()) => replayed += 1,
+            Err(rejection) => reject_again(retry, letter, rejection)?,
+        }
+    }
+    Ok(replayed)
+}
+
+async fn connect(env_name: &str) -> Result<DatabaseConnection> {
+    let database_url = env::var(env_name)
+        .with_context(|| format!("{} is not set", env_name))?;
+    let mut opt = ConnectOptions::new(&database_url).to_owned();
+    opt.max_connections(5);
+    opt.connect_timeout(std::time::Duration::from_secs(10));
+    opt.acquire_timeout(std::time::Duration::from_secs(10));
+    opt.set_schema_search_path("public");
+    Database::connect(opt).await
+        .context("Failed to connect to database")
+}
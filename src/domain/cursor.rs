@@ -0,0 +1,108 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// キーセットページネーションで使えるソートキー
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    Date,
+    DatePublished,
+    GalleryId,
+}
+
+impl SortKey {
+    /// galleries (エイリアス `g`) に対するソート式
+    /// date_published は NULL を行値比較から外さないよう -infinity に寄せる
+    pub fn expr(&self) -> &'static str {
+        match self {
+            Self::Date => "g.date",
+            Self::DatePublished => "COALESCE(g.date_published, '-infinity'::date)",
+            Self::GalleryId => "g.gallery_id",
+        }
+    }
+
+    /// カーソルに保存した文字列表現をキャストする型
+    pub fn sql_type(&self) -> &'static str {
+        match self {
+            Self::Date => "timestamptz",
+            Self::DatePublished => "date",
+            Self::GalleryId => "integer",
+        }
+    }
+}
+
+/// `SortKey::Date` のキー (timestamptz のテキスト表現) を UNIX 時間のマイクロ秒にする
+pub fn parse_date_key(key: &str) -> Option<i64> {
+    DateTime::parse_from_str(key, "%Y-%m-%d %H:%M:%S%.f%#z").ok().map(|date| date.timestamp_micros())
+}
+
+/// UNIX 時間のマイクロ秒を `SortKey::Date` のキーにする (PostgreSQL が UTC で出力する形と同じ)
+pub fn format_date_key(micros: i64) -> Option<String> {
+    DateTime::<Utc>::from_timestamp_micros(micros).map(|date| date.format("%Y-%m-%d %H:%M:%S%.f+00").to_string())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+
+    /// 「次のページ」を表す行値比較の演算子
+    pub fn next_operator(&self) -> &'static str {
+        match self {
+            Self::Asc => ">",
+            Self::Desc => "<",
+        }
+    }
+}
+
+/// 最後に返した行の位置 (クライアントには不透明な文字列として渡す)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub sort_by: SortKey,
+    pub order: SortOrder,
+    /// ソートキーの値 (PostgreSQL のテキスト表現)
+    pub key: String,
+    pub gallery_id: i32,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("Cursor is always serializable");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, String> {
+        let json = URL_SAFE_NO_PAD.decode(cursor)
+            .map_err(|_| "Invalid cursor.".to_string())?;
+        serde_json::from_slice(&json)
+            .map_err(|_| "Invalid cursor.".to_string())
+    }
+}
+
+/// キーセットページネーションの指定 (after が None なら先頭ページ)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keyset {
+    pub sort_by: SortKey,
+    pub order: SortOrder,
+    pub after: Option<Cursor>,
+}
+
+impl Keyset {
+    /// 最後に返した行からカーソルを作る
+    pub fn next_cursor(&self, key: String, gallery_id: i32) -> Cursor {
+        Cursor { sort_by: self.sort_by, order: self.order, key, gallery_id }
+    }
+}
@@ -1,7 +1,194 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use crate::api::error::ApiError;
+use crate::domain::cursor::{parse_date_key, Cursor, Keyset, SortKey, SortOrder};
+use crate::domain::facet::Facet;
+use crate::domain::set_expr::{SetExpr, SetOperand};
+use crate::search::bitmap_index::IndexStats;
+use crate::search::compiler;
+use crate::search::parser::Namespace;
+use sqlparser::ast::{Expr, LimitClause, Offset, OffsetRows, Query, Statement as ParserStatement, Value};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// クエリ1回あたりの statement_timeout の上限 (ミリ秒)
+pub const MAX_STATEMENT_TIMEOUT_MS: u32 = 60_000;
+
+/// batch_size の上限 (1チャンクのバッファサイズを抑えるため)
+pub const MAX_BATCH_SIZE: u32 = 100_000;
+
+/// `/suggest` で返す候補数の上限
+pub const MAX_SUGGEST_LIMIT: u32 = 100;
+
+/// `/facets` の項目ごとの上位 N 件の上限
+pub const MAX_FACET_TOP_N: u32 = 1000;
+
+/// `/sets` の式木に含められるサブクエリ (葉) の数の上限
+pub const MAX_SET_OPERANDS: usize = 32;
+
+/// `/sets` の葉1つあたりに読み込める gallery_id の数の上限
+pub const MAX_SET_OPERAND_ROWS: u64 = 2_000_000;
 
 #[derive(Deserialize)]
 pub struct SQLRequest {
+    pub query: String,
+    /// 指定した場合、クエリのトップレベルの OFFSET として設定される
+    #[serde(default)]
+    pub offset: Option<u32>,
+    /// 指定した場合、クエリのトップレベルの LIMIT として設定される
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default = "default_batch_size")]
+    pub batch_size: u32,
+    /// 指定した場合は gallery_id の代わりに各ギャラリーの本体を返す
+    #[serde(default)]
+    pub hydrate: Option<Hydrate>,
+    /// statement_timeout (ミリ秒, 1 から MAX_STATEMENT_TIMEOUT_MS まで)
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u32,
+    /// 指定した場合はキーセットページネーションになる (offset とは併用不可)
+    #[serde(default)]
+    pub sort_by: Option<SortKey>,
+    #[serde(default)]
+    pub order: Option<SortOrder>,
+    /// 前のレスポンスの end フレームの next_cursor の値
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Hydrate {
+    /// fbs_galleries の FlatBuffers (解凍済み) を返す
+    Fbs,
+}
+
+/// `/sql` のレスポンスの形式
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// ヘッダ・chunk・end フレームで包んだ形式 (`crate::framing`)
+    #[default]
+    Framed,
+    /// hitomi の `.nozomi` と同じビッグエンディアンの i32 列 (フレームなし)
+    Nozomi,
+}
+
+impl SQLRequest  {
+    /// 実行前に全フィールドを検証する
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_common(&self.query, self.batch_size, self.timeout_ms)?;
+        if self.format == OutputFormat::Nozomi && self.hydrate.is_some() {
+            return Err(ApiError::invalid_request("'hydrate' cannot be combined with the nozomi format."));
+        }
+        // nozomi 形式には end フレームが無く、次のページのカーソルを返せない
+        if self.format == OutputFormat::Nozomi && (self.sort_by.is_some() || self.cursor.is_some()) {
+            return Err(ApiError::invalid_request("Cursor pagination cannot be combined with the nozomi format."));
+        }
+        Ok(())
+    }
+
+    /// クエリをパースし、トップレベルの LIMIT / OFFSET をリクエストの値で設定する
+    ///
+    /// サブクエリ内の LIMIT / OFFSET はそのまま残す。
+    /// トップレベルに既に LIMIT / OFFSET があり、リクエストの値と異なる場合はエラーとする。
+    pub fn build_paginated_query(&self) -> Result<String, ApiError> {
+        self.paginate(self.limit, self.offset)
+    }
+
+    /// キーセットページネーション用に、正規化したクエリを返す
+    ///
+    /// 外側のクエリで並べ替えてページを切り出すので、トップレベルの LIMIT / OFFSET / FETCH は
+    /// (一部の行だけがページングの対象になってしまうため) エラーとする。
+    pub fn build_inner_query(&self) -> Result<String, ApiError> {
+        let query = parse_single_query(&self.query)?;
+        if query.limit_clause.is_some() || query.fetch.is_some() {
+            return Err(ApiError::invalid_request(
+                "The top-level LIMIT / OFFSET / FETCH clause cannot be combined with cursor pagination. Use 'limit' instead."
+            ));
+        }
+        Ok(query.to_string())
+    }
+
+    /// sort_by / order / cursor からキーセットページネーションの指定を組み立てる
+    pub fn keyset(&self) -> Result<Option<Keyset>, ApiError> {
+        let after = self.cursor.as_deref()
+            .map(Cursor::decode)
+            .transpose()
+            .map_err(ApiError::invalid_cursor)?;
+
+        let sort_by = match (&after, self.sort_by) {
+            (Some(cursor), Some(sort_by)) if cursor.sort_by != sort_by => {
+                return Err(ApiError::invalid_cursor("The cursor was issued for a different 'sort_by'."));
+            }
+            (Some(cursor), _) => cursor.sort_by,
+            (None, Some(sort_by)) => sort_by,
+            (None, None) => {
+                if self.order.is_some() {
+                    return Err(ApiError::invalid_request("'order' requires 'sort_by'."));
+                }
+                return Ok(None);
+            }
+        };
+
+        let order = match (&after, self.order) {
+            (Some(cursor), Some(order)) if cursor.order != order => {
+                return Err(ApiError::invalid_cursor("The cursor was issued for a different 'order'."));
+            }
+            (Some(cursor), _) => cursor.order,
+            (None, order) => order.unwrap_or_default(),
+        };
+
+        if self.offset.is_some() {
+            return Err(ApiError::invalid_request("'offset' cannot be combined with cursor pagination."));
+        }
+
+        Ok(Some(Keyset { sort_by, order, after }))
+    }
+
+    fn paginate(&self, limit_value: Option<u32>, offset_value: Option<u32>) -> Result<String, ApiError> {
+        let mut query = parse_single_query(&self.query)?;
+
+        if query.fetch.is_some() && limit_value.is_some() {
+            return Err(ApiError::invalid_request("The SQL query has a FETCH clause that conflicts with 'limit'."));
+        }
+
+        let (limit, offset) = match query.limit_clause.take() {
+            None => (None, None),
+            Some(LimitClause::LimitOffset { limit, offset, limit_by }) if limit_by.is_empty() => {
+                (limit, offset.map(|o| o.value))
+            }
+            Some(LimitClause::LimitOffset { .. }) => {
+                return Err(ApiError::invalid_request("LIMIT BY is not supported."));
+            }
+            Some(LimitClause::OffsetCommaLimit { offset, limit }) => (Some(limit), Some(offset)),
+        };
+
+        let limit = merge_pagination("LIMIT", limit, limit_value)?;
+        let offset = merge_pagination("OFFSET", offset, offset_value)?;
+
+        if limit.is_some() || offset.is_some() {
+            query.limit_clause = Some(LimitClause::LimitOffset {
+                limit,
+                offset: offset.map(|value| Offset { value, rows: OffsetRows::None }),
+                limit_by: vec![],
+            });
+        }
+
+        Ok(query.to_string())
+    }
+
+    pub fn statement_timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms as u64)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SearchRequest {
+    /// 検索クエリ (例: `female:big_breasts artist:alp -tag:ntr`)
     pub query: String,
     #[serde(default = "default_offset")]
     pub offset: u32,
@@ -9,24 +196,319 @@ pub struct SQLRequest {
     pub limit: u32,
     #[serde(default = "default_batch_size")]
     pub batch_size: u32,
+    #[serde(default)]
+    pub hydrate: Option<Hydrate>,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u32,
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// title モードで綴りの揺れを許容する (pg_trgm)
+    #[serde(default)]
+    pub fuzzy: bool,
+    /// 前のレスポンスの end フレームの next_cursor の値 (query モードのみ、offset とは併用不可)
+    #[serde(default)]
+    pub cursor: Option<String>,
 }
 
-impl SQLRequest  {
-    fn contains_pagination_keywords(&self) -> bool {
-        let q = self.query.to_lowercase();
-        q.contains("limit") || q.contains("offset")
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// hitomi の検索クエリ (新しい順)
+    #[default]
+    Query,
+    /// title / japanese_title の全文検索 (関連度順)
+    Title,
+}
+
+impl SearchRequest {
+    /// 実行前に全フィールドを検証する
+    pub fn validate(&self) -> Result<(), ApiError> {
+        validate_common(&self.query, self.batch_size, self.timeout_ms)?;
+        if self.fuzzy && self.mode != SearchMode::Title {
+            return Err(ApiError::invalid_request("'fuzzy' is only supported in title mode."));
+        }
+        Ok(())
+    }
+
+    /// キーセットページネーションの指定
+    ///
+    /// query モードで offset を使わない場合は、先頭ページでも次のページのカーソルを返す。
+    /// title モードは関連度順なのでカーソルは使えない。
+    pub fn keyset(&self) -> Result<Option<Keyset>, ApiError> {
+        let after = self.cursor.as_deref()
+            .map(Cursor::decode)
+            .transpose()
+            .map_err(ApiError::invalid_cursor)?;
+
+        if let Some(cursor) = &after {
+            if self.mode != SearchMode::Query {
+                return Err(ApiError::invalid_request("'cursor' is only supported in query mode."));
+            }
+            if self.offset > 0 {
+                return Err(ApiError::invalid_request("'offset' cannot be combined with cursor pagination."));
+            }
+            if (cursor.sort_by, cursor.order) != (compiler::SORT_KEY, compiler::SORT_ORDER)
+                || parse_date_key(&cursor.key).is_none() {
+                return Err(ApiError::invalid_cursor("The cursor was not issued by /search."));
+            }
+        }
+        if self.mode != SearchMode::Query || self.offset > 0 {
+            return Ok(None);
+        }
+        Ok(Some(Keyset { sort_by: compiler::SORT_KEY, order: compiler::SORT_ORDER, after }))
+    }
+
+    pub fn statement_timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms as u64)
     }
+}
+
+/// `GET /suggest?ns=artist&q=al&limit=10`
+#[derive(Deserialize)]
+pub struct SuggestRequest {
+    /// 検索クエリと同じ名前空間 (female, male, tag, artist, group, character, series, language)
+    pub ns: String,
+    /// 入力途中の値 (前方一致・部分一致)
+    pub q: String,
+    #[serde(default = "default_suggest_limit")]
+    pub limit: u32,
+}
 
-    pub fn build_paginated_query(&self) -> Result<String, String> {
-        if self.contains_pagination_keywords() {
-            Err("The SQL query should not contain LIMIT or OFFSET clauses.".into())
-        } else {
-            let sql = format!("{} LIMIT {} OFFSET {}", self.query, self.limit, self.offset);
-            Ok(sql)
+impl SuggestRequest {
+    /// 実行前に全フィールドを検証し、名前空間を返す
+    pub fn validate(&self) -> Result<Namespace, ApiError> {
+        let namespace = Namespace::from_name(&self.ns.to_lowercase())
+            .filter(|namespace| !matches!(namespace, Namespace::Type | Namespace::Title))
+            .ok_or_else(|| ApiError::invalid_request(format!("Namespace '{}' has no suggestions.", self.ns)))?;
+        if self.value().is_empty() {
+            return Err(ApiError::invalid_request("'q' must not be empty."));
+        }
+        if !(1..=MAX_SUGGEST_LIMIT).contains(&self.limit) {
+            return Err(ApiError::invalid_request(format!(
+                "'limit' must be between 1 and {}.", MAX_SUGGEST_LIMIT
+            )));
         }
+        Ok(namespace)
+    }
+
+    /// 検索クエリと同じく小文字化し、`_` を空白に戻した値
+    pub fn value(&self) -> String {
+        self.q.trim().to_lowercase().replace('_', " ")
     }
 }
 
+/// サジェストの候補1件
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub ns: &'static str,
+    pub value: String,
+    /// 紐づくギャラリー数
+    pub count: i64,
+    /// そのまま `/search` に使える検索語 (例: `female:big_breasts`)
+    pub term: String,
+}
+
+/// `/facets` のリクエスト (`sql` と `search` のどちらか一方を指定する)
+#[derive(Deserialize)]
+pub struct FacetsRequest {
+    /// `/sql` と同じ gallery_id を返す SQL
+    #[serde(default)]
+    pub sql: Option<String>,
+    /// `/search` と同じ検索クエリ
+    #[serde(default)]
+    pub search: Option<String>,
+    /// 各項目で返す上位の件数
+    #[serde(default = "default_facet_top_n")]
+    pub top_n: u32,
+    /// 項目ごとの上位の件数 (top_n より優先、0 の項目は集計しない)
+    #[serde(default)]
+    pub limits: BTreeMap<Facet, u32>,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u32,
+}
+
+impl FacetsRequest {
+    /// 実行前に全フィールドを検証する
+    pub fn validate(&self) -> Result<(), ApiError> {
+        let query = match (&self.sql, &self.search) {
+            (Some(query), None) | (None, Some(query)) => query,
+            _ => return Err(ApiError::invalid_request("Exactly one of 'sql' and 'search' must be given.")),
+        };
+        validate_common(query, 1, self.timeout_ms)?;
+        if let Some(top_n) = std::iter::once(self.top_n).chain(self.limits.values().copied()).find(|n| *n > MAX_FACET_TOP_N) {
+            return Err(ApiError::invalid_request(format!(
+                "Top-N ({}) must be at most {}.", top_n, MAX_FACET_TOP_N
+            )));
+        }
+        Ok(())
+    }
+
+    /// `sql` をパースし直したクエリ (コメントや末尾の `;` を含まないので、サブクエリとして埋め込める)
+    pub fn normalized_sql(&self) -> Result<Option<String>, ApiError> {
+        self.sql.as_deref()
+            .map(|sql| parse_single_query(sql).map(|query| query.to_string()))
+            .transpose()
+    }
+
+    /// 全項目の上位の件数 (limits で上書きしたもの)
+    pub fn resolved_limits(&self) -> BTreeMap<Facet, u32> {
+        Facet::ALL.into_iter()
+            .map(|facet| (facet, self.limits.get(&facet).copied().unwrap_or(self.top_n)))
+            .collect()
+    }
+
+    pub fn statement_timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms as u64)
+    }
+}
+
+/// `/sets` のリクエスト
+#[derive(Deserialize)]
+pub struct SetsRequest {
+    pub expr: SetExpr,
+    #[serde(default = "default_offset")]
+    pub offset: u32,
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+    #[serde(default = "default_batch_size")]
+    pub batch_size: u32,
+    #[serde(default)]
+    pub hydrate: Option<Hydrate>,
+    /// 検索語と全ギャラリーの読み込みの statement_timeout (ミリ秒, 1 から MAX_STATEMENT_TIMEOUT_MS まで)
+    /// (sql の葉はそれぞれの timeout_ms を使う)
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u32,
+}
+
+impl SetsRequest {
+    /// 実行前に式木全体を検証する
+    pub fn validate(&self) -> Result<(), ApiError> {
+        if !(1..=MAX_BATCH_SIZE).contains(&self.batch_size) {
+            return Err(ApiError::invalid_request(format!(
+                "'batch_size' must be between 1 and {}.", MAX_BATCH_SIZE
+            )));
+        }
+        validate_timeout(self.timeout_ms)?;
+        validate_set_expr(&self.expr)?;
+
+        let operands = self.expr.operands();
+        if operands.len() > MAX_SET_OPERANDS {
+            return Err(ApiError::invalid_request(format!(
+                "At most {} sub-queries are allowed, but {} were given.", MAX_SET_OPERANDS, operands.len()
+            )));
+        }
+        for operand in operands {
+            match operand {
+                SetOperand::Sql(request) => {
+                    request.validate()?;
+                    if request.hydrate.is_some() || request.format != OutputFormat::Framed {
+                        return Err(ApiError::invalid_request("'hydrate' and 'format' are only allowed at the top level."));
+                    }
+                    if request.sort_by.is_some() || request.order.is_some() || request.cursor.is_some() {
+                        return Err(ApiError::invalid_request("Cursor pagination is not supported in sub-queries."));
+                    }
+                }
+                SetOperand::Term(term) if term.trim().is_empty() => {
+                    return Err(ApiError::invalid_request("'term' must not be empty."));
+                }
+                SetOperand::Term(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn statement_timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms as u64)
+    }
+}
+
+fn validate_set_expr(expr: &SetExpr) -> Result<(), ApiError> {
+    match expr {
+        SetExpr::And(items) | SetExpr::Or(items) if items.is_empty() => {
+            Err(ApiError::invalid_request("'and' and 'or' must have at least one operand."))
+        }
+        SetExpr::And(items) | SetExpr::Or(items) => items.iter().try_for_each(validate_set_expr),
+        SetExpr::Not(inner) => validate_set_expr(inner),
+        SetExpr::Sql(_) | SetExpr::Term(_) => Ok(()),
+    }
+}
+
+/// `POST /index/refresh` (何も指定しない場合は追加・更新・削除された行だけを反映する)
+#[derive(Deserialize)]
+pub struct RefreshIndexRequest {
+    /// すべて読み直す
+    #[serde(default)]
+    pub full: bool,
+    /// 更新されたギャラリーだけを読み直す
+    #[serde(default)]
+    pub gallery_ids: Option<Vec<i32>>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RefreshIndexResponse {
+    #[serde(flatten)]
+    pub stats: IndexStats,
+    pub elapsed_ms: u128,
+}
+
+/// SQL をパースし、単一の SELECT であることを確認する
+fn parse_single_query(sql: &str) -> Result<Box<Query>, ApiError> {
+    let dialect = PostgreSqlDialect {};
+    let mut statements = Parser::parse_sql(&dialect, sql)
+        .map_err(|err| ApiError::invalid_sql(&err))?;
+
+    if statements.len() != 1 {
+        return Err(ApiError::forbidden_statement(format!(
+            "Exactly one statement is allowed, but {} were given.", statements.len()
+        )));
+    }
+    match statements.remove(0) {
+        ParserStatement::Query(query) => Ok(query),
+        _ => Err(ApiError::forbidden_statement("Only SELECT statements are allowed.")),
+    }
+}
+
+/// クエリ側の句とリクエストの値をまとめる (両方あって値が異なる場合はエラー)
+fn merge_pagination(clause: &str, existing: Option<Expr>, requested: Option<u32>) -> Result<Option<Expr>, ApiError> {
+    match (existing, requested) {
+        (Some(expr), Some(value)) => {
+            let same = matches!(&expr, Expr::Value(v) if v.value == Value::Number(value.to_string(), false));
+            if same {
+                Ok(Some(expr))
+            } else {
+                Err(ApiError::invalid_request(format!(
+                    "The top-level {} clause ({}) conflicts with the requested value ({}).", clause, expr, value
+                )))
+            }
+        }
+        (Some(expr), None) => Ok(Some(expr)),
+        (None, Some(value)) => Ok(Some(Expr::value(Value::Number(value.to_string(), false)))),
+        (None, None) => Ok(None),
+    }
+}
+
+fn validate_common(query: &str, batch_size: u32, timeout_ms: u32) -> Result<(), ApiError> {
+    if query.trim().is_empty() {
+        return Err(ApiError::invalid_request("'query' must not be empty."));
+    }
+    if !(1..=MAX_BATCH_SIZE).contains(&batch_size) {
+        return Err(ApiError::invalid_request(format!(
+            "'batch_size' must be between 1 and {}.", MAX_BATCH_SIZE
+        )));
+    }
+    validate_timeout(timeout_ms)
+}
+
+fn validate_timeout(timeout_ms: u32) -> Result<(), ApiError> {
+    // 0 は PostgreSQL では「タイムアウトなし」になるので許可しない
+    if !(1..=MAX_STATEMENT_TIMEOUT_MS).contains(&timeout_ms) {
+        return Err(ApiError::invalid_request(format!(
+            "'timeout_ms' must be between 1 and {}.", MAX_STATEMENT_TIMEOUT_MS
+        )));
+    }
+    Ok(())
+}
+
 fn default_offset() -> u32 {
     0
 }
@@ -38,3 +520,15 @@ fn default_limit() -> u32 {
 fn default_batch_size() -> u32 {
     1000
 }
+
+fn default_timeout_ms() -> u32 {
+    30_000
+}
+
+fn default_suggest_limit() -> u32 {
+    10
+}
+
+fn default_facet_top_n() -> u32 {
+    10
+}
\ No newline at end of file
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// `/facets` で集計できる項目
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Facet {
+    Tags,
+    Artists,
+    Groups,
+    Characters,
+    Parodies,
+    Languages,
+    Types,
+}
+
+impl Facet {
+    pub const ALL: [Facet; 7] = [
+        Self::Tags,
+        Self::Artists,
+        Self::Groups,
+        Self::Characters,
+        Self::Parodies,
+        Self::Languages,
+        Self::Types,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Tags => "tags",
+            Self::Artists => "artists",
+            Self::Groups => "groups",
+            Self::Characters => "characters",
+            Self::Parodies => "parodies",
+            Self::Languages => "languages",
+            Self::Types => "types",
+        }
+    }
+}
+
+/// 集計結果の1件
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FacetValue {
+    /// 検索クエリの名前空間 (tags は female / male / tag のいずれか)
+    pub ns: String,
+    pub value: String,
+    /// 結果セット内でこの値を持つギャラリー数
+    pub count: i64,
+    /// そのまま `/search` に使える検索語
+    pub term: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FacetsResponse {
+    /// 結果セットのギャラリー数
+    pub total: i64,
+    pub facets: BTreeMap<Facet, Vec<FacetValue>>,
+}
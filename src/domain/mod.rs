@@ -1,2 +1,6 @@
 pub mod gallery;
 pub mod dto;
+pub mod cursor;
+
+pub mod facet;
+pub mod set_expr;
\ No newline at end of file
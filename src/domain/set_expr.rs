@@ -0,0 +1,98 @@
+use roaring::{MultiOps, RoaringBitmap};
+use serde::Deserialize;
+use crate::domain::dto::SQLRequest;
+
+/// `/sets` の式木
+///
+/// 例: `{"and": [{"term": "female:collar"}, {"not": {"sql": {"query": "SELECT ..."}}}]}`
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SetExpr {
+    And(Vec<SetExpr>),
+    Or(Vec<SetExpr>),
+    Not(Box<SetExpr>),
+    /// gallery_id を返す SQL (`/sql` と同じ制約)
+    Sql(Box<SQLRequest>),
+    /// `namespace:value` 形式の検索語
+    Term(String),
+}
+
+/// 式木の葉 (DB かビットマップインデックスで評価する部分)
+#[derive(Clone, Copy)]
+pub enum SetOperand<'a> {
+    Sql(&'a SQLRequest),
+    Term(&'a str),
+}
+
+impl SetExpr {
+    /// 葉を前順 (combine が結果を読む順) に並べる
+    pub fn operands(&self) -> Vec<SetOperand<'_>> {
+        let mut operands = Vec::new();
+        self.collect_operands(&mut operands);
+        operands
+    }
+
+    fn collect_operands<'a>(&'a self, operands: &mut Vec<SetOperand<'a>>) {
+        match self {
+            Self::And(items) | Self::Or(items) => items.iter().for_each(|item| item.collect_operands(operands)),
+            Self::Not(inner) => inner.collect_operands(operands),
+            Self::Sql(request) => operands.push(SetOperand::Sql(request)),
+            Self::Term(term) => operands.push(SetOperand::Term(term)),
+        }
+    }
+
+    /// 全ギャラリーとの差を取る必要があるか
+    ///
+    /// `and` の中の `not` は他の項から引くだけなので不要。
+    /// `not` だけの `and`、`or` の中の `not`、トップレベルの `not` では必要になる。
+    pub fn needs_universe(&self) -> bool {
+        match self {
+            Self::And(items) => {
+                let has_positive = items.iter().any(|item| !matches!(item, Self::Not(_)));
+                items.iter().any(|item| match item {
+                    Self::Not(inner) => !has_positive || inner.needs_universe(),
+                    item => item.needs_universe(),
+                })
+            }
+            Self::Or(items) => items.iter().any(|item| matches!(item, Self::Not(_)) || item.needs_universe()),
+            Self::Not(_) => true,
+            Self::Sql(_) | Self::Term(_) => false,
+        }
+    }
+
+    /// 葉の評価結果 (operands と同じ順) を組み合わせる
+    ///
+    /// `universe` は needs_universe が true の場合だけ必要 (無ければ空集合として扱う)。
+    pub fn combine(&self, results: &[RoaringBitmap], universe: Option<&RoaringBitmap>) -> RoaringBitmap {
+        let mut results = results.iter();
+        self.combine_next(&mut results, universe)
+    }
+
+    fn combine_next<'a>(
+        &self,
+        results: &mut impl Iterator<Item = &'a RoaringBitmap>,
+        universe: Option<&RoaringBitmap>,
+    ) -> RoaringBitmap {
+        let complement = |bitmap: RoaringBitmap| universe.map(|all| all - bitmap).unwrap_or_default();
+        match self {
+            Self::And(items) => {
+                let mut positives = Vec::new();
+                let mut negatives = Vec::new();
+                for item in items {
+                    match item {
+                        Self::Not(inner) => negatives.push(inner.combine_next(results, universe)),
+                        item => positives.push(item.combine_next(results, universe)),
+                    }
+                }
+                let matched = match positives.is_empty() {
+                    true => universe.cloned().unwrap_or_default(),
+                    false => positives.intersection(),
+                };
+                matched - negatives.union()
+            }
+            Self::Or(items) => items.iter().map(|item| item.combine_next(results, universe)).collect::<Vec<_>>().union(),
+            Self::Not(inner) => complement(inner.combine_next(results, universe)),
+            Self::Sql(_) | Self::Term(_) => results.next().cloned().unwrap_or_default(),
+        }
+    }
+}
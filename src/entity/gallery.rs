@@ -19,6 +19,7 @@ pub struct Model {
     
     pub language_id: Option<i32>,
     pub translation_group_id: Vec<String>,
+    pub languages: Json,
 
     pub video: Option<String>,
     pub videofilename: Option<String>,
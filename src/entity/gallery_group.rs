@@ -8,6 +8,8 @@ pub struct Model {
 
     #[sea_orm(primary_key)]
     pub group_id: i32,
+
+    pub position: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter)]
@@ -8,6 +8,8 @@ pub struct Model {
 
     #[sea_orm(primary_key)]
     pub parody_id: i32,
+
+    pub position: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter)]
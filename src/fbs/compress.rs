@@ -0,0 +1,31 @@
+/// fbs_compress_types.name に対応する圧縮形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressType {
+    None,
+    Zstd,
+}
+
+impl CompressType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// 圧縮済みデータを FlatBuffers の生バイト列に戻す
+    pub fn decompress(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(data),
+            Self::Zstd => zstd::decode_all(&data[..]),
+        }
+    }
+}
@@ -7,7 +7,7 @@ pub fn serialize_gallery(gallery_data: &gallery::Gallery) -> Vec<u8> {
     let root_offset = gallery_data.to_flatbuffer(&mut fbb);
     fbb.finish(root_offset, None);
 
-    return fbb.finished_data().to_vec()
+    fbb.finished_data().to_vec()
 }
 
 pub fn deserialize_gallery(data: &[u8]) -> gallery::Gallery {
@@ -84,7 +84,7 @@ pub fn deserialize_gallery(data: &[u8]) -> gallery::Gallery {
                 .collect()
             });
 
-            let gallery = gallery::Gallery {
+            gallery::Gallery {
                 gallery_id: data.gallery_id(),
                 title: data.title().to_string(),
                 date: chrono::DateTime::parse_from_rfc3339(data.date()).unwrap(),
@@ -108,8 +108,7 @@ pub fn deserialize_gallery(data: &[u8]) -> gallery::Gallery {
                 gallery_url: data.gallery_url().map(|s| s.to_string()),
                 date_published: data.date_published().and_then(|date_str| chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d").ok()),
                 blocked: data.blocked(),
-            };
-            return gallery
+            }
         }
         Err(e) => {
                 panic!("Failed to deserialize gallery: {:?}", e);
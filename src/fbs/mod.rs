@@ -1,2 +1,4 @@
+#[allow(clippy::all)]
 pub mod gallery_generated;
 pub mod converter;
+pub mod compress;
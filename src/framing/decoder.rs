@@ -0,0 +1,293 @@
+use std::fmt;
+use crate::framing::{
+    Frame, PayloadKind, StreamEnd, StreamError, HEADER_LENGTH, MAGIC, STATUS_ERROR, STATUS_OK, TAG_CHUNK, TAG_END, VERSION,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    UnknownPayload(u8),
+    UnknownFrame(u8),
+    Malformed(String),
+    /// end フレームより後ろにデータがある
+    TrailingData,
+    /// end フレームの前にストリームが終わった
+    Truncated,
+    /// end フレームの total_rows と chunk の行数の合計が一致しない
+    RowCountMismatch { expected: u64, actual: u64 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "Not a gallery id stream (bad magic)"),
+            Self::UnsupportedVersion(version) => write!(f, "Unsupported stream version: {}", version),
+            Self::UnknownPayload(payload) => write!(f, "Unknown payload kind: {}", payload),
+            Self::UnknownFrame(tag) => write!(f, "Unknown frame tag: {:#04x}", tag),
+            Self::Malformed(message) => write!(f, "Malformed frame: {}", message),
+            Self::TrailingData => write!(f, "Unexpected data after the end frame"),
+            Self::Truncated => write!(f, "Stream ended before the end frame"),
+            Self::RowCountMismatch { expected, actual } => {
+                write!(f, "Row count mismatch: end frame says {}, chunks have {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Header,
+    Body,
+    Done,
+}
+
+/// 受信したバイト列を少しずつ渡してフレームを取り出すデコーダ
+///
+/// ```ignore
+/// let mut decoder = Decoder::new();
+/// while let Some(bytes) = body.next().await {
+///     decoder.push(&bytes?);
+///     while let Some(frame) = decoder.next_frame()? { ... }
+/// }
+/// decoder.finish()?;
+/// ```
+#[derive(Debug)]
+pub struct Decoder {
+    buffer: Vec<u8>,
+    state: State,
+    payload: Option<PayloadKind>,
+    rows: u64,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self { buffer: Vec::new(), state: State::Header, payload: None, rows: 0 }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// ヘッダのペイロード形式 (ヘッダを読むまでは None)
+    pub fn payload(&self) -> Option<PayloadKind> {
+        self.payload
+    }
+
+    /// 完全なフレームが1つ溜まっていれば取り出す (足りなければ None)
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, DecodeError> {
+        match self.state {
+            State::Header => self.read_header(),
+            State::Body => self.read_body_frame(),
+            State::Done if self.buffer.is_empty() => Ok(None),
+            State::Done => Err(DecodeError::TrailingData),
+        }
+    }
+
+    /// 入力の終わりで呼び、end フレームまで読めたかを確認する
+    pub fn finish(&self) -> Result<(), DecodeError> {
+        match self.state {
+            State::Done if self.buffer.is_empty() => Ok(()),
+            State::Done => Err(DecodeError::TrailingData),
+            _ => Err(DecodeError::Truncated),
+        }
+    }
+
+    fn read_header(&mut self) -> Result<Option<Frame>, DecodeError> {
+        if self.buffer.len() < HEADER_LENGTH {
+            // 最初の数バイトで別の形式だと分かる場合は早めにエラーにする
+            let n = self.buffer.len().min(MAGIC.len());
+            if self.buffer[..n] != MAGIC[..n] {
+                return Err(DecodeError::BadMagic);
+            }
+            return Ok(None);
+        }
+        if &self.buffer[..4] != MAGIC {
+            return Err(DecodeError::BadMagic);
+        }
+        let version = u16::from_le_bytes([self.buffer[4], self.buffer[5]]);
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let payload = PayloadKind::from_byte(self.buffer[6]).ok_or(DecodeError::UnknownPayload(self.buffer[6]))?;
+
+        self.buffer.drain(..HEADER_LENGTH);
+        self.state = State::Body;
+        self.payload = Some(payload);
+        Ok(Some(Frame::Header { version, payload }))
+    }
+
+    fn read_body_frame(&mut self) -> Result<Option<Frame>, DecodeError> {
+        let Some(&tag) = self.buffer.first() else {
+            return Ok(None);
+        };
+        match tag {
+            TAG_CHUNK => {
+                let mut reader = Reader::new(&self.buffer[1..]);
+                let (Some(rows), Some(length)) = (reader.u32(), reader.u32()) else {
+                    return Ok(None);
+                };
+                let Some(data) = reader.bytes(length as usize) else {
+                    return Ok(None);
+                };
+                let frame = Frame::Chunk { rows, data: data.to_vec() };
+                let consumed = 1 + reader.position;
+                self.buffer.drain(..consumed);
+                self.rows += rows as u64;
+                Ok(Some(frame))
+            }
+            TAG_END => {
+                let mut reader = Reader::new(&self.buffer[1..]);
+                let (Some(total_rows), Some(status)) = (reader.u64(), reader.u8()) else {
+                    return Ok(None);
+                };
+                let error = match status {
+                    STATUS_OK => None,
+                    STATUS_ERROR => {
+                        let Some(code) = reader.u16().and_then(|n| reader.bytes(n as usize)).map(<[u8]>::to_vec) else {
+                            return Ok(None);
+                        };
+                        let Some(message) = reader.u32().and_then(|n| reader.bytes(n as usize)).map(<[u8]>::to_vec) else {
+                            return Ok(None);
+                        };
+                        Some(StreamError { code: utf8(code)?, message: utf8(message)? })
+                    }
+                    status => return Err(DecodeError::Malformed(format!("Unknown status: {}", status))),
+                };
+                let Some(cursor) = reader.u16().and_then(|n| reader.bytes(n as usize)).map(<[u8]>::to_vec) else {
+                    return Ok(None);
+                };
+                let next_cursor = if cursor.is_empty() { None } else { Some(utf8(cursor)?) };
+                if total_rows != self.rows {
+                    return Err(DecodeError::RowCountMismatch { expected: total_rows, actual: self.rows });
+                }
+                let consumed = 1 + reader.position;
+                self.buffer.drain(..consumed);
+                self.state = State::Done;
+                Ok(Some(Frame::End(StreamEnd { total_rows, error, next_cursor })))
+            }
+            tag => Err(DecodeError::UnknownFrame(tag)),
+        }
+    }
+}
+
+/// ストリーム全体をデコードした結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedStream {
+    pub payload: PayloadKind,
+    /// chunk のペイロードを連結したもの
+    pub data: Vec<u8>,
+    pub end: StreamEnd,
+}
+
+impl DecodedStream {
+    /// payload が Ids の場合の gallery_id の列
+    pub fn ids(&self) -> Result<Vec<i32>, DecodeError> {
+        decode_ids(&self.data)
+    }
+
+    /// payload が Fbs の場合の (gallery_id, FlatBuffers) の列
+    pub fn fbs_records(&self) -> Result<Vec<(i32, Vec<u8>)>, DecodeError> {
+        decode_fbs_records(&self.data)
+    }
+}
+
+/// 受信済みのバイト列全体をデコードする (end フレームが無ければ Truncated)
+pub fn decode(bytes: &[u8]) -> Result<DecodedStream, DecodeError> {
+    let mut decoder = Decoder::new();
+    decoder.push(bytes);
+
+    let mut data = Vec::new();
+    let mut end = None;
+    while let Some(frame) = decoder.next_frame()? {
+        match frame {
+            Frame::Header { .. } => {}
+            Frame::Chunk { data: chunk, .. } => data.extend_from_slice(&chunk),
+            Frame::End(stream_end) => end = Some(stream_end),
+        }
+    }
+    decoder.finish()?;
+
+    Ok(DecodedStream {
+        payload: decoder.payload().ok_or(DecodeError::Truncated)?,
+        data,
+        end: end.ok_or(DecodeError::Truncated)?,
+    })
+}
+
+/// Ids の chunk を gallery_id の列に戻す
+pub fn decode_ids(data: &[u8]) -> Result<Vec<i32>, DecodeError> {
+    if !data.len().is_multiple_of(4) {
+        return Err(DecodeError::Malformed(format!("Id payload length {} is not a multiple of 4", data.len())));
+    }
+    Ok(data.chunks_exact(4)
+        .map(|bytes| i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect())
+}
+
+/// Fbs の chunk を (gallery_id, FlatBuffers) の列に戻す (fbs_galleries に無い id は空)
+pub fn decode_fbs_records(data: &[u8]) -> Result<Vec<(i32, Vec<u8>)>, DecodeError> {
+    let mut reader = Reader::new(data);
+    let mut records = Vec::new();
+    while reader.position < data.len() {
+        let record = reader.i32()
+            .zip(reader.u32().and_then(|n| reader.bytes(n as usize)))
+            .ok_or_else(|| DecodeError::Malformed("Truncated FlatBuffers record".to_string()))?;
+        records.push((record.0, record.1.to_vec()));
+    }
+    Ok(records)
+}
+
+fn utf8(bytes: Vec<u8>) -> Result<String, DecodeError> {
+    String::from_utf8(bytes).map_err(|_| DecodeError::Malformed("Invalid UTF-8 in end frame".to_string()))
+}
+
+/// リトルエンディアンの値を順に読む (足りなければ None)
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.position..self.position.checked_add(n)?)?;
+        self.position += n;
+        Some(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.bytes(N).map(|bytes| bytes.try_into().expect("length is checked"))
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.array::<1>().map(|[byte]| byte)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.array().map(i32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+}
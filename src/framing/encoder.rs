@@ -0,0 +1,55 @@
+use crate::framing::{PayloadKind, StreamEnd, MAGIC, STATUS_ERROR, STATUS_OK, TAG_CHUNK, TAG_END, VERSION};
+
+pub fn encode_header(payload: PayloadKind) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(crate::framing::HEADER_LENGTH);
+    buffer.extend_from_slice(MAGIC);
+    buffer.extend_from_slice(&VERSION.to_le_bytes());
+    buffer.push(payload.to_byte());
+    buffer.push(0);
+    buffer
+}
+
+pub fn encode_chunk(rows: u32, data: &[u8]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(9 + data.len());
+    buffer.push(TAG_CHUNK);
+    buffer.extend_from_slice(&rows.to_le_bytes());
+    buffer.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(data);
+    buffer
+}
+
+pub fn encode_end(end: &StreamEnd) -> Vec<u8> {
+    let mut buffer = vec![TAG_END];
+    buffer.extend_from_slice(&end.total_rows.to_le_bytes());
+    match &end.error {
+        None => buffer.push(STATUS_OK),
+        Some(error) => {
+            buffer.push(STATUS_ERROR);
+            buffer.extend_from_slice(&(error.code.len() as u16).to_le_bytes());
+            buffer.extend_from_slice(error.code.as_bytes());
+            buffer.extend_from_slice(&(error.message.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(error.message.as_bytes());
+        }
+    }
+    let cursor = end.next_cursor.as_deref().unwrap_or_default();
+    buffer.extend_from_slice(&(cursor.len() as u16).to_le_bytes());
+    buffer.extend_from_slice(cursor.as_bytes());
+    buffer
+}
+
+/// gallery_id をリトルエンディアンの i32 列としてエンコードする
+pub fn encode_ids(ids: &[i32]) -> Vec<u8> {
+    // メモリ効率のため、あらかじめバッファを確保（i32 = 4 bytes）
+    let mut buffer = Vec::with_capacity(ids.len() * 4);
+    for gallery_id in ids {
+        buffer.extend_from_slice(&gallery_id.to_le_bytes());
+    }
+    buffer
+}
+
+/// `gallery_id (i32 LE) | length (u32 LE) | FlatBuffers` のレコードを追加する
+pub fn push_fbs_record(buffer: &mut Vec<u8>, gallery_id: i32, data: &[u8]) {
+    buffer.extend_from_slice(&gallery_id.to_le_bytes());
+    buffer.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(data);
+}
@@ -0,0 +1,74 @@
+pub mod encoder;
+pub mod decoder;
+
+// `/sql` と `/search` が返すバイナリストリームのフレーミング
+//
+// header : magic "HTID" | version (u16 LE) | payload (u8, 0 = ids, 1 = fbs) | reserved (u8)
+// chunk  : 0x01 | rows (u32 LE) | length (u32 LE) | payload (length bytes)
+// end    : 0x02 | total_rows (u64 LE) | status (u8, 0 = ok, 1 = error)
+//          [status = error のとき: code_length (u16 LE) | code | message_length (u32 LE) | message]
+//          | cursor_length (u16 LE) | cursor (0 バイトなら次のページのカーソルなし)
+//
+// chunk は X-Batch-Size 行ごとに1つ送られ、ストリームは必ず end で終わる。
+// end が無いまま接続が切れた場合は結果が途中で切れている。
+
+pub const MAGIC: &[u8; 4] = b"HTID";
+pub const VERSION: u16 = 2;
+
+pub const HEADER_LENGTH: usize = 8;
+pub const TAG_CHUNK: u8 = 0x01;
+pub const TAG_END: u8 = 0x02;
+
+const STATUS_OK: u8 = 0;
+const STATUS_ERROR: u8 = 1;
+
+/// chunk のペイロードの形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadKind {
+    /// gallery_id (i32 LE) の列
+    Ids,
+    /// `gallery_id (i32 LE) | length (u32 LE) | FlatBuffers` の列
+    Fbs,
+}
+
+impl PayloadKind {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Ids),
+            1 => Some(Self::Fbs),
+            _ => None,
+        }
+    }
+
+    pub fn to_byte(&self) -> u8 {
+        match self {
+            Self::Ids => 0,
+            Self::Fbs => 1,
+        }
+    }
+}
+
+/// ストリーム途中で発生したエラー (code は API のエラーコードと同じ)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamError {
+    pub code: String,
+    pub message: String,
+}
+
+/// 終端フレーム
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamEnd {
+    /// 送信済みの chunk の行数の合計
+    pub total_rows: u64,
+    pub error: Option<StreamError>,
+    /// キーセットページネーションで次のページを要求するためのカーソル
+    /// (最後まで返せた場合のみ。HTTP トレーラはブラウザから読めないので end に載せる)
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Header { version: u16, payload: PayloadKind },
+    Chunk { rows: u32, data: Vec<u8> },
+    End(StreamEnd),
+}
@@ -0,0 +1,163 @@
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, DbErr, Statement};
+
+/// 取り込み済みの位置 (最後にコミットしたバッチの直後)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Checkpoint {
+    pub byte_offset: u64,
+    pub line_number: u64,
+    pub batches: u64,
+    pub completed: bool,
+}
+
+/// チェックポイントを記録した時点のファイル (変わっていたら最初から読み直す)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFingerprint {
+    pub size: u64,
+    pub modified_ms: i64,
+}
+
+impl FileFingerprint {
+    pub fn of(path: &Path) -> io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let modified_ms = metadata.modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as i64)
+            .unwrap_or(0);
+        Ok(Self { size: metadata.len(), modified_ms })
+    }
+}
+
+/// import_checkpoints テーブルへの読み書き (パイプラインごとに分ける)
+#[derive(Debug, Clone)]
+pub struct CheckpointStore {
+    db: DatabaseConnection,
+    pipeline: &'static str,
+}
+
+impl CheckpointStore {
+    pub fn new(db: DatabaseConnection, pipeline: &'static str) -> Self {
+        Self { db, pipeline }
+    }
+
+    pub async fn create_table(&self) -> Result<(), DbErr> {
+        let schema = include_str!("../../sql/checkpoint_schema.sql");
+        self.db.execute_unprepared(schema).await?;
+        Ok(())
+    }
+
+    /// このパイプラインのチェックポイントをすべて消す (最初から取り込み直す)
+    pub async fn clear(&self) -> Result<u64, DbErr> {
+        let result = self.db.execute_raw(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "DELETE FROM import_checkpoints WHERE pipeline = $1",
+            vec![self.pipeline.into()],
+        )).await?;
+        Ok(result.rows_affected())
+    }
+
+    /// ファイルが前回から変わっていない場合だけチェックポイントを返す
+    pub async fn load(&self, path: &Path, fingerprint: FileFingerprint) -> Result<Option<Checkpoint>, DbErr> {
+        let row = self.db.query_one_raw(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT byte_offset, line_number, batches, completed FROM import_checkpoints \
+             WHERE pipeline = $1 AND path = $2 AND file_size = $3 AND file_modified_ms = $4",
+            vec![
+                self.pipeline.into(),
+                path_key(path).into(),
+                (fingerprint.size as i64).into(),
+                fingerprint.modified_ms.into(),
+            ],
+        )).await?;
+
+        row.map(|row| Ok(Checkpoint {
+            byte_offset: row.try_get::<i64>("", "byte_offset")? as u64,
+            line_number: row.try_get::<i64>("", "line_number")? as u64,
+            batches: row.try_get::<i64>("", "batches")? as u64,
+            completed: row.try_get("", "completed")?,
+        })).transpose()
+    }
+
+    pub async fn save(&self, path: &Path, fingerprint: FileFingerprint, checkpoint: &Checkpoint) -> Result<(), DbErr> {
+        self.db.execute_raw(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "INSERT INTO import_checkpoints \
+             (pipeline, path, file_size, file_modified_ms, byte_offset, line_number, batches, completed, updated_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, now()) \
+             ON CONFLICT (pipeline, path) DO UPDATE SET \
+             file_size = EXCLUDED.file_size, file_modified_ms = EXCLUDED.file_modified_ms, \
+             byte_offset = EXCLUDED.byte_offset, line_number = EXCLUDED.line_number, \
+             batches = EXCLUDED.batches, completed = EXCLUDED.completed, updated_at = now()",
+            vec![
+                self.pipeline.into(),
+                path_key(path).into(),
+                (fingerprint.size as i64).into(),
+                fingerprint.modified_ms.into(),
+                (checkpoint.byte_offset as i64).into(),
+                (checkpoint.line_number as i64).into(),
+                (checkpoint.batches as i64).into(),
+                checkpoint.completed.into(),
+            ],
+        )).await?;
+        Ok(())
+    }
+}
+
+/// 実行時のカレントディレクトリに依存しないように絶対パスで記録する
+fn path_key(path: &Path) -> String {
+    std::fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// 読み込んだバイト数と行番号を数えながら1行ずつ読む
+///
+/// `BufRead::lines` と同じく末尾の改行は取り除く。UTF-8 として不正な行もバイト数には数える。
+pub struct TrackedLines<R> {
+    reader: R,
+    byte_offset: u64,
+    line_number: u64,
+}
+
+impl<R: BufRead> TrackedLines<R> {
+    /// `byte_offset` / `line_number` は読み始める位置 (シーク済みの reader に合わせる)
+    pub fn new(reader: R, byte_offset: u64, line_number: u64) -> Self {
+        Self { reader, byte_offset, line_number }
+    }
+
+    /// 最後に返した行の直後のバイト位置
+    pub fn byte_offset(&self) -> u64 {
+        self.byte_offset
+    }
+
+    /// 最後に返した行の行番号 (1始まり)
+    pub fn line_number(&self) -> u64 {
+        self.line_number
+    }
+}
+
+impl<R: BufRead> Iterator for TrackedLines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buffer = Vec::new();
+        match self.reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => None,
+            Ok(read) => {
+                self.byte_offset += read as u64;
+                self.line_number += 1;
+                if buffer.ends_with(b"\n") {
+                    buffer.pop();
+                    if buffer.ends_with(b"\r") {
+                        buffer.pop();
+                    }
+                }
+                Some(String::from_utf8(buffer).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)))
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 出力先ディレクトリの既定値 (環境変数 DEAD_LETTER_DIR で変更できる)
+pub const DEFAULT_DEAD_LETTER_DIR: &str = "data/dead_letter/";
+
+/// レコードを処理するパイプラインの段階
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// RESP (スクレイピング結果) → raw JSON
+    RespToJson,
+    /// raw JSON → 正規化した Gallery JSON
+    NormalizeJson,
+    /// Gallery JSON → リレーショナルテーブル
+    ImportToDb,
+    /// Gallery JSON → fbs_galleries
+    ImportBinToDb,
+}
+
+impl Stage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RespToJson => "resp_to_json",
+            Self::NormalizeJson => "normalize_json",
+            Self::ImportToDb => "import_to_db",
+            Self::ImportBinToDb => "import_bin_to_db",
+        }
+    }
+}
+
+/// レコードを受け付けなかった理由の分類
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectKind {
+    /// 行を読めなかった (UTF-8 として不正など)
+    Read,
+    /// JSON としてパースできなかった、または期待する形でなかった
+    Parse,
+    /// RESP のステータスが 200 / 404 以外
+    UnexpectedStatus,
+    /// raw_data が `var galleryinfo = ` で始まらないなど
+    UnexpectedFormat,
+    /// FlatBuffers の圧縮に失敗した
+    Compress,
+    /// DB への書き込みに失敗した (バッチ単位の失敗ではバッチ内の全レコード)
+    Insert,
+}
+
+/// 1つの段階で処理できなかったレコード
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetter {
+    pub source: String,
+    pub line_number: u64,
+    pub gallery_id: Option<i32>,
+    pub stage: Stage,
+    pub kind: RejectKind,
+    pub error: String,
+    /// 元の行 (再処理に使う)
+    pub raw: String,
+    pub rejected_at: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    pub fn new(source: &Path, line_number: u64, stage: Stage, rejection: Rejection, raw: impl Into<String>) -> Self {
+        Self {
+            source: source.to_string_lossy().into_owned(),
+            line_number,
+            gallery_id: rejection.gallery_id,
+            stage,
+            kind: rejection.kind,
+            error: rejection.message,
+            raw: raw.into(),
+            rejected_at: Utc::now(),
+        }
+    }
+}
+
+/// レコード単位の処理の失敗
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    pub kind: RejectKind,
+    pub gallery_id: Option<i32>,
+    pub message: String,
+}
+
+impl Rejection {
+    pub fn new(kind: RejectKind, gallery_id: Option<i32>, message: impl Into<String>) -> Self {
+        Self { kind, gallery_id, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.gallery_id {
+            Some(gallery_id) => write!(f, "{:?} (gallery_id {}): {}", self.kind, gallery_id, self.message),
+            None => write!(f, "{:?}: {}", self.kind, self.message),
+        }
+    }
+}
+
+/// デッドレターを JSONL に追記する (複数スレッドから共有できる)
+///
+/// ファイルは最初の書き込みで作るので、失敗が無ければ何も残らない。
+#[derive(Debug)]
+pub struct DeadLetterWriter {
+    path: PathBuf,
+    writer: Mutex<Option<BufWriter<File>>>,
+    count: AtomicU64,
+}
+
+impl DeadLetterWriter {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), writer: Mutex::new(None), count: AtomicU64::new(0) }
+    }
+
+    /// `$DEAD_LETTER_DIR/<stage>.jsonl`
+    pub fn for_stage(stage: Stage) -> Self {
+        let dir = std::env::var("DEAD_LETTER_DIR").unwrap_or_else(|_| DEFAULT_DEAD_LETTER_DIR.to_string());
+        Self::in_dir(Path::new(&dir), stage)
+    }
+
+    /// `<dir>/<stage>.jsonl`
+    pub fn in_dir(dir: &Path, stage: Stage) -> Self {
+        Self::new(dir.join(format!("{}.jsonl", stage.name())))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 書き込んだレコード数
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// 1レコードを書き込む (途中で落ちても失われないように毎回 flush する)
+    pub fn write(&self, letter: &DeadLetter) -> io::Result<()> {
+        let mut writer = self.writer.lock().expect("dead letter lock poisoned");
+        if writer.is_none() {
+            if let Some(parent) = self.path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+            *writer = Some(BufWriter::new(file));
+        }
+        let writer = writer.as_mut().expect("dead letter writer is opened above");
+        serde_json::to_writer(&mut *writer, letter)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        self.count.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// デッドレターの JSONL を読む
+pub fn read_dead_letters(path: &Path) -> io::Result<Vec<DeadLetter>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut letters = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        letters.push(serde_json::from_str(&line)?);
+    }
+    Ok(letters)
+}
@@ -0,0 +1,98 @@
+pub mod checkpoint;
+pub mod dead_letter;
+pub mod record;
+pub mod session;
+
+use std::str::FromStr;
+
+/// 既存のデータがある DB への取り込み方 (`--mode=append|upsert|rebuild`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportMode {
+    /// 既に存在するギャラリーは読み飛ばす
+    Append,
+    /// 既に存在するギャラリーは上書きする (ON CONFLICT DO UPDATE)
+    #[default]
+    Upsert,
+    /// テーブルを削除して作り直してから取り込む
+    Rebuild,
+}
+
+impl ImportMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Append => "append",
+            Self::Upsert => "upsert",
+            Self::Rebuild => "rebuild",
+        }
+    }
+}
+
+impl FromStr for ImportMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "append" => Ok(Self::Append),
+            "upsert" => Ok(Self::Upsert),
+            "rebuild" => Ok(Self::Rebuild),
+            _ => Err(format!("Unknown import mode '{}' (expected append, upsert or rebuild)", value)),
+        }
+    }
+}
+
+/// バッチを DB に書き込む方法 (`--loader=insert|copy`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Loader {
+    /// 複数行の INSERT ... ON CONFLICT
+    #[default]
+    Insert,
+    /// COPY ... FROM STDIN (FORMAT binary) で一時テーブルに送り、INSERT ... SELECT ... ON CONFLICT でマージする
+    Copy,
+}
+
+impl Loader {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Insert => "insert",
+            Self::Copy => "copy",
+        }
+    }
+}
+
+impl FromStr for Loader {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "insert" => Ok(Self::Insert),
+            "copy" => Ok(Self::Copy),
+            _ => Err(format!("Unknown loader '{}' (expected insert or copy)", value)),
+        }
+    }
+}
+
+/// コマンドライン引数から `--mode=<mode>` (または `--mode <mode>`) を取り出す
+///
+/// 戻り値はモードと、それを取り除いた残りの引数。
+pub fn parse_mode_arg(args: Vec<String>) -> Result<(ImportMode, Vec<String>), String> {
+    let mut mode = ImportMode::default();
+    let mut rest = Vec::with_capacity(args.len());
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix("--mode=") {
+            mode = value.parse()?;
+        } else if arg == "--mode" {
+            mode = args.next().ok_or("--mode requires a value")?.parse()?;
+        } else {
+            rest.push(arg);
+        }
+    }
+    Ok((mode, rest))
+}
+
+/// コマンドライン引数から `flag` (例: `--sweep`) を取り除き、指定されていたかを返す
+pub fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let len = args.len();
+    args.retain(|arg| arg != flag);
+    args.len() != len
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use crate::domain::gallery::Gallery;
+use crate::fbs::converter;
+use crate::import::dead_letter::{RejectKind, Rejection, Stage};
+
+/// RESP ファイルの1行 (スクレイピング結果)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RespRecord {
+    pub gallery_id: i32,
+    pub status: i32,
+    pub raw_data: String,
+    pub meta_data: String,
+}
+
+/// RESP の1行を raw JSON の1行 (gallery_id を追加した galleryinfo) にする
+///
+/// 404 は多いので None を返して読み飛ばす。
+pub fn resp_to_raw_json(line: &str) -> Result<Option<String>, Rejection> {
+    let Some((gallery_id, raw_json)) = resp_galleryinfo(line)? else {
+        return Ok(None);
+    };
+    serde_json::to_string(&raw_json)
+        .map(Some)
+        .map_err(|e| Rejection::new(RejectKind::Parse, Some(gallery_id), format!("Failed to serialize JSON: {}", e)))
+}
+
+/// RESP の1行を、中間ファイルを経由せずに Gallery にする (resp_to_raw_json → normalize_raw_json と同じ結果)
+///
+/// 失敗した場合は、失敗した段階とその段階への入力 (RESP の行、または raw JSON) を返す。
+pub fn resp_to_gallery(line: &str) -> Result<Option<Gallery>, (Stage, Rejection, String)> {
+    let Some((gallery_id, raw_json)) = resp_galleryinfo(line).map_err(|rejection| (Stage::RespToJson, rejection, line.to_string()))? else {
+        return Ok(None);
+    };
+    Gallery::deserialize(&raw_json)
+        .map(Some)
+        .map_err(|e| {
+            let rejection = Rejection::new(RejectKind::Parse, Some(gallery_id), format!("Failed to parse JSON line as Gallery: {}", e));
+            (Stage::NormalizeJson, rejection, raw_json.to_string())
+        })
+}
+
+/// RESP の1行から galleryinfo を取り出し、gallery_id を追加する
+fn resp_galleryinfo(line: &str) -> Result<Option<(i32, serde_json::Value)>, Rejection> {
+    let record: RespRecord = serde_json::from_str(line)
+        .map_err(|e| Rejection::new(RejectKind::Parse, sniff_gallery_id(line.as_bytes()), format!("Failed to parse RESP data: {}", e)))?;
+    let gallery_id = Some(record.gallery_id);
+
+    if record.status != 200 {
+        if record.status == 404 {
+            return Ok(None);
+        }
+        return Err(Rejection::new(
+            RejectKind::UnexpectedStatus,
+            gallery_id,
+            format!("Unexpected status code: {}, metadata: {}", record.status, record.meta_data),
+        ));
+    }
+
+    // raw_dataからJSON部分を抽出
+    let json_prefix = "var galleryinfo = ";
+    let json_str = record.raw_data.strip_prefix(json_prefix)
+        .ok_or_else(|| Rejection::new(RejectKind::UnexpectedFormat, gallery_id, "raw_data does not start with 'var galleryinfo = '"))?;
+
+    let mut raw_json = serde_json::from_str::<serde_json::Value>(json_str)
+        .map_err(|e| Rejection::new(RejectKind::Parse, gallery_id, format!("Failed to parse raw_data as JSON: {}", e)))?;
+
+    // gallery_idを追加
+    let obj = raw_json.as_object_mut()
+        .ok_or_else(|| Rejection::new(RejectKind::UnexpectedFormat, gallery_id, "Expected JSON object in raw_data"))?;
+    obj.insert("gallery_id".to_string(), serde_json::json!(record.gallery_id));
+
+    Ok(Some((record.gallery_id, raw_json)))
+}
+
+/// raw JSON の1行を Gallery として正規化した JSON にする
+pub fn normalize_raw_json(line: &[u8]) -> Result<String, Rejection> {
+    let gallery = serde_json::from_slice::<Gallery>(line)
+        .map_err(|e| Rejection::new(RejectKind::Parse, sniff_gallery_id(line), format!("Failed to parse JSON line as Gallery: {}", e)))?;
+    serde_json::to_string(&gallery)
+        .map_err(|e| Rejection::new(RejectKind::Parse, Some(gallery.gallery_id), format!("Failed to serialize Gallery: {}", e)))
+}
+
+/// 正規化済みの1行を Gallery にする
+pub fn parse_gallery(line: &str) -> Result<Gallery, Rejection> {
+    serde_json::from_str(line)
+        .map_err(|e| Rejection::new(RejectKind::Parse, sniff_gallery_id(line.as_bytes()), format!("Failed to parse JSON: {}", e)))
+}
+
+/// FlatBuffers に変換して zstd で圧縮する
+pub fn compress_gallery(gallery: &Gallery) -> Result<Vec<u8>, Rejection> {
+    let fbs_data = converter::serialize_gallery(gallery);
+    zstd::encode_all(&fbs_data[..], 3)
+        .map_err(|e| Rejection::new(RejectKind::Compress, Some(gallery.gallery_id), format!("Failed to compress gallery: {}", e)))
+}
+
+/// パースできなかった行からも、できるだけ gallery_id を拾う
+pub fn sniff_gallery_id(raw: &[u8]) -> Option<i32> {
+    let value: serde_json::Value = serde_json::from_slice(raw).ok()?;
+    let gallery_id = value.get("gallery_id")?;
+    gallery_id.as_i64()
+        .or_else(|| gallery_id.as_str()?.parse().ok())
+        .and_then(|id| i32::try_from(id).ok())
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, RwLock};
+
+/// 名前 (キー) → ID の対応
+///
+/// ID と一緒に upsert した値 (url など) も覚え、値が同じ場合だけ覚えている ID を返す。
+#[derive(Debug)]
+pub struct EntityCache<K, V = ()> {
+    ids: RwLock<HashMap<K, (i32, V)>>,
+}
+
+impl<K, V> Default for EntityCache<K, V> {
+    fn default() -> Self {
+        Self { ids: RwLock::new(HashMap::new()) }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: PartialEq> EntityCache<K, V> {
+    pub fn get(&self, key: &K, value: &V) -> Option<i32> {
+        self.ids.read().unwrap().get(key)
+            .filter(|(_, stored)| stored == value)
+            .map(|&(id, _)| id)
+    }
+
+    /// `entries` のうち ID を覚えているキーの対応
+    ///
+    /// 同じキーが複数回現れる場合は、すべて覚えている値と同じときだけ含める。
+    pub fn lookup<'a>(&self, entries: impl IntoIterator<Item = (&'a K, &'a V)>) -> HashMap<K, i32>
+    where
+        K: 'a,
+        V: 'a,
+    {
+        let ids = self.ids.read().unwrap();
+        let mut known: HashMap<K, Option<i32>> = HashMap::new();
+        for (key, value) in entries {
+            let id = ids.get(key).filter(|(_, stored)| stored == value).map(|&(id, _)| id);
+            known.entry(key.clone())
+                .and_modify(|known| *known = known.and(id))
+                .or_insert(id);
+        }
+        known.into_iter()
+            .filter_map(|(key, id)| Some((key, id?)))
+            .collect()
+    }
+
+    /// (キー, 値, ID) を覚える (同じキーは後のものが残る)
+    pub fn extend(&self, entries: impl IntoIterator<Item = (K, V, i32)>) {
+        self.ids.write().unwrap().extend(entries.into_iter().map(|(key, value, id)| (key, (id, value))));
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.ids.write().unwrap().clear();
+    }
+}
+
+#[derive(Debug, Default)]
+struct Caches {
+    languages: EntityCache<String>,
+    tags: EntityCache<(String, bool, bool), String>,
+    artists: EntityCache<String, String>,
+    groups: EntityCache<String, String>,
+    characters: EntityCache<String, String>,
+    parodies: EntityCache<String, String>,
+}
+
+/// 一連の書き込みの間、言語・タグ・アーティストなどの ID を覚えておくキャッシュ
+///
+/// バッチごとの upsert では、まだ覚えていない名前と url が変わった名前だけを DB に送る
+/// (言語は既存の行を更新しないので名前だけで覚える)。
+/// clone したものは同じキャッシュを共有するので、並行するタスクに渡してよい。
+/// 覚えるのはコミットしたバッチの ID だけ。テーブルを作り直したり、参照されないエンティティを
+/// 削除した後は `clear` すること。
+#[derive(Debug, Clone, Default)]
+pub struct ImportSession {
+    caches: Arc<Caches>,
+}
+
+impl ImportSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn languages(&self) -> &EntityCache<String> {
+        &self.caches.languages
+    }
+
+    pub fn tags(&self) -> &EntityCache<(String, bool, bool), String> {
+        &self.caches.tags
+    }
+
+    pub fn artists(&self) -> &EntityCache<String, String> {
+        &self.caches.artists
+    }
+
+    pub fn groups(&self) -> &EntityCache<String, String> {
+        &self.caches.groups
+    }
+
+    pub fn characters(&self) -> &EntityCache<String, String> {
+        &self.caches.characters
+    }
+
+    pub fn parodies(&self) -> &EntityCache<String, String> {
+        &self.caches.parodies
+    }
+
+    /// 覚えている ID の数 (テーブル名, 件数)
+    pub fn sizes(&self) -> [(&'static str, usize); 6] {
+        [
+            ("languages", self.caches.languages.len()),
+            ("tags", self.caches.tags.len()),
+            ("artists", self.caches.artists.len()),
+            ("groups", self.caches.groups.len()),
+            ("characters", self.caches.characters.len()),
+            ("parodies", self.caches.parodies.len()),
+        ]
+    }
+
+    pub fn clear(&self) {
+        self.caches.languages.clear();
+        self.caches.tags.clear();
+        self.caches.artists.clear();
+        self.caches.groups.clear();
+        self.caches.characters.clear();
+        self.caches.parodies.clear();
+    }
+}
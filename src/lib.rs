@@ -3,3 +3,8 @@ pub mod entity;
 pub mod mapper;
 pub mod fbs;
 pub mod api;
+pub mod search;
+pub mod framing;
+pub mod nozomi;
+pub mod import;
+pub mod pipeline;
\ No newline at end of file
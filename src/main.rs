@@ -1,26 +1,13 @@
-use axum::{routing::{get, post}, Router};
-use sea_orm::{ConnectOptions, Database};
-use std::env;
-use hitomi_server_rs::api::perform_sql::perform_sql;
+use hitomi_server_rs::api::server::{self, ServerConfig};
 
 #[tokio::main]
 async fn main() {
-    let server_port = env::var("SERVER_PORT").expect("Failed to get SERVER_PORT");
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
-
-    let mut opt = ConnectOptions::new(&database_url).to_owned();
-    opt.max_connections(100);
-    let db = Database::connect(opt).await.expect("Failed to connect to database");
-
-    let app = Router::new()
-        .route("/", get(|| async { "Hello, World!" }))
-        .route("/sql", post(perform_sql))
-        .with_state(db);
-
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", server_port)).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let config = ServerConfig::from_env().expect("Failed to read server config");
+    server::serve(config).await.expect("Server failed");
 }
 
+// DB を使うテストは DATABASE_URL が設定されている場合だけ実行する
+// (例: DATABASE_URL=postgres://postgres@localhost/postgres cargo test)
 #[cfg(test)]
 mod tests {
     use std::env;
@@ -29,7 +16,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_postgres_connection() -> Result<()> {
-        let database_url = env::var("DATABASE_URL").with_context(|| "DATABASE_URL is not set")?;
+        let Some(database_url) = test_database_url() else { return Ok(()) };
         let mut opt = ConnectOptions::new(&database_url).to_owned();
         opt.max_connections(5);
         let db = Database::connect(opt).await?;
@@ -51,7 +38,7 @@ mod tests {
     #[test]
     fn test_serde_convert() {
         let gallery_raw_json_str = r#"{"artists":[{"artist":"alp","url":"/artist/alp-all.html"}],"blocked":0,"characters":[{"character":"chika takami","url":"/character/chika%20takami-all.html"},{"character":"kanan matsuura","url":"/character/kanan%20matsuura-all.html"},{"character":"mari ohara","url":"/character/mari%20ohara-all.html"},{"character":"tsuki watanabe","url":"/character/tsuki%20watanabe-all.html"},{"character":"yoshiko tsushima","url":"/character/yoshiko%20tsushima-all.html"},{"character":"you watanabe","url":"/character/you%20watanabe-all.html"}],"date":"2022-01-14 00:36:00-06","datepublished":"2021-06-01","files":[{"hasavif":1,"hash":"1ee62c24d5eb66a91674a5feb41cb3d280b36a4f94ec776ce018e0868eb36b35","height":2400,"name":"001.jpg","width":1685},{"hasavif":1,"hash":"ed75b58ec79663b23a2e58d8061e1bfb120a51178c644dc16e83158b7b09bcdd","height":2400,"name":"002.jpg","single":1,"width":1678},{"hasavif":1,"hash":"726d5efddc9ebd3722616df29bb2f854cc8d1cd8dc7cd8fa9294ad1055f3bd71","height":2400,"name":"003.jpg","width":1716},{"hasavif":1,"hash":"d41dd0ae4477c1b3ce7dcd6f752fd34823c8b206ad7c677352e94cd7bf82fea4","height":2400,"name":"004.jpg","width":1716},{"hasavif":1,"hash":"e10f65dc234d44f57a29b880f2f3feac129b6772ce79246771b0dfab2aaccadc","height":2400,"name":"005.jpg","width":1716},{"hasavif":1,"hash":"543d84b11a92e134d084c45184a01c303385bced4d7aaf838a3aa34b470187b0","height":2400,"name":"006.jpg","width":1716},{"hasavif":1,"hash":"491def12d76d8e3a75d9d53adc8e4796c6bb47af172f119cd3a5a97193b8d872","height":2400,"name":"007.jpg","width":1716},{"hasavif":1,"hash":"83a43403e014dbc567d883df184112d05084f66000591056e7f436551cabdfc1","height":2400,"name":"008.jpg","width":1716},{"hasavif":1,"hash":"3c7c7e2ea3c79b0ec22b176d675c2fdf98d88d4caeb9748788bd2f8f0d4f3ca1","height":2400,"name":"009.jpg","width":1716},{"hasavif":1,"hash":"13550554947ebb1d599f0b78af52264d9561c75c227c0ffaf8f8b420151be0e9","height":2400,"name":"010.jpg","width":1716},{"hasavif":1,"hash":"b34b91db8ee792f6a8390426d7777c291fa60ee7f16c376390b8bba2aee5cd70","height":2400,"name":"011.jpg","width":1716},{"hasavif":1,"hash":"0c74505acdc57514c0aa6b6471b0ee6c94ff841e89805741c02cd8b6593aa491","height":2400,"name":"012.jpg","width":1716},{"hasavif":1,"hash":"1aa13200b6f690f09f489f74da7283f59ff2f5b4f22f8e3b5af5e04ca2a97455","height":2400,"name":"013.jpg","width":1716},{"hasavif":1,"hash":"a39e33758238cf3af1aa6ca39c23493f0c5ab648f7abc43cc80af0f9e9d9ce25","height":2400,"name":"014.jpg","width":1716},{"hasavif":1,"hash":"bf6288ba25c70430e6a9d18c15bb8fe91a9421eb60cf6d87a5800651284f8de6","height":2400,"name":"015.jpg","width":1716},{"hasavif":1,"hash":"40e12955ca9975f9cd3fb7a8b1f4207753c973dc53d235a8e6696c1f8642b913","height":2400,"name":"016.jpg","width":1716},{"hasavif":1,"hash":"45df3c2330870372d03310e84414cae29b94432dc33db74ecb3afb48a2e1e14b","height":2400,"name":"017.jpg","width":1716},{"hasavif":1,"hash":"16cfa110e6f1339a9863877483713dd031157a3b06d56ac00da7626e2e092060","height":2400,"name":"018.jpg","width":1716},{"hasavif":1,"hash":"d6e48f6bfb3dd0933ab23b9b89f922244859884f29e6f50a7539557c44ef3fcb","height":2400,"name":"019.jpg","width":1716},{"hasavif":1,"hash":"e06a49d30ea7a883edc9aa9415ab15408363712488d3635e210656bd758a8ce4","height":2400,"name":"020.jpg","width":1716},{"hasavif":1,"hash":"db5975ff906ce8f882b3a22c475ad108dec3b4a7a480266966aca88b09460839","height":2400,"name":"021.jpg","width":1716},{"hasavif":1,"hash":"30e2de302d1d68b037241b225ffd4ec1d0f77f56ec928377513fe395efd386f6","height":2400,"name":"022.jpg","width":1716},{"hasavif":1,"hash":"a476ce6ac90b3206012df8c2fac2f0cfb6fe4050269bfbf7d01d3b1a1ce762c7","height":2400,"name":"023.jpg","width":1716},{"hasavif":1,"hash":"08995bf454e699f6a4d4108d14c7f80de460b26ae765a307a47313eea536aa6e","height":2400,"name":"024.jpg","width":1716},{"hasavif":1,"hash":"bd4ae8248a55faee278a4465e591d3e4fe81df0aeed593a75f67e58a4fb0d89b","height":2400,"name":"025.jpg","width":1716},{"hasavif":1,"hash":"b3957e09a4713947252e1f33634ad91e324f34033eb7f4c7f58b0f3ae79af265","height":2400,"name":"026.jpg","width":1716},{"hasavif":1,"hash":"b6eaa94a342d3035d8a1bc3891bddea2f31046a62769e2f0187e5fe6bdb446cb","height":2400,"name":"027.jpg","width":1716},{"hasavif":1,"hash":"e4c2ec5296f85f2f35ea63fce7371e0e194c00d80ac3aeedb086a5cbe82594ec","height":2400,"name":"028.jpg","width":1716},{"hasavif":1,"hash":"2b1a1bf9efe99458d6d944eaf1b41d51352c55715b8714993638fb5cd36b1c5d","height":2400,"name":"029.jpg","width":1716},{"hasavif":1,"hash":"1bf3a60794a049085e8c2b165162c1045e3641d865d6348c385dd38371c4b95f","height":2400,"name":"030.jpg","width":1716},{"hasavif":1,"hash":"04a4a07c61f4fab30bcea51c0b8f6d055011628cfe53b5c28ae071c6444fa61a","height":2400,"name":"031.jpg","width":1716},{"hasavif":1,"hash":"3de721a2999ac04981e6c4c2387054a92b043829c4f272bf8466144866b774f1","height":2400,"name":"032.jpg","width":1716},{"hasavif":1,"hash":"f03736cb869350154e910008991fde3b7920643f4cee23f515fd6a5340626e28","height":2400,"name":"033.jpg","width":1716},{"hasavif":1,"hash":"047c8bdd920654869d77ea159272e03988b3865cb2cce2427731ea4960112997","height":2400,"name":"034.jpg","width":1716},{"hasavif":1,"hash":"7b1feadabb8bf3f5d669ac311cd6ede88eb6567d79ae5c7d37e2dc098166fe90","height":2400,"name":"035.jpg","width":1716},{"hasavif":1,"hash":"98493bb60e299d5a6f23a1b5642a44edff3030f05e386378df19d1939ea8546c","height":2400,"name":"036.jpg","width":1715},{"hasavif":1,"hash":"697393f2307da7ee4753d246f240833b8b1eec48c83dae2e3d387a2479815bce","height":2400,"name":"037.jpg","width":1716},{"hasavif":1,"hash":"32f6bd06f22af546e04b176b321c1c83b6dc3715340fefeb6e0a811ac40c6020","height":2400,"name":"038.jpg","width":1716},{"hasavif":1,"hash":"1b100c3c0a7d30b617acd75a1f0eaac2be0405af19a1d1359c842858a9226f16","height":2400,"name":"039.jpg","width":1716},{"hasavif":1,"hash":"5846d2738f52cb132b58326168dc6bf91ee7660eee1b781fdeb7d40b5c486c5a","height":2400,"name":"040.jpg","width":1716},{"hasavif":1,"hash":"8a14514886436da170e3240a2ddf2046c741b799d4080a80a07c2857f372666a","height":2400,"name":"041.jpg","width":1716},{"hasavif":1,"hash":"0ac82ae9c1d809e516ad7bb3b6944ae2d3e8fdd0bda50d53edf798e8846551b9","height":2400,"name":"042.jpg","width":1716},{"hasavif":1,"hash":"9cf177d3ff6723083d84d6ee214fd902d1e367db2d42a0c0f94c41e22a8f33e7","height":2400,"name":"043.jpg","width":1716},{"hasavif":1,"hash":"630c803f2bbd3cf244759bbd1e404ecf47fd772b169fc892464061effbddc040","height":2400,"name":"044.jpg","width":1716},{"hasavif":1,"hash":"b3b8d475861165571fc5a0d887a4042c958fdf39341d8c7350a355cf4aa9d37e","height":2400,"name":"045.jpg","width":1716},{"hasavif":1,"hash":"ff27de2b8eb5f3f613d45d748fe7b94aeb12dffcfa7c19dededb0c7ada047927","height":2400,"name":"046.jpg","width":1716},{"hasavif":1,"hash":"28b191b0bc4a0bfb1e256a0d07ea6b5422b8bdc6c8f34c83d2eb2474fe2d4506","height":2400,"name":"047.jpg","width":1716},{"hasavif":1,"hash":"644ee6c57f3e74a07721486639138435962d661feb939f62ca2e52a2a7a672cf","height":2400,"name":"048.jpg","width":1716},{"hasavif":1,"hash":"c787a7bf2948d759f99b6ee85aa281471f1257f9de1e404539b8380850e2b460","height":2400,"name":"049.jpg","width":1716},{"hasavif":1,"hash":"e35eea14a3f12a462c92d9eafea105ea235ce8ae5623360fd3c6362a7e4c4306","height":2400,"name":"050.jpg","width":1716},{"hasavif":1,"hash":"5ccfb0c49a69018a1d9f9455b8c236566ae1fe1fed6a40d09b9a96224dab0f08","height":2400,"name":"051.jpg","width":1716},{"hasavif":1,"hash":"33cbe790fa64e3913d90691cc688ff584811c194da7622a46f019d7c328ee040","height":2400,"name":"052.jpg","width":1716},{"hasavif":1,"hash":"b77f10afe8ae0f81d55820d529337b9ff410b68b92a716deaa758ff5a81d930e","height":2400,"name":"053.jpg","width":1716},{"hasavif":1,"hash":"4d8bbeafc2294c6489ff7c635feea60b06177c856451cb5f024ca865220ddb24","height":2400,"name":"054.jpg","width":1716},{"hasavif":1,"hash":"61250c4a740e8ba71dff64b1e13db85e26507a20b7f68aedacc0bb997d6d71a4","height":2400,"name":"055.jpg","width":1716},{"hasavif":1,"hash":"08606d64e2a028c88738e0751a26521c141e47a3cef1065399c6268504e8f0bb","height":2400,"name":"056.jpg","width":1716},{"hasavif":1,"hash":"4a042e9a8073984c16994f315f89d1e93a82f9acd55e447de1744e4a357987cb","height":2400,"name":"057.jpg","width":1716},{"hasavif":1,"hash":"4bd05fd5973cb19515e6d10faf5a143083bc41fb90122b5e2e70a99073127336","height":2400,"name":"058.jpg","width":1716},{"hasavif":1,"hash":"83507c94ae5649e79a2d1d4c7b813c54e37bd1d103870bb9619a0401bb71015e","height":2400,"name":"059.jpg","width":1716},{"hasavif":1,"hash":"3f337b0d8559d5196a07a6c02f29b7a9e158c580d55777805b04543721deddff","height":2400,"name":"060.jpg","width":1715},{"hasavif":1,"hash":"93ac1bc7401dc01f2f7710506b134e08b4e65089f707e73dfa09b63983c2c7bb","height":2400,"name":"061.jpg","width":1716},{"hasavif":1,"hash":"9de86f07ad7623475936c99ff9a191ae2fd94f6a4ce409f7f72d551c59d7cf96","height":2400,"name":"062.jpg","width":1716},{"hasavif":1,"hash":"a95cfac643863a08c39d7a333d86edb1d753f524cfacda5c73d0ffc694cde1a1","height":2400,"name":"063.jpg","width":1716},{"hasavif":1,"hash":"7deea8d27f1ad4e61f324b015208364133744c32e9a08f741d1fd789f777d49d","height":2400,"name":"064.jpg","width":1716},{"hasavif":1,"hash":"1a80b2f630985ff85b4de677469e806458c8a87a736fb134e69a2d067242ea46","height":2400,"name":"065.jpg","width":1716},{"hasavif":1,"hash":"d30b806f8e74250cf0f247beacd67169a787ad5c7751a6f5e68414876514a855","height":2400,"name":"066.jpg","width":1716},{"hasavif":1,"hash":"722db7f6c7886cf86768c5f868ccb8e42c0e58ba5072460f83155134e2550057","height":2400,"name":"067.jpg","width":1716},{"hasavif":1,"hash":"0ae99bd64c8e65693699b966942753966efc923b8837815280c4187e52e5b337","height":2400,"name":"068.jpg","width":1716},{"hasavif":1,"hash":"62eda346e2f6ccdd587ee6bc368459246e15d1c6bdbcf9cafa974d04f1a9349d","height":2400,"name":"069.jpg","width":1716},{"hasavif":1,"hash":"322dbbba8170826c1dee7b58e1c2fb2d4f8e82e83cbf459d2a250dd51d5b6fa0","height":2400,"name":"070.jpg","width":1716},{"hasavif":1,"hash":"05718e77b4b8c7f8bced29bfe107ed2690cc45b26ca2d97cadc70c634cd2e446","height":2400,"name":"071.jpg","width":1716},{"hasavif":1,"hash":"68598e7cff239dbfd825a21a9c55046228e1924c01fc9d14394f97cdb019b410","height":2400,"name":"072.jpg","width":1716},{"hasavif":1,"hash":"53c7f00587cb4e60383f7ebeffadf4fba6cef33166846e1e0c8d5bd8de143c8c","height":2400,"name":"073.jpg","width":1716},{"hasavif":1,"hash":"676619d3c32b16854fc409d8f8ce448d144cd7b05966f2d809e61b9232158367","height":2400,"name":"074.jpg","width":1716},{"hasavif":1,"hash":"39d7955fb84c4a7d757f7e8cc4bbe0361e8f2c29be6aeb96e539f37b7ba27f64","height":2400,"name":"075.jpg","width":1716},{"hasavif":1,"hash":"25a692cd85d2487337c7f2f80f2c2088ecb68623f79c8c1ae93d691f4620e813","height":2400,"name":"076.jpg","width":1716},{"hasavif":1,"hash":"964bec2cc806c105e39377656787f91159ed3a5c791e4feab83ca45d5158722f","height":2400,"name":"077.jpg","width":1716},{"hasavif":1,"hash":"512d4d2e9f7bd78d1da4f1f980d3d0e28baefc273ba79752fc2d6c79af6c2afb","height":2400,"name":"078.jpg","width":1716},{"hasavif":1,"hash":"023054f45f3cac13985efc27a2baf78809ed7f777e1a8ec5af1f8f3714d4cd32","height":2400,"name":"079.jpg","width":1716},{"hasavif":1,"hash":"68e76771ae37de17684bee5d0a6d0b71a9836460ff6e4c40684361694be68f38","height":2400,"name":"080.jpg","width":1716},{"hasavif":1,"hash":"cb6a560aba42a119b178a07c9b8b19ee289050d0887b2994b7c56d3644c6e076","height":2400,"name":"081.jpg","width":1716},{"hasavif":1,"hash":"cf7da96ef26537e21bf4f8b3bcab0e866a73e327295a8f625d79aad009f27cea","height":2400,"name":"082.jpg","width":1716},{"hasavif":1,"hash":"4b2da20ecfd67f148b4045eb95c898ebce211b6913ea80fa379392d85eb4bdc9","height":2400,"name":"083.jpg","width":1716},{"hasavif":1,"hash":"dae7df0545155e3358f575d3e4703edde0ed5b9488f3ce619106db0a2282f389","height":2400,"name":"084.jpg","width":1716},{"hasavif":1,"hash":"733382ef007dd981cab333a69f9bbdcea96e3774860a6f48eff5821ede7c20b1","height":2400,"name":"085.jpg","width":1716},{"hasavif":1,"hash":"253bb642b0e316cb7945fa86b2fcfd72be81daf13727e03801ed4acffebf2788","height":2400,"name":"086.jpg","width":1716},{"hasavif":1,"hash":"e01d6a0fd0ef6b0bfea1b1480e3c7b1f97ac22a9032b37a33a3c7e9be05ac542","height":2400,"name":"087.jpg","width":1716},{"hasavif":1,"hash":"874682c6af7fa1fe9a9154e067938a60f5b7b1cabe204168fc94ceed5cb7bdf1","height":2400,"name":"088.jpg","width":1716},{"hasavif":1,"hash":"98f23732f80737bf60897446e3bfb97ee32e7a5bd4fcd1f55eb5e3f70b288544","height":2400,"name":"089.jpg","width":1716},{"hasavif":1,"hash":"3729479e23fd732a54d4dac040eea6065ec40142901f012469e9f74cf69b7218","height":2400,"name":"090.jpg","width":1715},{"hasavif":1,"hash":"be48ed9733b18718b3ad558d8ba6c45bcd68fa91fb818207aa36778e276ba4a3","height":2400,"name":"091.jpg","width":1726},{"hasavif":1,"hash":"dcd6495e108c6296ccc5fec489091fcaff1364700653b6e2d07ca363e1ed0c63","height":2400,"name":"092.jpg","width":1726},{"hasavif":1,"hash":"4aca91b845f6d02f46d25bc8832ad68d5bc0eadd7a12f26107f587e27a669b58","height":2400,"name":"093.jpg","width":1726},{"hasavif":1,"hash":"0ccfc750503384d0d5b4bb95f4da3c07fbc08d22ef287822bed1813b92dfba58","height":2400,"name":"094.jpg","width":1726},{"hasavif":1,"hash":"6cc675cbbfc45884cbae4dbb7044e21ecab31396058e9296e4c4580550390f81","height":2400,"name":"095.jpg","width":1726},{"hasavif":1,"hash":"f8853f991ee5f8b5123dcca789c373c5834088f57898e7e48dbe71f85dfb67af","height":2400,"name":"096.jpg","width":1726},{"hasavif":1,"hash":"47f46ddee7bc17e76320ad3d29d07089f642e31d603946a1d418da4f662449d4","height":2400,"name":"097.jpg","width":1726},{"hasavif":1,"hash":"772d3567d8d2f54141a1808bcd1941a789a2079bfc150f3271c2e458e9e1e5b4","height":2400,"name":"098.jpg","width":1726},{"hasavif":1,"hash":"39865a9a4ed0f5342699fdd465b1652d6f0eadd753bf44b76453b1a6ec3290ce","height":2400,"name":"099.jpg","width":1726},{"hasavif":1,"hash":"d89ca86281bfc70e865f7f528ce604c3e754462817e9a614fba2a350593c9e31","height":2400,"name":"100.jpg","width":1726},{"hasavif":1,"hash":"de7b90e3c5ad2a420a79aae3330bd4f2fde2c5ec22a88f2896e4e7f3be51761f","height":2400,"name":"101.jpg","width":1726},{"hasavif":1,"hash":"13902ddb96248b8d026340c8dde6245a069177918065b71a2a90fde3df9b18d2","height":2400,"name":"102.jpg","width":1726},{"hasavif":1,"hash":"403894db9073c0193affcc15aee637684381f14bc56035f147b4ae04d637a04f","height":2400,"name":"103.jpg","width":1726},{"hasavif":1,"hash":"2f115e5a8851af436a4ec280d65ad5d2b394dbfc564ac9aa19977a53edb407d5","height":2400,"name":"104.jpg","width":1726},{"hasavif":1,"hash":"819c9daf4b80d3b56c1cc7a8cfa6603a5723f91810ceb04a8a6e49fc7bcca049","height":2400,"name":"105.jpg","width":1726},{"hasavif":1,"hash":"f2d197c1642a1b131c94d8a9b7e63f8c5b2f48c3c9da90b138316eef5398d2be","height":2400,"name":"106.jpg","width":1726},{"hasavif":1,"hash":"6cd3b8513f9b3110fd9dcac387a1bac64f4acd8fbe9ffb39e57a79815381800e","height":2400,"name":"107.jpg","width":1726},{"hasavif":1,"hash":"9d69d72f912995aed66e71985daa4ec9e5fe7e1e505650eb6f096b7515b99d83","height":2400,"name":"108.jpg","width":1726},{"hasavif":1,"hash":"8f360c6dc18a0ca35f7644a7c3d84dc5b5424e94591e2bbb88da8ea28ae8589a","height":2400,"name":"109.jpg","width":1726},{"hasavif":1,"hash":"67d379f9a7a07f227e20b5e80735f1ed062b6348afcadecdc50295fdad9b8bc7","height":2400,"name":"110.jpg","width":1726},{"hasavif":1,"hash":"28b35617b8fcb81feb2fa909e16e520eb33bc4cdfe455d7fb0f8922479dc1fb2","height":2400,"name":"111.jpg","width":1726},{"hasavif":1,"hash":"2258314bc143cbe8ff226af936966a89393e10ca57a5b0b54988223b52e345ee","height":2400,"name":"112.jpg","width":1726},{"hasavif":1,"hash":"4f0831fba337a19b79f6b4b1f585b65e1de5d2ee84c3a90cbb93a730d69348a6","height":2400,"name":"113.jpg","width":1726},{"hasavif":1,"hash":"8154aab7c84913389ac61595cbd1f99686f965b9d35625d0876ee8722477d330","height":2400,"name":"114.jpg","width":1726},{"hasavif":1,"hash":"ec6fc5b4032b9925e102cbd50b2369a2fab400da44923844ddedc25440f52979","height":2400,"name":"115.jpg","width":1726},{"hasavif":1,"hash":"7837bab95fcc08e8de60ce8f96725dbeece651096871ab918789edfe56b642b8","height":2400,"name":"116.jpg","width":1726},{"hasavif":1,"hash":"3aa72d127aac46df8376ed019cb6a207332b3ba612c4168d0fa29f70401ee84e","height":2400,"name":"117.jpg","width":1726},{"hasavif":1,"hash":"5a67bcb91c8abc293cfb73c7d7cb9be1262c60f78306eab90d5a3791d8f8abf8","height":2400,"name":"118.jpg","width":1726},{"hasavif":1,"hash":"ff45567c05895f028bfdaabd95a187fd86b3bd82799f7500a59287d8ab58343d","height":2400,"name":"119.jpg","width":1726},{"hasavif":1,"hash":"036e4c70d22c397df61832c1013c6488a3f43cec4012412291244054a5dec6a8","height":2400,"name":"120.jpg","width":1726},{"hasavif":1,"hash":"1495426f3d1fd33db83f152b5896f1fce1351f1e399c0a82a6972cb97eb04b68","height":2400,"name":"121.jpg","width":1726},{"hasavif":1,"hash":"ef1a107d35e76025b73c8cb0d3770694d915ca70afb052a88b158056b15ba5e3","height":2400,"name":"122.jpg","width":1726},{"hasavif":1,"hash":"3303013ab63fb562b3a785221f17b6afc33d55c39ac360a97aa503792a06444d","height":2400,"name":"123.jpg","width":1726},{"hasavif":1,"hash":"32089954c2136bd691ff58feaee70cdd30b03509eaa9ad623224867d52e5f47e","height":2400,"name":"124.jpg","width":1726},{"hasavif":1,"hash":"3f51c63c55d7ede80bbc75e775bbdc7235a38f09a5dcee9fcadf26099c91bdb2","height":2400,"name":"125.jpg","width":1716},{"hasavif":1,"hash":"3e87b7ff652e97ea5753a88baf402512d9b37a5d5738942a52663f7bb115783f","height":2400,"name":"126.jpg","width":1716},{"hasavif":1,"hash":"9c598de145ae6e7ffbe0c01b5472d3780e4cb88d1821e548408c36e44718c1af","height":2400,"name":"127.jpg","width":1716},{"hasavif":1,"hash":"796c954df88a41418943040849cfd732db58df66123d23452144baca945239db","height":2400,"name":"128.jpg","width":1716},{"hasavif":1,"hash":"839f9da6441dc2c25cb76400b9982af2a68592e749e5ff8d1e63ea31b32fded1","height":2400,"name":"129.jpg","width":1716},{"hasavif":1,"hash":"665098a2e52b8da7f5a6c0e08303466f33e1e51e3004cf5360e25c7e3ad3785e","height":2400,"name":"130.jpg","width":1716},{"hasavif":1,"hash":"98cddb755a098c86af873f78c1771a2cf3998b6b1ee31c6ad829687394902007","height":2400,"name":"131.jpg","width":1716},{"hasavif":1,"hash":"115fc07b57beafa94929f0d8f57a292f0f4d8581c35bcfdc9e229767e1e0b0b9","height":2400,"name":"132.jpg","width":1716},{"hasavif":1,"hash":"812e710b1a7a44c9374fd2adb9c1650c30c1dfbcb6d680661d28c2b85c250968","height":2400,"name":"133.jpg","width":1716},{"hasavif":1,"hash":"66e5cbe5b8b8ced7f54fb35839db83faf7ad1e10077af9b82631818dfba8e181","height":2400,"name":"134.jpg","width":1716},{"hasavif":1,"hash":"d419ff5d8973cc7e21c20c89012749af433715bd8d1fac6ec960b87bd4410d76","height":2400,"name":"135.jpg","width":1716},{"hasavif":1,"hash":"2706db087c30047a2e4a9a9ce2c71e221d4ca1b9ed31d46c66efa3b29171a9cc","height":2400,"name":"136.jpg","width":1716},{"hasavif":1,"hash":"0bf064d6a6556832ecc0f6b511ba7d8af839eaffeb2ac7d6dfecb7238d8a5634","height":2400,"name":"137.jpg","width":1716},{"hasavif":1,"hash":"c027cab28a94720bf80509ddcf7219254f2a1791ea0db2d32b5b7da65080b7f5","height":2400,"name":"138.jpg","width":1716},{"hasavif":1,"hash":"4e2b4a983d0d206f8c54aed8e502543badb1ae35fe8a0b494673073a7acfcb90","height":2400,"name":"139.jpg","width":1716},{"hasavif":1,"hash":"b0e2113421e5c5c6a2cd1d473a8e3f96ccc23bfd115162e4dbcceac48b6ef9f2","height":2400,"name":"140.jpg","width":1716},{"hasavif":1,"hash":"e996458fe246a0a62e2fba3c7c80b4e53708f63279fc0a383d2015d38924e7d3","height":2400,"name":"141.jpg","width":1716},{"hasavif":1,"hash":"025b666797201fba873f0364c3240823b2cc0354c3199874532ec790f6a0602e","height":2400,"name":"142.jpg","width":1716},{"hasavif":1,"hash":"7bf5774ee79e773e69561e2c1c2ca4f48ecd4ce87e202a10bc01665a6d80ec3c","height":2400,"name":"143.jpg","width":1716},{"hasavif":1,"hash":"ea0a358a9541dfcf34e2aa6763975ca23826b14d17ac3bcfbb22da3967832483","height":2400,"name":"144.jpg","width":1716},{"hasavif":1,"hash":"2dfe8ddade26e49d149125181f5d12cd4f97af72c6306f1587cd3d021ff4a9e4","height":2400,"name":"145.jpg","width":1716},{"hasavif":1,"hash":"45170fd5f5a8ae04213f107792cb1fe8f2b86aa03483bbee00e161ac147c454e","height":2400,"name":"146.jpg","width":1716},{"hasavif":1,"hash":"4b9592fc4952d6f49e85da4a0283445115e0156b28beb99a75ca61ad26d5b471","height":2400,"name":"147.jpg","width":1716},{"hasavif":1,"hash":"895c27defa640c0a79e90884c85c6892b155ffcae3e9cbe9ff135cf6cd44ba6b","height":2400,"name":"148.jpg","width":1716},{"hasavif":1,"hash":"399d8c5819d510350a9f941b81bfb7bdae615977adac9b31c1ca0b520879fc8a","height":2400,"name":"149.jpg","width":1716},{"hasavif":1,"hash":"cb584ade8fd1386fceca971d15753626ebe649db721e1564b72cee342feb5541","height":2400,"name":"150.jpg","width":1716},{"hasavif":1,"hash":"657d708165dba1025f9f32db29413c755ba18d17f3f08ca577f2015cadcd66bb","height":2400,"name":"151.jpg","width":1716},{"hasavif":1,"hash":"874986ab5ced66c0291e1482d1715388c8b610ddb02286b2fd4c2a33c0d27d17","height":2400,"name":"152.jpg","width":1716},{"hasavif":1,"hash":"ab302d61062306ea3f102e7f7bbf1eb368af71839c7e59ae0ef02d1ffb9a8f78","height":2400,"name":"153.jpg","width":1716},{"hasavif":1,"hash":"126bbdb717071e5eee0c13c0d24ef0beef817e425901fe909c7270e6f372962e","height":2400,"name":"154.jpg","width":1715},{"hasavif":1,"hash":"1c8226fc0d3d0168169e02826bf5fdc4c576dd8f977f0bf80e8862eb1542970e","height":2400,"name":"155.jpg","width":1716},{"hasavif":1,"hash":"25c4b43e7df9b2ffbdaad7eb95bff5f97a2e0757623d65a9fb1cf5f380371012","height":2400,"name":"156.jpg","width":1716},{"hasavif":1,"hash":"87af2942363452f0fadfeca98dbd75752ecd3bd1d0a694d02eec4df8be66efcc","height":2400,"name":"157.jpg","width":1716},{"hasavif":1,"hash":"8d7f1c87d823e1bf074b234b7f38d75a9e075005703039f601675a140cd0e68a","height":2400,"name":"158.jpg","width":1716},{"hasavif":1,"hash":"7c157104d05cf20977e1e821887ad74fca626a5c7ea63ffc93b7d78205f2ef6c","height":2400,"name":"159.jpg","width":1716},{"hasavif":1,"hash":"e559c11e0fa58f6e9621979056e68d8bf56a838151b0b6fab8d0fbb75bff05e4","height":2400,"name":"160.jpg","width":1716},{"hasavif":1,"hash":"9e9805b34a7675cb8b6934e253cd506f5f19fd7c0fe28ab947b1de87b09b3194","height":2400,"name":"161.jpg","width":1716},{"hasavif":1,"hash":"91b0e2e539e4d4560b86423d41f3c3073c622526bd48db97f520e52230c338cb","height":2400,"name":"162.jpg","width":1716},{"hasavif":1,"hash":"1080d5ce703b537c6c131f2ec15f991169dc8045d49f050f75a45e19c5af7760","height":2400,"name":"163.jpg","width":1716},{"hasavif":1,"hash":"8b5eaf6ead426fd18f34146b53b87931931bbe8dca4bc86156ac9edfd8128430","height":2400,"name":"164.jpg","width":1716},{"hasavif":1,"hash":"4287adee64d53ae8c7ac2ef63d426dd031489efce2a8ea3749b7e04e7cfea73f","height":2400,"name":"165.jpg","width":1716},{"hasavif":1,"hash":"5b3ab026bfb4703d1c8dc44b322e537343eecd8aa198f7ae6bd4d94f6b351b88","height":2400,"name":"166.jpg","width":1716},{"hasavif":1,"hash":"ef7622df534975498173629072baad972a99c846e754b6eac00d71a4603ad7d8","height":2400,"name":"167.jpg","width":1716},{"hasavif":1,"hash":"8ef1edef7e959ffc36a6a69a3161be0546d4fc4c37154f5f9f911bf552ee2030","height":2400,"name":"168.jpg","width":1716},{"hasavif":1,"hash":"148fbb392d4986d5e8c1f4c488c3680d5896489fa45cbcd4cf3c3531cead77a6","height":2400,"name":"169.jpg","width":1716},{"hasavif":1,"hash":"7cdfde5f50b59d9e1ecbb192a3e3438da7f84dab5b663d28a86525697f6c38d2","height":2400,"name":"170.jpg","width":1716},{"hasavif":1,"hash":"e38d6d2b4a2ff09d232c3c2c89398efa1f19c1f3399ca561d34a20dc87582b5a","height":2400,"name":"171.jpg","width":1716},{"hasavif":1,"hash":"e3f440f2ce8239da3a7eff5647bbc293b7329c46eecd01ae6918d79cf5135b89","height":2400,"name":"172.jpg","width":1716},{"hasavif":1,"hash":"7ab7d1a0fbd40ffedfcfcac47a3da329eb8982d46a3ed0afd2d2cc005300c4af","height":2400,"name":"173.jpg","width":1716},{"hasavif":1,"hash":"569be8e974f24ff4c6f8488689e7b71d58578651f1197a0a5aaaede56f1a50b7","height":2400,"name":"174.jpg","width":1716},{"hasavif":1,"hash":"9b2a16cc4427366c9b0c4c4d10f2c0e9f73f9df9a8df4b07a72866cbb8f26202","height":2400,"name":"175.jpg","width":1716},{"hasavif":1,"hash":"c05ca0696905f50bc1e9508e7a5606acb1d826bfe6c6fdac614da022476fa702","height":2400,"name":"176.jpg","width":1716},{"hasavif":1,"hash":"88578e844dd682fe40acdc2384edef8dc2c228e40cf1aa10d1ece55acddb6abe","height":2400,"name":"177.jpg","width":1716},{"hasavif":1,"hash":"3bbbf6dc6ca851f4a73325cb2ce7a4898c96b9936a74ccc36c63e3775d0216df","height":2400,"name":"178.jpg","width":1716},{"hasavif":1,"hash":"10894a2865a2e1b8bc9ef17a3a731f6ff6d0d99595eace9e97f11a5d5d0d1619","height":2400,"name":"179.jpg","width":1716},{"hasavif":1,"hash":"438fa0a7c7fb7d30408c9082526972698cf52d40c2206e326b2a863fb8ca16fe","height":2400,"name":"180.jpg","width":1716},{"hasavif":1,"hash":"df9223d3cdba46e755eb5d7a1ad5ff1fcbe003a9409c9fcd406e78cca5625531","height":2400,"name":"181.jpg","width":1716},{"hasavif":1,"hash":"f114567c4fb32ec542e5d3e1ce9b01eea95f3e85e667b77abcfbc8d84c4bd9cf","height":2400,"name":"182.jpg","width":1715},{"hasavif":1,"hash":"20f520370688a1b331bee5899b06bf700ec8f4bf618a112aebc1fa4659ebf34b","height":2400,"name":"183.jpg","width":1716},{"hasavif":1,"hash":"ea8ed8d8a5ebe01ef44390026d8384467904a1ccf9aac14c595e46a9878bf6e0","height":2400,"name":"184.jpg","width":1716},{"hasavif":1,"hash":"86e48d8a7d2658a2aa5d3106208e71a0b43ddca66127b23ee3d0e09405184d6e","height":2400,"name":"185.jpg","width":1716},{"hasavif":1,"hash":"c067d2595a8fe63536cb3a98ef5503d63cfd4d05cfc021e4354d6d67e031c46e","height":2400,"name":"186.jpg","width":1716},{"hasavif":1,"hash":"086c181e0837466ef06e087969e55749c4831382cac55afd5179b52e80763bdc","height":2400,"name":"187.jpg","width":1716},{"hasavif":1,"hash":"03092bb444732ceb1885814cedbec5c87469602d2ac2f6550ea7298990268019","height":2400,"name":"188.jpg","width":1716},{"hasavif":1,"hash":"126ff03203196fc381af7cfdd29d574a7b74ab8362828e3f493d61a04a9b5568","height":2400,"name":"189.jpg","width":1716},{"hasavif":1,"hash":"9faaca36879a0f09e46a433df6e12cb81cbe4c874d95900f079c99fc4c2f61f8","height":2400,"name":"190.jpg","width":1716},{"hasavif":1,"hash":"e3f2ad03cff8b54daa62ae32897d4a8bf3acc10ed70aea4cd5338ea6081eda84","height":2400,"name":"191.jpg","width":1716},{"hasavif":1,"hash":"1e23ccae085422ae3b7ac4fff2e847f0c6b71abf2175dd6d275a60248c56a4f6","height":2400,"name":"192.jpg","width":1716},{"hasavif":1,"hash":"c9422e206b32a80628d5e96afde03cf8017a60b5f4d65b50ec0c278064d01fc1","height":2400,"name":"193.jpg","width":1716},{"hasavif":1,"hash":"9bbe4bc3d76cf933f753564d113fdceb312be3104b4dc708a64147868b10a3fd","height":2400,"name":"194.jpg","width":1716},{"hasavif":1,"hash":"65ea472a15598aea98f1ed82eab67a724495adee996d003441332f2c333ee961","height":2400,"name":"195.jpg","width":1716},{"hasavif":1,"hash":"7bd39d6fd9bea0ef3de08d6cf0b481d896ae345002a851f57b0ebe1130de5f5b","height":2400,"name":"196.jpg","width":1716},{"hasavif":1,"hash":"f284b163249d63917a977c35b15a0ded967b5205daab64278b758d4832ce5992","height":2400,"name":"197.jpg","width":1716},{"hasavif":1,"hash":"ace090d2171c2f91b6eb2ddd4338ae0fff3f610b111382977daca543a23d7411","height":2400,"name":"198.jpg","width":1716},{"hasavif":1,"hash":"0489b6ed1fd3677a9fe33ba17013a516a960828f149294241ee965be95c66426","height":2400,"name":"199.jpg","width":1716},{"hasavif":1,"hash":"27b47fe4392e515c8495fa8951c09f941c018b99338b2b32fbcde05a570daacc","height":2400,"name":"200.jpg","width":1716},{"hasavif":1,"hash":"6db5d5be2bb82674cfa8684fb209b0be95bff16dfa475ee324b08b6742f2efe5","height":2400,"name":"201.jpg","width":1716},{"hasavif":1,"hash":"955a05133f3c7d1d48f05c0535fe84e56efd7ea644d66654fb594e82dacffca0","height":2400,"name":"202.jpg","width":1716},{"hasavif":1,"hash":"de303b9e082fc1139cbd70fd6118e85cd93ac2df8e0dce6dd682c2cc5fba6f49","height":2400,"name":"203.jpg","width":1716},{"hasavif":1,"hash":"2b82542fefddd61d3a658f9737f0ee93b9a5ff75cae2917c6a8fbd87733fee29","height":2400,"name":"204.jpg","width":1716},{"hasavif":1,"hash":"c24d9643028098bb62a1e7af9cd0a2f10b703ea2a362dd939969fc61304007b3","height":2400,"name":"205.jpg","width":1716},{"hasavif":1,"hash":"50adcbe54e0909dc4ceec3361cd560abc32e08188bc5385e11972021ba444e9b","height":2400,"name":"206.jpg","width":1716},{"hasavif":1,"hash":"7d51d487b599bae3ef5f12fc736fca97644cd22ed98937f546191961f35dfb4f","height":2400,"name":"207.jpg","width":1716},{"hasavif":1,"hash":"f3e256076d17801641f99c0611a191393269ec193dfb12118f70ba328219b971","height":2400,"name":"208.jpg","width":1716},{"hasavif":1,"hash":"466941a507c11e4aca723ff622e9e457c904fa597ef05d6ef2dcc97d43bb8150","height":2400,"name":"209.jpg","width":1716},{"hasavif":1,"hash":"cc26e6aced29d393c2700c87f6ff028e29a141a51fe8f2a840eca71d0fee3923","height":2400,"name":"210.jpg","width":1726},{"hasavif":1,"hash":"14d17174a324ff30f4d602d33b90129b93c99f051cc1231e6b8a780ccd504526","height":2400,"name":"211.jpg","width":1715},{"hasavif":1,"hash":"976c7143c87ec38017d3f9c74f68a9b09d98105959a98c33c7c4938bc60655d8","height":2400,"name":"212.jpg","width":1726},{"hasavif":1,"hash":"1322fd7094f30b8fea727dc5bbd51e730bd2bca138cbc3b706fe06b233b71bd9","height":2400,"name":"213.jpg","width":1726},{"hasavif":1,"hash":"b1608b16bfea9b437cb4dd0a69a66c797ddd04cae6f9f5fa889d5fceb39a7ff0","height":2400,"name":"214.jpg","width":1726},{"hasavif":1,"hash":"d9e55cf5f553078df2422e283151f241739b86ad2912861ea2d6aac094c72bbc","height":2400,"name":"215.jpg","width":1726},{"hasavif":1,"hash":"ed502acc43e8f7664884e6dd61a611b34bc576db4c3996d1d08e4ad2ad2a42db","height":2400,"name":"216.jpg","width":1726},{"hasavif":1,"hash":"63d869da2350d666347fd1e06d1f12f42f1ed22bbe34766e91a1e6efb65ce040","height":2400,"name":"217.jpg","width":1726},{"hasavif":1,"hash":"b6ab7f00e705b1f907e1b953593f3dc4ec60b73bc0cac5568b9ea1d3f32c7a53","height":2400,"name":"218.jpg","width":1726},{"hasavif":1,"hash":"31fa8674e9c962687a269b0dee336069bcac0d617bce2b1cde1e88228b1dc2e9","height":2400,"name":"219.jpg","width":1726},{"hasavif":1,"hash":"d8dbc31cda71cb6b3b7648c93ce65aea91add601e57943019303b81501e29e5a","height":2400,"name":"220.jpg","width":1726},{"hasavif":1,"hash":"4345804078f4c0cb054596ce2a87349fd365a35f2066abd043fa6862a61338dc","height":2400,"name":"221.jpg","width":1726},{"hasavif":1,"hash":"d4edc47e79a69a59dfdefbf7f0610c524d6be318eb6eec936a819c7fa5a7da5a","height":2400,"name":"222.jpg","width":1726},{"hasavif":1,"hash":"e03561f0276dcd93fbf206b9a107997273c609ceceaf9ba44b217cb6a713b6b8","height":2400,"name":"223.jpg","width":1726},{"hasavif":1,"hash":"1ddf0c54068d07ffd3f1aee45fb8016c2620011ddecb181352c3751aafc89119","height":2400,"name":"224.jpg","width":1726},{"hasavif":1,"hash":"511ea01926c54e74a2e21d7f055144c812b2ce8277bdbd35590615f59a1ff2e2","height":2400,"name":"225.jpg","width":1726},{"hasavif":1,"hash":"8d33a59da4187490a3ef7cb8d134c1f2733594693a8f5eaeecb9594645d949ab","height":2400,"name":"226.jpg","width":1726},{"hasavif":1,"hash":"bb10c3aba64a32c7f09e86976448a59cc905f3147d7b030ad7a0daa690b09932","height":2400,"name":"227.jpg","width":1726},{"hasavif":1,"hash":"dc8a5405d70be3fcac813d357f1f1d7c5f4a8b8f693de59ae46bbe905009ac48","height":2400,"name":"228.jpg","width":1726},{"hasavif":1,"hash":"c8bfd879eb1c1fb0ba799fe9eedeb4932ac296782ff2e64e7a9ea73d41b3acb5","height":2400,"name":"229.jpg","width":1726},{"hasavif":1,"hash":"088cdd9b78c7d12a528a1487aedc614e436bdd86eb942ab83e55bf74dd273311","height":2400,"name":"230.jpg","width":1726},{"hasavif":1,"hash":"c3b129707c857a7f57a94aeeaadc469cb18d1f266bf981bc9bf501814c819c32","height":2400,"name":"231.jpg","width":1726},{"hasavif":1,"hash":"8a0291d957c5b8bba281c1f40fa69a66f04539b7748ff90869a62df832a85436","height":2400,"name":"232.jpg","width":1726},{"hasavif":1,"hash":"d3c2211e64062f803088f3224b37538805602f2645fc044d5e052b878b17450a","height":2400,"name":"233.jpg","width":1726},{"hasavif":1,"hash":"8776a585c1fa616aa07d9df8e7e20cf05e9aacf91605c50b377f957a6b6398a9","height":2400,"name":"234.jpg","width":1726},{"hasavif":1,"hash":"40788cfda838816bf525c82512dc6b7e552a8894cd4fefdb445b2da94f527ed2","height":2400,"name":"235.jpg","width":1726},{"hasavif":1,"hash":"af78644cacb4ccd2e23945fe985bbe7ea070eefdf9fe9dc2b80251252f8bd2fd","height":2400,"name":"236.jpg","width":1726},{"hasavif":1,"hash":"fc078365f39d3cfe1e25c7f900605ad9b74233b1f0248e4f6d22220861aaf2af","height":2400,"name":"237.jpg","width":1726},{"hasavif":1,"hash":"fd7a61a3e632da2d762e986f562c81af78ac9ae4e62483ce2b62bfbbf2ba59fd","height":2400,"name":"238.jpg","width":1726},{"hasavif":1,"hash":"757e7e65befe07de7bcebf96f7c06d402909b0267aaf6dc02a41df2d35fbb2cd","height":2400,"name":"239.jpg","width":1726},{"hasavif":1,"hash":"0e482771400872cba51911a16322bf259a66b4e291c5729f0ecc6abe2f41d662","height":2400,"name":"240.jpg","width":1726},{"hasavif":1,"hash":"d2b2769c29f071e1c4b45e9b7eefd41e96108623e3709897b818068c2363da54","height":2400,"name":"241.jpg","width":1726},{"hasavif":1,"hash":"013074619dfa6b5db4d8e9728cc3a6f1453e82074e8b40efa2158d87da0135bc","height":2400,"name":"242.jpg","width":1726},{"hasavif":1,"hash":"cbcf1862ae9b32afd5c7505061935c3a1496efa7c44f8f46fe5bce0fe26994a1","height":2400,"name":"243.jpg","width":1726},{"hasavif":1,"hash":"f046ae081f4aa794a4ef8ec441786109bc12f0dff4dff4d96fe7861957cbcdb9","height":2400,"name":"244.jpg","width":1726},{"hasavif":1,"hash":"185a9283f2c4d53ef926869501283358020255a037b4d09938fc1cb337e8d04a","height":2400,"name":"245.jpg","width":1726},{"hasavif":1,"hash":"604e5697635c2063d58388c4310ccda72526147eb17f5b0c9b2f303e7dd8b6d9","height":761,"name":"246.jpg","width":1109}],"gallery_id":2112790,"galleryurl":"/doujinshi/スクールアイドルの休日-総集編i--episode-of-school-idol--日本語-387870-2112790.html","groups":[{"group":"ringoya","url":"/group/ringoya-all.html"}],"id":"2112790","japanese_title":"スクールアイドルの休日 総集編I -episode of school idol-","language":"japanese","language_localname":"日本語","language_url":"/index-japanese.html","languages":[{"galleryid":1923364,"language_localname":"中文","name":"chinese","url":"/galleries/1923364.html"},{"galleryid":2112790,"language_localname":"日本語","name":"japanese","url":"/galleries/2112790.html"}],"parodys":[{"parody":"love live sunshine","url":"/series/love%20live%20sunshine-all.html"}],"related":[1913889,982193,1326665,2461569,2441782],"scene_indexes":[],"tags":[{"female":"1","male":"","tag":"anal","url":"/tag/female%3Aanal-all.html"},{"female":"1","male":"","tag":"apron","url":"/tag/female%3Aapron-all.html"},{"female":"1","male":"","tag":"bike shorts","url":"/tag/female%3Abike%20shorts-all.html"},{"female":"1","male":"","tag":"blowjob","url":"/tag/female%3Ablowjob-all.html"},{"female":"1","male":"","tag":"bondage","url":"/tag/female%3Abondage-all.html"},{"female":"1","male":"","tag":"collar","url":"/tag/female%3Acollar-all.html"},{"female":"","male":"1","tag":"condom","url":"/tag/male%3Acondom-all.html"},{"tag":"digital","url":"/tag/digital-all.html"},{"female":"1","male":"","tag":"fingering","url":"/tag/female%3Afingering-all.html"},{"female":"1","male":"","tag":"garter belt","url":"/tag/female%3Agarter%20belt-all.html"},{"female":"1","male":"","tag":"hair buns","url":"/tag/female%3Ahair%20buns-all.html"},{"female":"1","male":"","tag":"hairy","url":"/tag/female%3Ahairy-all.html"},{"female":"1","male":"","tag":"leg lock","url":"/tag/female%3Aleg%20lock-all.html"},{"female":"1","male":"","tag":"maid","url":"/tag/female%3Amaid-all.html"},{"female":"1","male":"","tag":"ponytail","url":"/tag/female%3Aponytail-all.html"},{"female":"1","male":"","tag":"schoolgirl uniform","url":"/tag/female%3Aschoolgirl%20uniform-all.html"},{"female":"1","male":"","tag":"sex toys","url":"/tag/female%3Asex%20toys-all.html"},{"tag":"soushuuhen","url":"/tag/soushuuhen-all.html"},{"female":"1","male":"","tag":"stockings","url":"/tag/female%3Astockings-all.html"},{"female":"1","male":"","tag":"sweating","url":"/tag/female%3Asweating-all.html"},{"female":"","male":"1","tag":"sweating","url":"/tag/male%3Asweating-all.html"},{"female":"1","male":"","tag":"swimsuit","url":"/tag/female%3Aswimsuit-all.html"},{"female":"1","male":"","tag":"x-ray","url":"/tag/female%3Ax%2Dray-all.html"}],"title":"School Idol no Kyuujitsu Soushuuhen I -episode of school idol-","type":"doujinshi","video":null,"videofilename":null}"#;
-        let gallery = serde_json::from_str::<hitomi_server_rs::domain::gallery::Gallery>(&gallery_raw_json_str)
+        let gallery = serde_json::from_str::<hitomi_server_rs::domain::gallery::Gallery>(gallery_raw_json_str)
             .expect("Failed to deserialize gallery JSON");
         let normalized_json = serde_json::to_string_pretty(&gallery)
             .expect("Failed to serialize gallery to pretty JSON");
@@ -72,7 +59,7 @@ mod tests {
     fn test_fbs_zstd_roundtrip() {
         use std::io::Read;
         let gallery_raw_json_str = r#"{"artists":[{"artist":"alp","url":"/artist/alp-all.html"}],"blocked":0,"characters":[{"character":"chika takami","url":"/character/chika%20takami-all.html"},{"character":"kanan matsuura","url":"/character/kanan%20matsuura-all.html"},{"character":"mari ohara","url":"/character/mari%20ohara-all.html"},{"character":"tsuki watanabe","url":"/character/tsuki%20watanabe-all.html"},{"character":"yoshiko tsushima","url":"/character/yoshiko%20tsushima-all.html"},{"character":"you watanabe","url":"/character/you%20watanabe-all.html"}],"date":"2022-01-14 00:36:00-06","datepublished":"2021-06-01","files":[{"hasavif":1,"hash":"1ee62c24d5eb66a91674a5feb41cb3d280b36a4f94ec776ce018e0868eb36b35","height":2400,"name":"001.jpg","width":1685},{"hasavif":1,"hash":"ed75b58ec79663b23a2e58d8061e1bfb120a51178c644dc16e83158b7b09bcdd","height":2400,"name":"002.jpg","single":1,"width":1678},{"hasavif":1,"hash":"726d5efddc9ebd3722616df29bb2f854cc8d1cd8dc7cd8fa9294ad1055f3bd71","height":2400,"name":"003.jpg","width":1716},{"hasavif":1,"hash":"d41dd0ae4477c1b3ce7dcd6f752fd34823c8b206ad7c677352e94cd7bf82fea4","height":2400,"name":"004.jpg","width":1716},{"hasavif":1,"hash":"e10f65dc234d44f57a29b880f2f3feac129b6772ce79246771b0dfab2aaccadc","height":2400,"name":"005.jpg","width":1716},{"hasavif":1,"hash":"543d84b11a92e134d084c45184a01c303385bced4d7aaf838a3aa34b470187b0","height":2400,"name":"006.jpg","width":1716},{"hasavif":1,"hash":"491def12d76d8e3a75d9d53adc8e4796c6bb47af172f119cd3a5a97193b8d872","height":2400,"name":"007.jpg","width":1716},{"hasavif":1,"hash":"83a43403e014dbc567d883df184112d05084f66000591056e7f436551cabdfc1","height":2400,"name":"008.jpg","width":1716},{"hasavif":1,"hash":"3c7c7e2ea3c79b0ec22b176d675c2fdf98d88d4caeb9748788bd2f8f0d4f3ca1","height":2400,"name":"009.jpg","width":1716},{"hasavif":1,"hash":"13550554947ebb1d599f0b78af52264d9561c75c227c0ffaf8f8b420151be0e9","height":2400,"name":"010.jpg","width":1716},{"hasavif":1,"hash":"b34b91db8ee792f6a8390426d7777c291fa60ee7f16c376390b8bba2aee5cd70","height":2400,"name":"011.jpg","width":1716},{"hasavif":1,"hash":"0c74505acdc57514c0aa6b6471b0ee6c94ff841e89805741c02cd8b6593aa491","height":2400,"name":"012.jpg","width":1716},{"hasavif":1,"hash":"1aa13200b6f690f09f489f74da7283f59ff2f5b4f22f8e3b5af5e04ca2a97455","height":2400,"name":"013.jpg","width":1716},{"hasavif":1,"hash":"a39e33758238cf3af1aa6ca39c23493f0c5ab648f7abc43cc80af0f9e9d9ce25","height":2400,"name":"014.jpg","width":1716},{"hasavif":1,"hash":"bf6288ba25c70430e6a9d18c15bb8fe91a9421eb60cf6d87a5800651284f8de6","height":2400,"name":"015.jpg","width":1716},{"hasavif":1,"hash":"40e12955ca9975f9cd3fb7a8b1f4207753c973dc53d235a8e6696c1f8642b913","height":2400,"name":"016.jpg","width":1716},{"hasavif":1,"hash":"45df3c2330870372d03310e84414cae29b94432dc33db74ecb3afb48a2e1e14b","height":2400,"name":"017.jpg","width":1716},{"hasavif":1,"hash":"16cfa110e6f1339a9863877483713dd031157a3b06d56ac00da7626e2e092060","height":2400,"name":"018.jpg","width":1716},{"hasavif":1,"hash":"d6e48f6bfb3dd0933ab23b9b89f922244859884f29e6f50a7539557c44ef3fcb","height":2400,"name":"019.jpg","width":1716},{"hasavif":1,"hash":"e06a49d30ea7a883edc9aa9415ab15408363712488d3635e210656bd758a8ce4","height":2400,"name":"020.jpg","width":1716},{"hasavif":1,"hash":"db5975ff906ce8f882b3a22c475ad108dec3b4a7a480266966aca88b09460839","height":2400,"name":"021.jpg","width":1716},{"hasavif":1,"hash":"30e2de302d1d68b037241b225ffd4ec1d0f77f56ec928377513fe395efd386f6","height":2400,"name":"022.jpg","width":1716},{"hasavif":1,"hash":"a476ce6ac90b3206012df8c2fac2f0cfb6fe4050269bfbf7d01d3b1a1ce762c7","height":2400,"name":"023.jpg","width":1716},{"hasavif":1,"hash":"08995bf454e699f6a4d4108d14c7f80de460b26ae765a307a47313eea536aa6e","height":2400,"name":"024.jpg","width":1716},{"hasavif":1,"hash":"bd4ae8248a55faee278a4465e591d3e4fe81df0aeed593a75f67e58a4fb0d89b","height":2400,"name":"025.jpg","width":1716},{"hasavif":1,"hash":"b3957e09a4713947252e1f33634ad91e324f34033eb7f4c7f58b0f3ae79af265","height":2400,"name":"026.jpg","width":1716},{"hasavif":1,"hash":"b6eaa94a342d3035d8a1bc3891bddea2f31046a62769e2f0187e5fe6bdb446cb","height":2400,"name":"027.jpg","width":1716},{"hasavif":1,"hash":"e4c2ec5296f85f2f35ea63fce7371e0e194c00d80ac3aeedb086a5cbe82594ec","height":2400,"name":"028.jpg","width":1716},{"hasavif":1,"hash":"2b1a1bf9efe99458d6d944eaf1b41d51352c55715b8714993638fb5cd36b1c5d","height":2400,"name":"029.jpg","width":1716},{"hasavif":1,"hash":"1bf3a60794a049085e8c2b165162c1045e3641d865d6348c385dd38371c4b95f","height":2400,"name":"030.jpg","width":1716},{"hasavif":1,"hash":"04a4a07c61f4fab30bcea51c0b8f6d055011628cfe53b5c28ae071c6444fa61a","height":2400,"name":"031.jpg","width":1716},{"hasavif":1,"hash":"3de721a2999ac04981e6c4c2387054a92b043829c4f272bf8466144866b774f1","height":2400,"name":"032.jpg","width":1716},{"hasavif":1,"hash":"f03736cb869350154e910008991fde3b7920643f4cee23f515fd6a5340626e28","height":2400,"name":"033.jpg","width":1716},{"hasavif":1,"hash":"047c8bdd920654869d77ea159272e03988b3865cb2cce2427731ea4960112997","height":2400,"name":"034.jpg","width":1716},{"hasavif":1,"hash":"7b1feadabb8bf3f5d669ac311cd6ede88eb6567d79ae5c7d37e2dc098166fe90","height":2400,"name":"035.jpg","width":1716},{"hasavif":1,"hash":"98493bb60e299d5a6f23a1b5642a44edff3030f05e386378df19d1939ea8546c","height":2400,"name":"036.jpg","width":1715},{"hasavif":1,"hash":"697393f2307da7ee4753d246f240833b8b1eec48c83dae2e3d387a2479815bce","height":2400,"name":"037.jpg","width":1716},{"hasavif":1,"hash":"32f6bd06f22af546e04b176b321c1c83b6dc3715340fefeb6e0a811ac40c6020","height":2400,"name":"038.jpg","width":1716},{"hasavif":1,"hash":"1b100c3c0a7d30b617acd75a1f0eaac2be0405af19a1d1359c842858a9226f16","height":2400,"name":"039.jpg","width":1716},{"hasavif":1,"hash":"5846d2738f52cb132b58326168dc6bf91ee7660eee1b781fdeb7d40b5c486c5a","height":2400,"name":"040.jpg","width":1716},{"hasavif":1,"hash":"8a14514886436da170e3240a2ddf2046c741b799d4080a80a07c2857f372666a","height":2400,"name":"041.jpg","width":1716},{"hasavif":1,"hash":"0ac82ae9c1d809e516ad7bb3b6944ae2d3e8fdd0bda50d53edf798e8846551b9","height":2400,"name":"042.jpg","width":1716},{"hasavif":1,"hash":"9cf177d3ff6723083d84d6ee214fd902d1e367db2d42a0c0f94c41e22a8f33e7","height":2400,"name":"043.jpg","width":1716},{"hasavif":1,"hash":"630c803f2bbd3cf244759bbd1e404ecf47fd772b169fc892464061effbddc040","height":2400,"name":"044.jpg","width":1716},{"hasavif":1,"hash":"b3b8d475861165571fc5a0d887a4042c958fdf39341d8c7350a355cf4aa9d37e","height":2400,"name":"045.jpg","width":1716},{"hasavif":1,"hash":"ff27de2b8eb5f3f613d45d748fe7b94aeb12dffcfa7c19dededb0c7ada047927","height":2400,"name":"046.jpg","width":1716},{"hasavif":1,"hash":"28b191b0bc4a0bfb1e256a0d07ea6b5422b8bdc6c8f34c83d2eb2474fe2d4506","height":2400,"name":"047.jpg","width":1716},{"hasavif":1,"hash":"644ee6c57f3e74a07721486639138435962d661feb939f62ca2e52a2a7a672cf","height":2400,"name":"048.jpg","width":1716},{"hasavif":1,"hash":"c787a7bf2948d759f99b6ee85aa281471f1257f9de1e404539b8380850e2b460","height":2400,"name":"049.jpg","width":1716},{"hasavif":1,"hash":"e35eea14a3f12a462c92d9eafea105ea235ce8ae5623360fd3c6362a7e4c4306","height":2400,"name":"050.jpg","width":1716},{"hasavif":1,"hash":"5ccfb0c49a69018a1d9f9455b8c236566ae1fe1fed6a40d09b9a96224dab0f08","height":2400,"name":"051.jpg","width":1716},{"hasavif":1,"hash":"33cbe790fa64e3913d90691cc688ff584811c194da7622a46f019d7c328ee040","height":2400,"name":"052.jpg","width":1716},{"hasavif":1,"hash":"b77f10afe8ae0f81d55820d529337b9ff410b68b92a716deaa758ff5a81d930e","height":2400,"name":"053.jpg","width":1716},{"hasavif":1,"hash":"4d8bbeafc2294c6489ff7c635feea60b06177c856451cb5f024ca865220ddb24","height":2400,"name":"054.jpg","width":1716},{"hasavif":1,"hash":"61250c4a740e8ba71dff64b1e13db85e26507a20b7f68aedacc0bb997d6d71a4","height":2400,"name":"055.jpg","width":1716},{"hasavif":1,"hash":"08606d64e2a028c88738e0751a26521c141e47a3cef1065399c6268504e8f0bb","height":2400,"name":"056.jpg","width":1716},{"hasavif":1,"hash":"4a042e9a8073984c16994f315f89d1e93a82f9acd55e447de1744e4a357987cb","height":2400,"name":"057.jpg","width":1716},{"hasavif":1,"hash":"4bd05fd5973cb19515e6d10faf5a143083bc41fb90122b5e2e70a99073127336","height":2400,"name":"058.jpg","width":1716},{"hasavif":1,"hash":"83507c94ae5649e79a2d1d4c7b813c54e37bd1d103870bb9619a0401bb71015e","height":2400,"name":"059.jpg","width":1716},{"hasavif":1,"hash":"3f337b0d8559d5196a07a6c02f29b7a9e158c580d55777805b04543721deddff","height":2400,"name":"060.jpg","width":1715},{"hasavif":1,"hash":"93ac1bc7401dc01f2f7710506b134e08b4e65089f707e73dfa09b63983c2c7bb","height":2400,"name":"061.jpg","width":1716},{"hasavif":1,"hash":"9de86f07ad7623475936c99ff9a191ae2fd94f6a4ce409f7f72d551c59d7cf96","height":2400,"name":"062.jpg","width":1716},{"hasavif":1,"hash":"a95cfac643863a08c39d7a333d86edb1d753f524cfacda5c73d0ffc694cde1a1","height":2400,"name":"063.jpg","width":1716},{"hasavif":1,"hash":"7deea8d27f1ad4e61f324b015208364133744c32e9a08f741d1fd789f777d49d","height":2400,"name":"064.jpg","width":1716},{"hasavif":1,"hash":"1a80b2f630985ff85b4de677469e806458c8a87a736fb134e69a2d067242ea46","height":2400,"name":"065.jpg","width":1716},{"hasavif":1,"hash":"d30b806f8e74250cf0f247beacd67169a787ad5c7751a6f5e68414876514a855","height":2400,"name":"066.jpg","width":1716},{"hasavif":1,"hash":"722db7f6c7886cf86768c5f868ccb8e42c0e58ba5072460f83155134e2550057","height":2400,"name":"067.jpg","width":1716},{"hasavif":1,"hash":"0ae99bd64c8e65693699b966942753966efc923b8837815280c4187e52e5b337","height":2400,"name":"068.jpg","width":1716},{"hasavif":1,"hash":"62eda346e2f6ccdd587ee6bc368459246e15d1c6bdbcf9cafa974d04f1a9349d","height":2400,"name":"069.jpg","width":1716},{"hasavif":1,"hash":"322dbbba8170826c1dee7b58e1c2fb2d4f8e82e83cbf459d2a250dd51d5b6fa0","height":2400,"name":"070.jpg","width":1716},{"hasavif":1,"hash":"05718e77b4b8c7f8bced29bfe107ed2690cc45b26ca2d97cadc70c634cd2e446","height":2400,"name":"071.jpg","width":1716},{"hasavif":1,"hash":"68598e7cff239dbfd825a21a9c55046228e1924c01fc9d14394f97cdb019b410","height":2400,"name":"072.jpg","width":1716},{"hasavif":1,"hash":"53c7f00587cb4e60383f7ebeffadf4fba6cef33166846e1e0c8d5bd8de143c8c","height":2400,"name":"073.jpg","width":1716},{"hasavif":1,"hash":"676619d3c32b16854fc409d8f8ce448d144cd7b05966f2d809e61b9232158367","height":2400,"name":"074.jpg","width":1716},{"hasavif":1,"hash":"39d7955fb84c4a7d757f7e8cc4bbe0361e8f2c29be6aeb96e539f37b7ba27f64","height":2400,"name":"075.jpg","width":1716},{"hasavif":1,"hash":"25a692cd85d2487337c7f2f80f2c2088ecb68623f79c8c1ae93d691f4620e813","height":2400,"name":"076.jpg","width":1716},{"hasavif":1,"hash":"964bec2cc806c105e39377656787f91159ed3a5c791e4feab83ca45d5158722f","height":2400,"name":"077.jpg","width":1716},{"hasavif":1,"hash":"512d4d2e9f7bd78d1da4f1f980d3d0e28baefc273ba79752fc2d6c79af6c2afb","height":2400,"name":"078.jpg","width":1716},{"hasavif":1,"hash":"023054f45f3cac13985efc27a2baf78809ed7f777e1a8ec5af1f8f3714d4cd32","height":2400,"name":"079.jpg","width":1716},{"hasavif":1,"hash":"68e76771ae37de17684bee5d0a6d0b71a9836460ff6e4c40684361694be68f38","height":2400,"name":"080.jpg","width":1716},{"hasavif":1,"hash":"cb6a560aba42a119b178a07c9b8b19ee289050d0887b2994b7c56d3644c6e076","height":2400,"name":"081.jpg","width":1716},{"hasavif":1,"hash":"cf7da96ef26537e21bf4f8b3bcab0e866a73e327295a8f625d79aad009f27cea","height":2400,"name":"082.jpg","width":1716},{"hasavif":1,"hash":"4b2da20ecfd67f148b4045eb95c898ebce211b6913ea80fa379392d85eb4bdc9","height":2400,"name":"083.jpg","width":1716},{"hasavif":1,"hash":"dae7df0545155e3358f575d3e4703edde0ed5b9488f3ce619106db0a2282f389","height":2400,"name":"084.jpg","width":1716},{"hasavif":1,"hash":"733382ef007dd981cab333a69f9bbdcea96e3774860a6f48eff5821ede7c20b1","height":2400,"name":"085.jpg","width":1716},{"hasavif":1,"hash":"253bb642b0e316cb7945fa86b2fcfd72be81daf13727e03801ed4acffebf2788","height":2400,"name":"086.jpg","width":1716},{"hasavif":1,"hash":"e01d6a0fd0ef6b0bfea1b1480e3c7b1f97ac22a9032b37a33a3c7e9be05ac542","height":2400,"name":"087.jpg","width":1716},{"hasavif":1,"hash":"874682c6af7fa1fe9a9154e067938a60f5b7b1cabe204168fc94ceed5cb7bdf1","height":2400,"name":"088.jpg","width":1716},{"hasavif":1,"hash":"98f23732f80737bf60897446e3bfb97ee32e7a5bd4fcd1f55eb5e3f70b288544","height":2400,"name":"089.jpg","width":1716},{"hasavif":1,"hash":"3729479e23fd732a54d4dac040eea6065ec40142901f012469e9f74cf69b7218","height":2400,"name":"090.jpg","width":1715},{"hasavif":1,"hash":"be48ed9733b18718b3ad558d8ba6c45bcd68fa91fb818207aa36778e276ba4a3","height":2400,"name":"091.jpg","width":1726},{"hasavif":1,"hash":"dcd6495e108c6296ccc5fec489091fcaff1364700653b6e2d07ca363e1ed0c63","height":2400,"name":"092.jpg","width":1726},{"hasavif":1,"hash":"4aca91b845f6d02f46d25bc8832ad68d5bc0eadd7a12f26107f587e27a669b58","height":2400,"name":"093.jpg","width":1726},{"hasavif":1,"hash":"0ccfc750503384d0d5b4bb95f4da3c07fbc08d22ef287822bed1813b92dfba58","height":2400,"name":"094.jpg","width":1726},{"hasavif":1,"hash":"6cc675cbbfc45884cbae4dbb7044e21ecab31396058e9296e4c4580550390f81","height":2400,"name":"095.jpg","width":1726},{"hasavif":1,"hash":"f8853f991ee5f8b5123dcca789c373c5834088f57898e7e48dbe71f85dfb67af","height":2400,"name":"096.jpg","width":1726},{"hasavif":1,"hash":"47f46ddee7bc17e76320ad3d29d07089f642e31d603946a1d418da4f662449d4","height":2400,"name":"097.jpg","width":1726},{"hasavif":1,"hash":"772d3567d8d2f54141a1808bcd1941a789a2079bfc150f3271c2e458e9e1e5b4","height":2400,"name":"098.jpg","width":1726},{"hasavif":1,"hash":"39865a9a4ed0f5342699fdd465b1652d6f0eadd753bf44b76453b1a6ec3290ce","height":2400,"name":"099.jpg","width":1726},{"hasavif":1,"hash":"d89ca86281bfc70e865f7f528ce604c3e754462817e9a614fba2a350593c9e31","height":2400,"name":"100.jpg","width":1726},{"hasavif":1,"hash":"de7b90e3c5ad2a420a79aae3330bd4f2fde2c5ec22a88f2896e4e7f3be51761f","height":2400,"name":"101.jpg","width":1726},{"hasavif":1,"hash":"13902ddb96248b8d026340c8dde6245a069177918065b71a2a90fde3df9b18d2","height":2400,"name":"102.jpg","width":1726},{"hasavif":1,"hash":"403894db9073c0193affcc15aee637684381f14bc56035f147b4ae04d637a04f","height":2400,"name":"103.jpg","width":1726},{"hasavif":1,"hash":"2f115e5a8851af436a4ec280d65ad5d2b394dbfc564ac9aa19977a53edb407d5","height":2400,"name":"104.jpg","width":1726},{"hasavif":1,"hash":"819c9daf4b80d3b56c1cc7a8cfa6603a5723f91810ceb04a8a6e49fc7bcca049","height":2400,"name":"105.jpg","width":1726},{"hasavif":1,"hash":"f2d197c1642a1b131c94d8a9b7e63f8c5b2f48c3c9da90b138316eef5398d2be","height":2400,"name":"106.jpg","width":1726},{"hasavif":1,"hash":"6cd3b8513f9b3110fd9dcac387a1bac64f4acd8fbe9ffb39e57a79815381800e","height":2400,"name":"107.jpg","width":1726},{"hasavif":1,"hash":"9d69d72f912995aed66e71985daa4ec9e5fe7e1e505650eb6f096b7515b99d83","height":2400,"name":"108.jpg","width":1726},{"hasavif":1,"hash":"8f360c6dc18a0ca35f7644a7c3d84dc5b5424e94591e2bbb88da8ea28ae8589a","height":2400,"name":"109.jpg","width":1726},{"hasavif":1,"hash":"67d379f9a7a07f227e20b5e80735f1ed062b6348afcadecdc50295fdad9b8bc7","height":2400,"name":"110.jpg","width":1726},{"hasavif":1,"hash":"28b35617b8fcb81feb2fa909e16e520eb33bc4cdfe455d7fb0f8922479dc1fb2","height":2400,"name":"111.jpg","width":1726},{"hasavif":1,"hash":"2258314bc143cbe8ff226af936966a89393e10ca57a5b0b54988223b52e345ee","height":2400,"name":"112.jpg","width":1726},{"hasavif":1,"hash":"4f0831fba337a19b79f6b4b1f585b65e1de5d2ee84c3a90cbb93a730d69348a6","height":2400,"name":"113.jpg","width":1726},{"hasavif":1,"hash":"8154aab7c84913389ac61595cbd1f99686f965b9d35625d0876ee8722477d330","height":2400,"name":"114.jpg","width":1726},{"hasavif":1,"hash":"ec6fc5b4032b9925e102cbd50b2369a2fab400da44923844ddedc25440f52979","height":2400,"name":"115.jpg","width":1726},{"hasavif":1,"hash":"7837bab95fcc08e8de60ce8f96725dbeece651096871ab918789edfe56b642b8","height":2400,"name":"116.jpg","width":1726},{"hasavif":1,"hash":"3aa72d127aac46df8376ed019cb6a207332b3ba612c4168d0fa29f70401ee84e","height":2400,"name":"117.jpg","width":1726},{"hasavif":1,"hash":"5a67bcb91c8abc293cfb73c7d7cb9be1262c60f78306eab90d5a3791d8f8abf8","height":2400,"name":"118.jpg","width":1726},{"hasavif":1,"hash":"ff45567c05895f028bfdaabd95a187fd86b3bd82799f7500a59287d8ab58343d","height":2400,"name":"119.jpg","width":1726},{"hasavif":1,"hash":"036e4c70d22c397df61832c1013c6488a3f43cec4012412291244054a5dec6a8","height":2400,"name":"120.jpg","width":1726},{"hasavif":1,"hash":"1495426f3d1fd33db83f152b5896f1fce1351f1e399c0a82a6972cb97eb04b68","height":2400,"name":"121.jpg","width":1726},{"hasavif":1,"hash":"ef1a107d35e76025b73c8cb0d3770694d915ca70afb052a88b158056b15ba5e3","height":2400,"name":"122.jpg","width":1726},{"hasavif":1,"hash":"3303013ab63fb562b3a785221f17b6afc33d55c39ac360a97aa503792a06444d","height":2400,"name":"123.jpg","width":1726},{"hasavif":1,"hash":"32089954c2136bd691ff58feaee70cdd30b03509eaa9ad623224867d52e5f47e","height":2400,"name":"124.jpg","width":1726},{"hasavif":1,"hash":"3f51c63c55d7ede80bbc75e775bbdc7235a38f09a5dcee9fcadf26099c91bdb2","height":2400,"name":"125.jpg","width":1716},{"hasavif":1,"hash":"3e87b7ff652e97ea5753a88baf402512d9b37a5d5738942a52663f7bb115783f","height":2400,"name":"126.jpg","width":1716},{"hasavif":1,"hash":"9c598de145ae6e7ffbe0c01b5472d3780e4cb88d1821e548408c36e44718c1af","height":2400,"name":"127.jpg","width":1716},{"hasavif":1,"hash":"796c954df88a41418943040849cfd732db58df66123d23452144baca945239db","height":2400,"name":"128.jpg","width":1716},{"hasavif":1,"hash":"839f9da6441dc2c25cb76400b9982af2a68592e749e5ff8d1e63ea31b32fded1","height":2400,"name":"129.jpg","width":1716},{"hasavif":1,"hash":"665098a2e52b8da7f5a6c0e08303466f33e1e51e3004cf5360e25c7e3ad3785e","height":2400,"name":"130.jpg","width":1716},{"hasavif":1,"hash":"98cddb755a098c86af873f78c1771a2cf3998b6b1ee31c6ad829687394902007","height":2400,"name":"131.jpg","width":1716},{"hasavif":1,"hash":"115fc07b57beafa94929f0d8f57a292f0f4d8581c35bcfdc9e229767e1e0b0b9","height":2400,"name":"132.jpg","width":1716},{"hasavif":1,"hash":"812e710b1a7a44c9374fd2adb9c1650c30c1dfbcb6d680661d28c2b85c250968","height":2400,"name":"133.jpg","width":1716},{"hasavif":1,"hash":"66e5cbe5b8b8ced7f54fb35839db83faf7ad1e10077af9b82631818dfba8e181","height":2400,"name":"134.jpg","width":1716},{"hasavif":1,"hash":"d419ff5d8973cc7e21c20c89012749af433715bd8d1fac6ec960b87bd4410d76","height":2400,"name":"135.jpg","width":1716},{"hasavif":1,"hash":"2706db087c30047a2e4a9a9ce2c71e221d4ca1b9ed31d46c66efa3b29171a9cc","height":2400,"name":"136.jpg","width":1716},{"hasavif":1,"hash":"0bf064d6a6556832ecc0f6b511ba7d8af839eaffeb2ac7d6dfecb7238d8a5634","height":2400,"name":"137.jpg","width":1716},{"hasavif":1,"hash":"c027cab28a94720bf80509ddcf7219254f2a1791ea0db2d32b5b7da65080b7f5","height":2400,"name":"138.jpg","width":1716},{"hasavif":1,"hash":"4e2b4a983d0d206f8c54aed8e502543badb1ae35fe8a0b494673073a7acfcb90","height":2400,"name":"139.jpg","width":1716},{"hasavif":1,"hash":"b0e2113421e5c5c6a2cd1d473a8e3f96ccc23bfd115162e4dbcceac48b6ef9f2","height":2400,"name":"140.jpg","width":1716},{"hasavif":1,"hash":"e996458fe246a0a62e2fba3c7c80b4e53708f63279fc0a383d2015d38924e7d3","height":2400,"name":"141.jpg","width":1716},{"hasavif":1,"hash":"025b666797201fba873f0364c3240823b2cc0354c3199874532ec790f6a0602e","height":2400,"name":"142.jpg","width":1716},{"hasavif":1,"hash":"7bf5774ee79e773e69561e2c1c2ca4f48ecd4ce87e202a10bc01665a6d80ec3c","height":2400,"name":"143.jpg","width":1716},{"hasavif":1,"hash":"ea0a358a9541dfcf34e2aa6763975ca23826b14d17ac3bcfbb22da3967832483","height":2400,"name":"144.jpg","width":1716},{"hasavif":1,"hash":"2dfe8ddade26e49d149125181f5d12cd4f97af72c6306f1587cd3d021ff4a9e4","height":2400,"name":"145.jpg","width":1716},{"hasavif":1,"hash":"45170fd5f5a8ae04213f107792cb1fe8f2b86aa03483bbee00e161ac147c454e","height":2400,"name":"146.jpg","width":1716},{"hasavif":1,"hash":"4b9592fc4952d6f49e85da4a0283445115e0156b28beb99a75ca61ad26d5b471","height":2400,"name":"147.jpg","width":1716},{"hasavif":1,"hash":"895c27defa640c0a79e90884c85c6892b155ffcae3e9cbe9ff135cf6cd44ba6b","height":2400,"name":"148.jpg","width":1716},{"hasavif":1,"hash":"399d8c5819d510350a9f941b81bfb7bdae615977adac9b31c1ca0b520879fc8a","height":2400,"name":"149.jpg","width":1716},{"hasavif":1,"hash":"cb584ade8fd1386fceca971d15753626ebe649db721e1564b72cee342feb5541","height":2400,"name":"150.jpg","width":1716},{"hasavif":1,"hash":"657d708165dba1025f9f32db29413c755ba18d17f3f08ca577f2015cadcd66bb","height":2400,"name":"151.jpg","width":1716},{"hasavif":1,"hash":"874986ab5ced66c0291e1482d1715388c8b610ddb02286b2fd4c2a33c0d27d17","height":2400,"name":"152.jpg","width":1716},{"hasavif":1,"hash":"ab302d61062306ea3f102e7f7bbf1eb368af71839c7e59ae0ef02d1ffb9a8f78","height":2400,"name":"153.jpg","width":1716},{"hasavif":1,"hash":"126bbdb717071e5eee0c13c0d24ef0beef817e425901fe909c7270e6f372962e","height":2400,"name":"154.jpg","width":1715},{"hasavif":1,"hash":"1c8226fc0d3d0168169e02826bf5fdc4c576dd8f977f0bf80e8862eb1542970e","height":2400,"name":"155.jpg","width":1716},{"hasavif":1,"hash":"25c4b43e7df9b2ffbdaad7eb95bff5f97a2e0757623d65a9fb1cf5f380371012","height":2400,"name":"156.jpg","width":1716},{"hasavif":1,"hash":"87af2942363452f0fadfeca98dbd75752ecd3bd1d0a694d02eec4df8be66efcc","height":2400,"name":"157.jpg","width":1716},{"hasavif":1,"hash":"8d7f1c87d823e1bf074b234b7f38d75a9e075005703039f601675a140cd0e68a","height":2400,"name":"158.jpg","width":1716},{"hasavif":1,"hash":"7c157104d05cf20977e1e821887ad74fca626a5c7ea63ffc93b7d78205f2ef6c","height":2400,"name":"159.jpg","width":1716},{"hasavif":1,"hash":"e559c11e0fa58f6e9621979056e68d8bf56a838151b0b6fab8d0fbb75bff05e4","height":2400,"name":"160.jpg","width":1716},{"hasavif":1,"hash":"9e9805b34a7675cb8b6934e253cd506f5f19fd7c0fe28ab947b1de87b09b3194","height":2400,"name":"161.jpg","width":1716},{"hasavif":1,"hash":"91b0e2e539e4d4560b86423d41f3c3073c622526bd48db97f520e52230c338cb","height":2400,"name":"162.jpg","width":1716},{"hasavif":1,"hash":"1080d5ce703b537c6c131f2ec15f991169dc8045d49f050f75a45e19c5af7760","height":2400,"name":"163.jpg","width":1716},{"hasavif":1,"hash":"8b5eaf6ead426fd18f34146b53b87931931bbe8dca4bc86156ac9edfd8128430","height":2400,"name":"164.jpg","width":1716},{"hasavif":1,"hash":"4287adee64d53ae8c7ac2ef63d426dd031489efce2a8ea3749b7e04e7cfea73f","height":2400,"name":"165.jpg","width":1716},{"hasavif":1,"hash":"5b3ab026bfb4703d1c8dc44b322e537343eecd8aa198f7ae6bd4d94f6b351b88","height":2400,"name":"166.jpg","width":1716},{"hasavif":1,"hash":"ef7622df534975498173629072baad972a99c846e754b6eac00d71a4603ad7d8","height":2400,"name":"167.jpg","width":1716},{"hasavif":1,"hash":"8ef1edef7e959ffc36a6a69a3161be0546d4fc4c37154f5f9f911bf552ee2030","height":2400,"name":"168.jpg","width":1716},{"hasavif":1,"hash":"148fbb392d4986d5e8c1f4c488c3680d5896489fa45cbcd4cf3c3531cead77a6","height":2400,"name":"169.jpg","width":1716},{"hasavif":1,"hash":"7cdfde5f50b59d9e1ecbb192a3e3438da7f84dab5b663d28a86525697f6c38d2","height":2400,"name":"170.jpg","width":1716},{"hasavif":1,"hash":"e38d6d2b4a2ff09d232c3c2c89398efa1f19c1f3399ca561d34a20dc87582b5a","height":2400,"name":"171.jpg","width":1716},{"hasavif":1,"hash":"e3f440f2ce8239da3a7eff5647bbc293b7329c46eecd01ae6918d79cf5135b89","height":2400,"name":"172.jpg","width":1716},{"hasavif":1,"hash":"7ab7d1a0fbd40ffedfcfcac47a3da329eb8982d46a3ed0afd2d2cc005300c4af","height":2400,"name":"173.jpg","width":1716},{"hasavif":1,"hash":"569be8e974f24ff4c6f8488689e7b71d58578651f1197a0a5aaaede56f1a50b7","height":2400,"name":"174.jpg","width":1716},{"hasavif":1,"hash":"9b2a16cc4427366c9b0c4c4d10f2c0e9f73f9df9a8df4b07a72866cbb8f26202","height":2400,"name":"175.jpg","width":1716},{"hasavif":1,"hash":"c05ca0696905f50bc1e9508e7a5606acb1d826bfe6c6fdac614da022476fa702","height":2400,"name":"176.jpg","width":1716},{"hasavif":1,"hash":"88578e844dd682fe40acdc2384edef8dc2c228e40cf1aa10d1ece55acddb6abe","height":2400,"name":"177.jpg","width":1716},{"hasavif":1,"hash":"3bbbf6dc6ca851f4a73325cb2ce7a4898c96b9936a74ccc36c63e3775d0216df","height":2400,"name":"178.jpg","width":1716},{"hasavif":1,"hash":"10894a2865a2e1b8bc9ef17a3a731f6ff6d0d99595eace9e97f11a5d5d0d1619","height":2400,"name":"179.jpg","width":1716},{"hasavif":1,"hash":"438fa0a7c7fb7d30408c9082526972698cf52d40c2206e326b2a863fb8ca16fe","height":2400,"name":"180.jpg","width":1716},{"hasavif":1,"hash":"df9223d3cdba46e755eb5d7a1ad5ff1fcbe003a9409c9fcd406e78cca5625531","height":2400,"name":"181.jpg","width":1716},{"hasavif":1,"hash":"f114567c4fb32ec542e5d3e1ce9b01eea95f3e85e667b77abcfbc8d84c4bd9cf","height":2400,"name":"182.jpg","width":1715},{"hasavif":1,"hash":"20f520370688a1b331bee5899b06bf700ec8f4bf618a112aebc1fa4659ebf34b","height":2400,"name":"183.jpg","width":1716},{"hasavif":1,"hash":"ea8ed8d8a5ebe01ef44390026d8384467904a1ccf9aac14c595e46a9878bf6e0","height":2400,"name":"184.jpg","width":1716},{"hasavif":1,"hash":"86e48d8a7d2658a2aa5d3106208e71a0b43ddca66127b23ee3d0e09405184d6e","height":2400,"name":"185.jpg","width":1716},{"hasavif":1,"hash":"c067d2595a8fe63536cb3a98ef5503d63cfd4d05cfc021e4354d6d67e031c46e","height":2400,"name":"186.jpg","width":1716},{"hasavif":1,"hash":"086c181e0837466ef06e087969e55749c4831382cac55afd5179b52e80763bdc","height":2400,"name":"187.jpg","width":1716},{"hasavif":1,"hash":"03092bb444732ceb1885814cedbec5c87469602d2ac2f6550ea7298990268019","height":2400,"name":"188.jpg","width":1716},{"hasavif":1,"hash":"126ff03203196fc381af7cfdd29d574a7b74ab8362828e3f493d61a04a9b5568","height":2400,"name":"189.jpg","width":1716},{"hasavif":1,"hash":"9faaca36879a0f09e46a433df6e12cb81cbe4c874d95900f079c99fc4c2f61f8","height":2400,"name":"190.jpg","width":1716},{"hasavif":1,"hash":"e3f2ad03cff8b54daa62ae32897d4a8bf3acc10ed70aea4cd5338ea6081eda84","height":2400,"name":"191.jpg","width":1716},{"hasavif":1,"hash":"1e23ccae085422ae3b7ac4fff2e847f0c6b71abf2175dd6d275a60248c56a4f6","height":2400,"name":"192.jpg","width":1716},{"hasavif":1,"hash":"c9422e206b32a80628d5e96afde03cf8017a60b5f4d65b50ec0c278064d01fc1","height":2400,"name":"193.jpg","width":1716},{"hasavif":1,"hash":"9bbe4bc3d76cf933f753564d113fdceb312be3104b4dc708a64147868b10a3fd","height":2400,"name":"194.jpg","width":1716},{"hasavif":1,"hash":"65ea472a15598aea98f1ed82eab67a724495adee996d003441332f2c333ee961","height":2400,"name":"195.jpg","width":1716},{"hasavif":1,"hash":"7bd39d6fd9bea0ef3de08d6cf0b481d896ae345002a851f57b0ebe1130de5f5b","height":2400,"name":"196.jpg","width":1716},{"hasavif":1,"hash":"f284b163249d63917a977c35b15a0ded967b5205daab64278b758d4832ce5992","height":2400,"name":"197.jpg","width":1716},{"hasavif":1,"hash":"ace090d2171c2f91b6eb2ddd4338ae0fff3f610b111382977daca543a23d7411","height":2400,"name":"198.jpg","width":1716},{"hasavif":1,"hash":"0489b6ed1fd3677a9fe33ba17013a516a960828f149294241ee965be95c66426","height":2400,"name":"199.jpg","width":1716},{"hasavif":1,"hash":"27b47fe4392e515c8495fa8951c09f941c018b99338b2b32fbcde05a570daacc","height":2400,"name":"200.jpg","width":1716},{"hasavif":1,"hash":"6db5d5be2bb82674cfa8684fb209b0be95bff16dfa475ee324b08b6742f2efe5","height":2400,"name":"201.jpg","width":1716},{"hasavif":1,"hash":"955a05133f3c7d1d48f05c0535fe84e56efd7ea644d66654fb594e82dacffca0","height":2400,"name":"202.jpg","width":1716},{"hasavif":1,"hash":"de303b9e082fc1139cbd70fd6118e85cd93ac2df8e0dce6dd682c2cc5fba6f49","height":2400,"name":"203.jpg","width":1716},{"hasavif":1,"hash":"2b82542fefddd61d3a658f9737f0ee93b9a5ff75cae2917c6a8fbd87733fee29","height":2400,"name":"204.jpg","width":1716},{"hasavif":1,"hash":"c24d9643028098bb62a1e7af9cd0a2f10b703ea2a362dd939969fc61304007b3","height":2400,"name":"205.jpg","width":1716},{"hasavif":1,"hash":"50adcbe54e0909dc4ceec3361cd560abc32e08188bc5385e11972021ba444e9b","height":2400,"name":"206.jpg","width":1716},{"hasavif":1,"hash":"7d51d487b599bae3ef5f12fc736fca97644cd22ed98937f546191961f35dfb4f","height":2400,"name":"207.jpg","width":1716},{"hasavif":1,"hash":"f3e256076d17801641f99c0611a191393269ec193dfb12118f70ba328219b971","height":2400,"name":"208.jpg","width":1716},{"hasavif":1,"hash":"466941a507c11e4aca723ff622e9e457c904fa597ef05d6ef2dcc97d43bb8150","height":2400,"name":"209.jpg","width":1716},{"hasavif":1,"hash":"cc26e6aced29d393c2700c87f6ff028e29a141a51fe8f2a840eca71d0fee3923","height":2400,"name":"210.jpg","width":1726},{"hasavif":1,"hash":"14d17174a324ff30f4d602d33b90129b93c99f051cc1231e6b8a780ccd504526","height":2400,"name":"211.jpg","width":1715},{"hasavif":1,"hash":"976c7143c87ec38017d3f9c74f68a9b09d98105959a98c33c7c4938bc60655d8","height":2400,"name":"212.jpg","width":1726},{"hasavif":1,"hash":"1322fd7094f30b8fea727dc5bbd51e730bd2bca138cbc3b706fe06b233b71bd9","height":2400,"name":"213.jpg","width":1726},{"hasavif":1,"hash":"b1608b16bfea9b437cb4dd0a69a66c797ddd04cae6f9f5fa889d5fceb39a7ff0","height":2400,"name":"214.jpg","width":1726},{"hasavif":1,"hash":"d9e55cf5f553078df2422e283151f241739b86ad2912861ea2d6aac094c72bbc","height":2400,"name":"215.jpg","width":1726},{"hasavif":1,"hash":"ed502acc43e8f7664884e6dd61a611b34bc576db4c3996d1d08e4ad2ad2a42db","height":2400,"name":"216.jpg","width":1726},{"hasavif":1,"hash":"63d869da2350d666347fd1e06d1f12f42f1ed22bbe34766e91a1e6efb65ce040","height":2400,"name":"217.jpg","width":1726},{"hasavif":1,"hash":"b6ab7f00e705b1f907e1b953593f3dc4ec60b73bc0cac5568b9ea1d3f32c7a53","height":2400,"name":"218.jpg","width":1726},{"hasavif":1,"hash":"31fa8674e9c962687a269b0dee336069bcac0d617bce2b1cde1e88228b1dc2e9","height":2400,"name":"219.jpg","width":1726},{"hasavif":1,"hash":"d8dbc31cda71cb6b3b7648c93ce65aea91add601e57943019303b81501e29e5a","height":2400,"name":"220.jpg","width":1726},{"hasavif":1,"hash":"4345804078f4c0cb054596ce2a87349fd365a35f2066abd043fa6862a61338dc","height":2400,"name":"221.jpg","width":1726},{"hasavif":1,"hash":"d4edc47e79a69a59dfdefbf7f0610c524d6be318eb6eec936a819c7fa5a7da5a","height":2400,"name":"222.jpg","width":1726},{"hasavif":1,"hash":"e03561f0276dcd93fbf206b9a107997273c609ceceaf9ba44b217cb6a713b6b8","height":2400,"name":"223.jpg","width":1726},{"hasavif":1,"hash":"1ddf0c54068d07ffd3f1aee45fb8016c2620011ddecb181352c3751aafc89119","height":2400,"name":"224.jpg","width":1726},{"hasavif":1,"hash":"511ea01926c54e74a2e21d7f055144c812b2ce8277bdbd35590615f59a1ff2e2","height":2400,"name":"225.jpg","width":1726},{"hasavif":1,"hash":"8d33a59da4187490a3ef7cb8d134c1f2733594693a8f5eaeecb9594645d949ab","height":2400,"name":"226.jpg","width":1726},{"hasavif":1,"hash":"bb10c3aba64a32c7f09e86976448a59cc905f3147d7b030ad7a0daa690b09932","height":2400,"name":"227.jpg","width":1726},{"hasavif":1,"hash":"dc8a5405d70be3fcac813d357f1f1d7c5f4a8b8f693de59ae46bbe905009ac48","height":2400,"name":"228.jpg","width":1726},{"hasavif":1,"hash":"c8bfd879eb1c1fb0ba799fe9eedeb4932ac296782ff2e64e7a9ea73d41b3acb5","height":2400,"name":"229.jpg","width":1726},{"hasavif":1,"hash":"088cdd9b78c7d12a528a1487aedc614e436bdd86eb942ab83e55bf74dd273311","height":2400,"name":"230.jpg","width":1726},{"hasavif":1,"hash":"c3b129707c857a7f57a94aeeaadc469cb18d1f266bf981bc9bf501814c819c32","height":2400,"name":"231.jpg","width":1726},{"hasavif":1,"hash":"8a0291d957c5b8bba281c1f40fa69a66f04539b7748ff90869a62df832a85436","height":2400,"name":"232.jpg","width":1726},{"hasavif":1,"hash":"d3c2211e64062f803088f3224b37538805602f2645fc044d5e052b878b17450a","height":2400,"name":"233.jpg","width":1726},{"hasavif":1,"hash":"8776a585c1fa616aa07d9df8e7e20cf05e9aacf91605c50b377f957a6b6398a9","height":2400,"name":"234.jpg","width":1726},{"hasavif":1,"hash":"40788cfda838816bf525c82512dc6b7e552a8894cd4fefdb445b2da94f527ed2","height":2400,"name":"235.jpg","width":1726},{"hasavif":1,"hash":"af78644cacb4ccd2e23945fe985bbe7ea070eefdf9fe9dc2b80251252f8bd2fd","height":2400,"name":"236.jpg","width":1726},{"hasavif":1,"hash":"fc078365f39d3cfe1e25c7f900605ad9b74233b1f0248e4f6d22220861aaf2af","height":2400,"name":"237.jpg","width":1726},{"hasavif":1,"hash":"fd7a61a3e632da2d762e986f562c81af78ac9ae4e62483ce2b62bfbbf2ba59fd","height":2400,"name":"238.jpg","width":1726},{"hasavif":1,"hash":"757e7e65befe07de7bcebf96f7c06d402909b0267aaf6dc02a41df2d35fbb2cd","height":2400,"name":"239.jpg","width":1726},{"hasavif":1,"hash":"0e482771400872cba51911a16322bf259a66b4e291c5729f0ecc6abe2f41d662","height":2400,"name":"240.jpg","width":1726},{"hasavif":1,"hash":"d2b2769c29f071e1c4b45e9b7eefd41e96108623e3709897b818068c2363da54","height":2400,"name":"241.jpg","width":1726},{"hasavif":1,"hash":"013074619dfa6b5db4d8e9728cc3a6f1453e82074e8b40efa2158d87da0135bc","height":2400,"name":"242.jpg","width":1726},{"hasavif":1,"hash":"cbcf1862ae9b32afd5c7505061935c3a1496efa7c44f8f46fe5bce0fe26994a1","height":2400,"name":"243.jpg","width":1726},{"hasavif":1,"hash":"f046ae081f4aa794a4ef8ec441786109bc12f0dff4dff4d96fe7861957cbcdb9","height":2400,"name":"244.jpg","width":1726},{"hasavif":1,"hash":"185a9283f2c4d53ef926869501283358020255a037b4d09938fc1cb337e8d04a","height":2400,"name":"245.jpg","width":1726},{"hasavif":1,"hash":"604e5697635c2063d58388c4310ccda72526147eb17f5b0c9b2f303e7dd8b6d9","height":761,"name":"246.jpg","width":1109}],"gallery_id":2112790,"galleryurl":"/doujinshi/スクールアイドルの休日-総集編i--episode-of-school-idol--日本語-387870-2112790.html","groups":[{"group":"ringoya","url":"/group/ringoya-all.html"}],"id":"2112790","japanese_title":"スクールアイドルの休日 総集編I -episode of school idol-","language":"japanese","language_localname":"日本語","language_url":"/index-japanese.html","languages":[{"galleryid":1923364,"language_localname":"中文","name":"chinese","url":"/galleries/1923364.html"},{"galleryid":2112790,"language_localname":"日本語","name":"japanese","url":"/galleries/2112790.html"}],"parodys":[{"parody":"love live sunshine","url":"/series/love%20live%20sunshine-all.html"}],"related":[1913889,982193,1326665,2461569,2441782],"scene_indexes":[],"tags":[{"female":"1","male":"","tag":"anal","url":"/tag/female%3Aanal-all.html"},{"female":"1","male":"","tag":"apron","url":"/tag/female%3Aapron-all.html"},{"female":"1","male":"","tag":"bike shorts","url":"/tag/female%3Abike%20shorts-all.html"},{"female":"1","male":"","tag":"blowjob","url":"/tag/female%3Ablowjob-all.html"},{"female":"1","male":"","tag":"bondage","url":"/tag/female%3Abondage-all.html"},{"female":"1","male":"","tag":"collar","url":"/tag/female%3Acollar-all.html"},{"female":"","male":"1","tag":"condom","url":"/tag/male%3Acondom-all.html"},{"tag":"digital","url":"/tag/digital-all.html"},{"female":"1","male":"","tag":"fingering","url":"/tag/female%3Afingering-all.html"},{"female":"1","male":"","tag":"garter belt","url":"/tag/female%3Agarter%20belt-all.html"},{"female":"1","male":"","tag":"hair buns","url":"/tag/female%3Ahair%20buns-all.html"},{"female":"1","male":"","tag":"hairy","url":"/tag/female%3Ahairy-all.html"},{"female":"1","male":"","tag":"leg lock","url":"/tag/female%3Aleg%20lock-all.html"},{"female":"1","male":"","tag":"maid","url":"/tag/female%3Amaid-all.html"},{"female":"1","male":"","tag":"ponytail","url":"/tag/female%3Aponytail-all.html"},{"female":"1","male":"","tag":"schoolgirl uniform","url":"/tag/female%3Aschoolgirl%20uniform-all.html"},{"female":"1","male":"","tag":"sex toys","url":"/tag/female%3Asex%20toys-all.html"},{"tag":"soushuuhen","url":"/tag/soushuuhen-all.html"},{"female":"1","male":"","tag":"stockings","url":"/tag/female%3Astockings-all.html"},{"female":"1","male":"","tag":"sweating","url":"/tag/female%3Asweating-all.html"},{"female":"","male":"1","tag":"sweating","url":"/tag/male%3Asweating-all.html"},{"female":"1","male":"","tag":"swimsuit","url":"/tag/female%3Aswimsuit-all.html"},{"female":"1","male":"","tag":"x-ray","url":"/tag/female%3Ax%2Dray-all.html"}],"title":"School Idol no Kyuujitsu Soushuuhen I -episode of school idol-","type":"doujinshi","video":null,"videofilename":null}"#;
-        let original_json_size = gallery_raw_json_str.as_bytes().len();
+        let original_json_size = gallery_raw_json_str.len();
         let gallery: hitomi_server_rs::domain::gallery::Gallery = serde_json::from_str(gallery_raw_json_str)
             .expect("Failed to parse initial JSON");
 
@@ -122,4 +109,1148 @@ mod tests {
     }
     println!("SQL validation tests completed.");
     }
+
+    #[test]
+    fn test_accepts_encoding() {
+        use axum::http::{header, HeaderMap, HeaderValue};
+        let cases = vec![
+            ("zstd", true),
+            ("gzip, br, zstd", true),
+            ("gzip, ZSTD;q=0.5", true),
+            ("gzip, zstd;q=0", false),
+            ("gzip, br", false),
+        ];
+        for (accept_encoding, expected) in cases {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_static(accept_encoding));
+            let result = hitomi_server_rs::api::get_fbs_gallery::accepts_encoding(&headers, "zstd");
+            assert_eq!(result, expected, "Accept-Encoding check failed for: {}", accept_encoding);
+        }
+        assert!(!hitomi_server_rs::api::get_fbs_gallery::accepts_encoding(&HeaderMap::new(), "zstd"));
+    }
+
+    #[test]
+    fn test_search_query_parse() {
+        use hitomi_server_rs::search::parser::{parse, Namespace, Query, Term};
+        let term = |namespace, value: &str| Query::Term(Term { namespace, value: value.to_string() });
+
+        let query = parse(r#"female:big_breasts artist:"alp" -tag:ntr (language:japanese | language:english)"#)
+            .expect("Failed to parse search query");
+        assert_eq!(query, Query::And(vec![
+            term(Namespace::Female, "big breasts"),
+            term(Namespace::Artist, "alp"),
+            Query::Not(Box::new(term(Namespace::Tag, "ntr"))),
+            Query::Or(vec![
+                term(Namespace::Language, "japanese"),
+                term(Namespace::Language, "english"),
+            ]),
+        ]));
+
+        let query = parse("type:doujinshi OR type:manga x-ray").expect("Failed to parse search query");
+        assert_eq!(query, Query::And(vec![
+            Query::Or(vec![term(Namespace::Type, "doujinshi"), term(Namespace::Type, "manga")]),
+            term(Namespace::Title, "x-ray"),
+        ]));
+
+        for invalid in ["", "foo:bar", "(artist:alp", "artist:alp)", "artist:\"alp", "-"] {
+            assert!(parse(invalid).is_err(), "Query should be rejected: {}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_read_only_validation() {
+        let queries = vec![
+            ("SELECT gallery_id FROM galleries WHERE gallery_id = 123", true),
+            ("SELECT g.gallery_id FROM galleries g JOIN gallery_tags gt ON gt.gallery_id = g.id WHERE lower(g.title) LIKE '%a%'", true),
+            ("WITH t AS (SELECT id FROM tags WHERE name = 'ntr') SELECT gallery_id FROM galleries WHERE id IN (SELECT gallery_id FROM gallery_tags WHERE tag_id IN (SELECT id FROM t))", true),
+            ("SELECT gallery_id FROM galleries; DELETE FROM galleries", false),
+            ("DELETE FROM galleries", false),
+            ("WITH d AS (DELETE FROM galleries RETURNING gallery_id) SELECT gallery_id FROM d", false),
+            ("SELECT gallery_id INTO backup FROM galleries", false),
+            ("SELECT gallery_id FROM galleries FOR UPDATE", false),
+            ("SELECT gallery_id FROM galleries WHERE pg_sleep(10) IS NULL", false),
+            ("SELECT gallery_id FROM dblink('host=evil', 'SELECT 1') AS t(gallery_id int)", false),
+            ("SELECT gallery_id FROM pg_catalog.pg_user", false),
+            ("SELECT gallery_id FROM fbs_galleries", false),
+            ("SELEC gallery_id FROM galleries", false),
+            // CTE の名前は定義したクエリの外では実テーブルを指す
+            ("SELECT gallery_id FROM galleries WHERE EXISTS (WITH pg_stat_activity AS (SELECT 1) SELECT 1 FROM pg_stat_activity) AND gallery_id IN (SELECT length(query) FROM pg_stat_activity)", false),
+            ("SELECT gallery_id FROM galleries WHERE EXISTS (WITH fbs_galleries AS (SELECT 1) SELECT 1 FROM fbs_galleries) AND gallery_id IN (SELECT gallery_id FROM fbs_galleries)", false),
+            ("WITH a AS (SELECT gallery_id FROM import_checkpoints), import_checkpoints AS (SELECT 1 AS gallery_id) SELECT gallery_id FROM a", false),
+            ("WITH t AS (SELECT 1 AS x) SELECT x FROM public.t", false),
+            ("WITH a AS (SELECT gallery_id FROM galleries), b AS (SELECT gallery_id FROM a) SELECT gallery_id FROM b", true),
+            ("WITH RECURSIVE r(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM r WHERE n < 3) SELECT n FROM r", true),
+            // TABLE 句も許可リストと CTE のスコープで検証する
+            ("SELECT gallery_id FROM galleries WHERE 1 IN (SELECT 1 UNION TABLE pg_catalog.pg_shadow)", false),
+            ("SELECT gallery_id FROM galleries UNION TABLE pg_shadow", false),
+            ("SELECT gallery_id FROM galleries UNION TABLE public.galleries", true),
+            ("WITH t AS (SELECT gallery_id FROM galleries) SELECT gallery_id FROM galleries UNION TABLE t", true),
+            ("SELECT gallery_id FROM galleries WHERE EXISTS (WITH pg_shadow AS (SELECT 1) SELECT 1) UNION TABLE pg_shadow", false),
+        ];
+        for (query, should_pass) in queries {
+            let result = hitomi_server_rs::api::sql_validator::validate_read_only_query(query);
+            assert_eq!(result.is_ok(), should_pass, "Read-only validation failed for query: {} ({:?})", query, result);
+        }
+    }
+
+    #[test]
+    fn test_build_paginated_query() {
+        use hitomi_server_rs::domain::dto::SQLRequest;
+        let cases = vec![
+            (r#"{"query": "SELECT gallery_id FROM galleries", "limit": 10, "offset": 20}"#,
+                Some("SELECT gallery_id FROM galleries LIMIT 10 OFFSET 20")),
+            (r#"{"query": "SELECT gallery_id FROM galleries"}"#,
+                Some("SELECT gallery_id FROM galleries")),
+            (r#"{"query": "SELECT gallery_id FROM galleries g JOIN tags t ON t.name = 'limited edition' WHERE g.id IN (SELECT gallery_id FROM gallery_tags LIMIT 5)", "limit": 10}"#,
+                Some("SELECT gallery_id FROM galleries g JOIN tags t ON t.name = 'limited edition' WHERE g.id IN (SELECT gallery_id FROM gallery_tags LIMIT 5) LIMIT 10")),
+            (r#"{"query": "SELECT id AS offset_x, gallery_id FROM galleries LIMIT 10", "limit": 10}"#,
+                Some("SELECT id AS offset_x, gallery_id FROM galleries LIMIT 10")),
+            (r#"{"query": "SELECT gallery_id FROM galleries LIMIT 10", "offset": 5}"#,
+                Some("SELECT gallery_id FROM galleries LIMIT 10 OFFSET 5")),
+            (r#"{"query": "SELECT gallery_id FROM galleries LIMIT 10", "limit": 20}"#, None),
+            (r#"{"query": "SELECT gallery_id FROM galleries OFFSET 10", "offset": 20}"#, None),
+            (r#"{"query": "SELECT gallery_id FROM galleries; SELECT 1"}"#, None),
+        ];
+        for (request, expected) in cases {
+            let request: SQLRequest = serde_json::from_str(request).expect("Failed to parse SQLRequest");
+            let result = request.build_paginated_query();
+            assert_eq!(result.as_deref().ok(), expected, "Pagination failed for query: {} ({:?})", request.query, result);
+        }
+    }
+
+    #[test]
+    fn test_keyset_cursor() {
+        use hitomi_server_rs::domain::cursor::{format_date_key, parse_date_key, Cursor, SortKey, SortOrder};
+        use hitomi_server_rs::domain::dto::{SQLRequest, SearchRequest};
+
+        let cursor = Cursor {
+            sort_by: SortKey::Date,
+            order: SortOrder::Desc,
+            key: "2022-01-14 06:36:00+00".to_string(),
+            gallery_id: 2112790,
+        };
+        let encoded = cursor.encode();
+        assert_eq!(Cursor::decode(&encoded).ok(), Some(cursor.clone()));
+        assert!(Cursor::decode("not a cursor").is_err());
+
+        let request = |json: serde_json::Value| -> SQLRequest {
+            serde_json::from_value(json).expect("Failed to parse SQLRequest")
+        };
+        let query = "SELECT gallery_id FROM galleries";
+
+        assert_eq!(request(serde_json::json!({"query": query})).keyset().ok(), Some(None));
+
+        let keyset = request(serde_json::json!({"query": query, "cursor": encoded}))
+            .keyset()
+            .expect("Cursor should be accepted")
+            .expect("Keyset should be resolved from the cursor");
+        assert_eq!((keyset.sort_by, keyset.order, keyset.after), (SortKey::Date, SortOrder::Desc, Some(cursor)));
+
+        for invalid in [
+            serde_json::json!({"query": query, "cursor": encoded, "sort_by": "gallery_id"}),
+            serde_json::json!({"query": query, "cursor": encoded, "order": "asc"}),
+            serde_json::json!({"query": query, "sort_by": "date", "offset": 10}),
+            serde_json::json!({"query": query, "order": "asc"}),
+        ] {
+            assert!(request(invalid.clone()).keyset().is_err(), "Keyset should be rejected: {}", invalid);
+        }
+
+        // ページは外側のクエリで切り出すので、トップレベルの LIMIT / OFFSET / FETCH は受け付けない
+        let inner = |sql: &str| request(serde_json::json!({"query": sql, "sort_by": "date"})).build_inner_query();
+        assert_eq!(inner(query).ok().as_deref(), Some(query));
+        let subquery_limit = "SELECT gallery_id FROM galleries WHERE id IN (SELECT gallery_id FROM gallery_tags LIMIT 5)";
+        assert_eq!(inner(subquery_limit).ok().as_deref(), Some(subquery_limit));
+        for sql in [
+            "SELECT gallery_id FROM galleries LIMIT 10",
+            "SELECT gallery_id FROM galleries OFFSET 5",
+            "SELECT gallery_id FROM galleries FETCH FIRST 5 ROWS ONLY",
+        ] {
+            assert!(inner(sql).is_err(), "Top-level pagination should be rejected: {}", sql);
+        }
+
+        // nozomi 形式には end フレームが無いのでカーソルを返せない
+        assert!(request(serde_json::json!({"query": query, "sort_by": "date", "format": "nozomi"})).validate().is_err());
+        assert!(request(serde_json::json!({"query": query, "cursor": encoded, "format": "nozomi"})).validate().is_err());
+
+        // /search はクエリモードで offset を使わなければ、先頭ページからカーソルを返す
+        let search = |json: serde_json::Value| -> SearchRequest {
+            serde_json::from_value(json).expect("Failed to parse SearchRequest")
+        };
+        let cursor = Cursor { sort_by: SortKey::Date, order: SortOrder::Desc, key: "2024-01-02 00:00:00.5+09".to_string(), gallery_id: 3 };
+        assert!(search(serde_json::json!({"query": "female:collar"})).keyset().is_ok_and(|keyset| keyset.is_some()));
+        assert!(search(serde_json::json!({"query": "female:collar", "offset": 10})).keyset().is_ok_and(|keyset| keyset.is_none()));
+        assert!(search(serde_json::json!({"query": "school", "mode": "title"})).keyset().is_ok_and(|keyset| keyset.is_none()));
+        assert!(search(serde_json::json!({"query": "female:collar", "cursor": cursor.encode()})).keyset().is_ok());
+        for invalid in [
+            serde_json::json!({"query": "female:collar", "cursor": cursor.encode(), "offset": 10}),
+            serde_json::json!({"query": "school", "mode": "title", "cursor": cursor.encode()}),
+            serde_json::json!({"query": "female:collar", "cursor": Cursor { sort_by: SortKey::GalleryId, ..cursor.clone() }.encode()}),
+            serde_json::json!({"query": "female:collar", "cursor": Cursor { key: "yesterday".to_string(), ..cursor.clone() }.encode()}),
+        ] {
+            assert!(search(invalid.clone()).keyset().is_err(), "Keyset should be rejected: {}", invalid);
+        }
+
+        // カーソルの日時は PostgreSQL のテキスト表現と相互に変換できる
+        let micros = parse_date_key("2024-01-02 00:00:00.5+09").expect("Failed to parse date key");
+        assert_eq!(format_date_key(micros).as_deref(), Some("2024-01-01 15:00:00.500+00"));
+        assert_eq!(format_date_key(micros).as_deref().and_then(parse_date_key), Some(micros));
+        assert_eq!(parse_date_key("2022-01-14 06:36:00+00").and_then(format_date_key).as_deref(), Some("2022-01-14 06:36:00+00"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_next_cursor() {
+        use hitomi_server_rs::api::perform_sql::stream_id_list;
+        use hitomi_server_rs::domain::cursor::{Cursor, SortKey, SortOrder};
+        use hitomi_server_rs::framing::decoder::decode;
+
+        let cursor = Cursor {
+            sort_by: SortKey::GalleryId,
+            order: SortOrder::Desc,
+            key: "2".to_string(),
+            gallery_id: 2,
+        };
+        let response = stream_id_list(sea_orm::DatabaseConnection::default(), vec![4, 3, 2], 2, None, Some(cursor.clone()))
+            .expect("Failed to build response");
+        // カーソルはブラウザからも読めるよう、HTTP トレーラではなく本文の end フレームに載る
+        assert!(response.headers().get(axum::http::header::TRAILER).is_none());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("Failed to read body");
+
+        let decoded = decode(&body).expect("Failed to decode stream");
+        assert_eq!(decoded.ids().ok(), Some(vec![4, 3, 2]));
+        assert_eq!(decoded.end.next_cursor.as_deref().map(Cursor::decode).and_then(|cursor| cursor.ok()), Some(cursor));
+
+        let response = stream_id_list(sea_orm::DatabaseConnection::default(), vec![1], 2, None, None)
+            .expect("Failed to build response");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("Failed to read body");
+        assert_eq!(decode(&body).expect("Failed to decode stream").end.next_cursor, None);
+    }
+
+    #[test]
+    fn test_api_error() {
+        use hitomi_server_rs::api::error::{ErrorCode, ErrorPosition};
+        use hitomi_server_rs::api::perform_sql::is_only_gallery_id_returned;
+        use hitomi_server_rs::api::sql_validator::validate_read_only_query;
+        use hitomi_server_rs::domain::dto::SQLRequest;
+
+        let err = validate_read_only_query("SELECT gallery_id FROM galleries WHERE )").unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidSql);
+        assert!(err.position.is_some());
+        let json = serde_json::to_value(&err).expect("Failed to serialize ApiError");
+        assert_eq!(json["code"], "invalid_sql");
+        assert!(json.get("status").is_none());
+
+        let err = validate_read_only_query("DELETE FROM galleries").unwrap_err();
+        assert_eq!((err.code, err.position), (ErrorCode::ForbiddenStatement, None));
+
+        let err = validate_read_only_query("SELECT gallery_id FROM galleries;\nSELECT 1 FROM )").unwrap_err();
+        assert_eq!(err.position.map(|p| p.line), Some(2));
+        assert_ne!(err.position, Some(ErrorPosition { line: 1, column: 1 }));
+
+        // パースできない SQL でも panic しない
+        assert!(!is_only_gallery_id_returned("SELECT FROM WHERE"));
+        assert!(!is_only_gallery_id_returned(""));
+
+        let request = |json: serde_json::Value| -> SQLRequest {
+            serde_json::from_value(json).expect("Failed to parse SQLRequest")
+        };
+        let query = "SELECT gallery_id FROM galleries";
+        assert!(request(serde_json::json!({"query": query})).validate().is_ok());
+        for invalid in [
+            serde_json::json!({"query": "  "}),
+            serde_json::json!({"query": query, "batch_size": 0}),
+            serde_json::json!({"query": query, "timeout_ms": 0}),
+            serde_json::json!({"query": query, "timeout_ms": 600_000}),
+        ] {
+            let err = request(invalid.clone()).validate().expect_err("Request should be rejected");
+            assert_eq!(err.code, ErrorCode::InvalidRequest, "{}", invalid);
+        }
+
+        let err = request(serde_json::json!({"query": query, "cursor": "???"})).keyset().unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidCursor);
+
+    }
+
+    #[test]
+    fn test_stream_framing() {
+        use hitomi_server_rs::framing::decoder::{decode, DecodeError, Decoder};
+        use hitomi_server_rs::framing::encoder::{encode_chunk, encode_end, encode_header, encode_ids};
+        use hitomi_server_rs::framing::{Frame, PayloadKind, StreamEnd, StreamError};
+
+        let mut bytes = encode_header(PayloadKind::Ids);
+        bytes.extend(encode_chunk(2, &encode_ids(&[3, 1])));
+        bytes.extend(encode_chunk(1, &encode_ids(&[2])));
+        let error = StreamError { code: "timeout".to_string(), message: "statement timeout exceeded".to_string() };
+        bytes.extend(encode_end(&StreamEnd { total_rows: 3, error: Some(error.clone()), next_cursor: None }));
+
+        let decoded = decode(&bytes).expect("Failed to decode stream");
+        assert_eq!(decoded.payload, PayloadKind::Ids);
+        assert_eq!(decoded.ids().ok(), Some(vec![3, 1, 2]));
+        assert_eq!(decoded.end, StreamEnd { total_rows: 3, error: Some(error), next_cursor: None });
+
+        // 1バイトずつ渡しても同じフレームが取り出せる
+        let mut decoder = Decoder::new();
+        let mut frames = Vec::new();
+        for byte in &bytes {
+            decoder.push(std::slice::from_ref(byte));
+            while let Some(frame) = decoder.next_frame().expect("Failed to decode frame") {
+                frames.push(frame);
+            }
+        }
+        assert!(decoder.finish().is_ok());
+        assert_eq!(frames.len(), 4);
+        assert!(matches!(frames[1], Frame::Chunk { rows: 2, .. }));
+
+        // end フレームが無ければ途中で切れたと分かる
+        assert_eq!(decode(&bytes[..bytes.len() - 1]).err(), Some(DecodeError::Truncated));
+        assert_eq!(decode(b"\x01\x00\x00\x00").err(), Some(DecodeError::BadMagic));
+
+        let mut bytes = encode_header(PayloadKind::Ids);
+        bytes.extend(encode_end(&StreamEnd { total_rows: 1, error: None, next_cursor: None }));
+        assert!(matches!(decode(&bytes), Err(DecodeError::RowCountMismatch { expected: 1, actual: 0 })));
+
+        // 次のページのカーソルは end フレームに載る
+        let end = StreamEnd { total_rows: 1, error: None, next_cursor: Some("eyJrZXkiOiIxIn0".to_string()) };
+        let mut bytes = encode_header(PayloadKind::Ids);
+        bytes.extend(encode_chunk(1, &encode_ids(&[1])));
+        bytes.extend(encode_end(&end));
+        assert_eq!(decode(&bytes).map(|decoded| decoded.end).ok(), Some(end));
+    }
+
+    #[test]
+    fn test_suggest_request() {
+        use hitomi_server_rs::domain::dto::SuggestRequest;
+        use hitomi_server_rs::search::parser::Namespace;
+
+        let request = |ns: &str, q: &str, limit: u32| SuggestRequest { ns: ns.to_string(), q: q.to_string(), limit };
+
+        assert_eq!(request("Female", "big_b", 10).validate().ok(), Some(Namespace::Female));
+        assert_eq!(request("series", "x", 10).validate().ok(), Some(Namespace::Parody));
+        assert_eq!(request("female", " Big_B ", 10).value(), "big b");
+        assert_eq!(Namespace::Parody.name(), Some("series"));
+
+        for (ns, q, limit) in [("type", "manga", 10), ("title", "x", 10), ("artist", "  ", 10), ("artist", "a", 0), ("artist", "a", 1000)] {
+            assert!(request(ns, q, limit).validate().is_err(), "Request should be rejected: {} {} {}", ns, q, limit);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_suggestions() -> Result<()> {
+        use hitomi_server_rs::mapper::suggest_mapper::{find_suggestions, refresh_suggest_entries};
+        use hitomi_server_rs::search::parser::Namespace;
+
+        let Some(db) = connect_test_schema("test_find_suggestions").await? else { return Ok(()) };
+        db.execute_unprepared(
+            "INSERT INTO galleries (gallery_id, title, date, type, external_id) VALUES \
+                 (1, 'a', now(), 'manga', ''), (2, 'b', now(), 'manga', ''), (3, 'c', now(), 'manga', ''); \
+             INSERT INTO tags (name, url, male, female) VALUES \
+                 ('big breasts', '', false, true), ('bigger', '', false, true), ('ribbon', '', false, true), \
+                 ('big breasts', '', true, true), ('big penis', '', true, false); \
+             INSERT INTO gallery_tags (gallery_id, tag_id) VALUES (1, 1), (2, 1), (3, 2), (1, 3), (2, 3), (3, 3), (1, 4); \
+             INSERT INTO artists (artist, url) VALUES ('Alp', ''), ('kalpa', '');"
+        ).await?;
+
+        // 集計は取り込み後の更新で反映される
+        assert!(find_suggestions(&db, Namespace::Female, "bi", 10).await?.is_empty());
+        refresh_suggest_entries(&db).await?;
+
+        let suggest = |namespace: Namespace, value: &'static str| {
+            let db = db.clone();
+            async move {
+                find_suggestions(&db, namespace, value, 10).await
+                    .map(|suggestions| suggestions.into_iter().map(|s| (s.value, s.count)).collect::<Vec<_>>())
+            }
+        };
+        // 短い入力は前方一致 ("ribbon" の部分一致は含まない)
+        assert_eq!(suggest(Namespace::Female, "bi").await?, vec![("big breasts".to_string(), 2), ("bigger".to_string(), 1)]);
+        // 3文字以上は部分一致で、ギャラリー数の多い順
+        assert_eq!(suggest(Namespace::Female, "bbo").await?, vec![("ribbon".to_string(), 3)]);
+        assert_eq!(suggest(Namespace::Male, "big").await?, vec![("big penis".to_string(), 0)]);
+        // 前方一致は大文字小文字を区別せず、同数なら前方一致を優先する
+        assert_eq!(suggest(Namespace::Artist, "al").await?, vec![("Alp".to_string(), 0)]);
+        assert_eq!(suggest(Namespace::Artist, "alp").await?, vec![("Alp".to_string(), 0), ("kalpa".to_string(), 0)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_import_refreshes_suggestions() -> Result<()> {
+        use hitomi_server_rs::import::dead_letter::DeadLetterWriter;
+        use hitomi_server_rs::import::{ImportMode, Loader};
+        use hitomi_server_rs::mapper::suggest_mapper::find_suggestions;
+        use hitomi_server_rs::pipeline::{import, ImportOptions};
+        use hitomi_server_rs::search::parser::Namespace;
+
+        let Some(db) = connect_test_schema("test_failed_import_suggestions").await? else { return Ok(()) };
+        let dir = std::env::temp_dir().join(format!("failed_import_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let input = dir.join("galleries.json");
+        std::fs::write(&input, serde_json::json!({
+            "gallery_id": 1, "title": "a", "date": "2024-01-02T03:04:05+00:00", "type": "manga", "id": "1",
+            "files": [], "languages": [], "scene_indexes": [], "related": [],
+            "tags": [{"tag": "collar", "url": "", "male": false, "female": true}],
+            "artists": [], "groups": [], "characters": [], "parodies": [],
+        }).to_string() + "\n")?;
+
+        // 2つ目の入力が読めずに失敗しても、1つ目でコミットした分はサジェストに反映される
+        let options = ImportOptions { mode: ImportMode::Upsert, restart: true, loader: Loader::Insert };
+        let dead_letters = DeadLetterWriter::new(dir.join("dead_letters.jsonl"));
+        let result = import::run(&db, &[input, dir.join("missing.json")], options, false, &dead_letters).await;
+        std::fs::remove_dir_all(&dir)?;
+        assert!(result.is_err());
+
+        let suggestions = find_suggestions(&db, Namespace::Female, "col", 10).await?;
+        assert_eq!(suggestions.into_iter().map(|s| (s.value, s.count)).collect::<Vec<_>>(), vec![("collar".to_string(), 1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_facets_request() {
+        use hitomi_server_rs::domain::dto::FacetsRequest;
+        use hitomi_server_rs::domain::facet::Facet;
+        use hitomi_server_rs::mapper::facets_mapper::build_facets_statement;
+
+        let request = |json: serde_json::Value| -> FacetsRequest {
+            serde_json::from_value(json).expect("Failed to parse FacetsRequest")
+        };
+
+        let facets = request(serde_json::json!({"search": "artist:alp", "top_n": 5, "limits": {"types": 0, "tags": 20}}));
+        assert!(facets.validate().is_ok());
+        let limits = facets.resolved_limits();
+        assert_eq!(limits.len(), Facet::ALL.len());
+        assert_eq!((limits[&Facet::Tags], limits[&Facet::Artists], limits[&Facet::Types]), (20, 5, 0));
+
+        let sql = build_facets_statement("SELECT g.id, g.language_id, g.type FROM galleries g", vec![], &limits).sql;
+        assert!(sql.starts_with("WITH matched AS (SELECT g.id"));
+        assert!(sql.contains("'tags' AS facet") && sql.contains("LIMIT 20"));
+        assert!(!sql.contains("'types' AS facet"));
+
+        // sql はパースし直してから埋め込むので、末尾のコメントや `;` で閉じ括弧が壊れない
+        for (sql, expected) in [
+            ("SELECT gallery_id FROM galleries WHERE gallery_id > 10 -- newest", "SELECT gallery_id FROM galleries WHERE gallery_id > 10"),
+            ("SELECT gallery_id FROM galleries;", "SELECT gallery_id FROM galleries"),
+            ("SELECT gallery_id /* ids */ FROM galleries;\n-- done", "SELECT gallery_id FROM galleries"),
+        ] {
+            let normalized = request(serde_json::json!({"sql": sql})).normalized_sql();
+            assert_eq!(normalized.ok().flatten().as_deref(), Some(expected), "Failed to normalize: {}", sql);
+        }
+        assert!(request(serde_json::json!({"sql": "SELECT gallery_id FROM galleries; SELECT 1"})).normalized_sql().is_err());
+        assert_eq!(request(serde_json::json!({"search": "artist:alp"})).normalized_sql().ok(), Some(None));
+
+        for invalid in [
+            serde_json::json!({}),
+            serde_json::json!({"sql": "SELECT gallery_id FROM galleries", "search": "artist:alp"}),
+            serde_json::json!({"search": "artist:alp", "top_n": 100_000}),
+            serde_json::json!({"search": "artist:alp", "limits": {"tags": 100_000}}),
+        ] {
+            assert!(request(invalid.clone()).validate().is_err(), "Request should be rejected: {}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_facets_tag_namespaces() -> Result<()> {
+        use hitomi_server_rs::domain::facet::Facet;
+        use hitomi_server_rs::mapper::facets_mapper::{build_facets_statement, find_facets};
+        use hitomi_server_rs::search::parser::Namespace;
+        use sea_orm::{DbBackend, Statement};
+        use std::collections::BTreeMap;
+
+        let Some(db) = connect_test_schema("test_find_facets").await? else { return Ok(()) };
+        // SQL 側の名前空間は検索と同じ規則
+        for (male, female) in [(false, false), (true, false), (false, true), (true, true)] {
+            let row = db.query_one_raw(Statement::from_string(
+                DbBackend::Postgres,
+                format!("SELECT tag_namespace({male}, {female}) AS ns"),
+            )).await?.context("No row")?;
+            let expected = Namespace::from_tag_flags(male, female).and_then(|namespace| namespace.name());
+            assert_eq!(row.try_get::<Option<String>>("", "ns")?.as_deref(), expected, "{} {}", male, female);
+        }
+
+        db.execute_unprepared(
+            "INSERT INTO galleries (gallery_id, title, date, type, external_id) VALUES (1, 'a', now(), 'manga', ''); \
+             INSERT INTO tags (name, url, male, female) VALUES \
+                 ('collar', '', false, true), ('beard', '', true, false), ('full color', '', false, false), ('futanari', '', true, true); \
+             INSERT INTO gallery_tags (gallery_id, tag_id) VALUES (1, 1), (1, 2), (1, 3), (1, 4);"
+        ).await?;
+        let limits = BTreeMap::from([(Facet::Tags, 10)]);
+        let stmt = build_facets_statement("SELECT g.id, g.language_id, g.type FROM galleries g", vec![], &limits);
+        let facets = find_facets(&db, stmt, &limits).await?;
+        let tags: Vec<_> = facets.facets[&Facet::Tags].iter().map(|v| (v.ns.as_str(), v.value.as_str())).collect();
+        // male と female の両方が立つタグは検索できないので数えない
+        assert_eq!(tags, vec![("male", "beard"), ("female", "collar"), ("tag", "full color")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_title_query() {
+        use hitomi_server_rs::search::title::{build_title_search_statement, title_bigrams, TitleQuery};
+
+        assert_eq!(title_bigrams("ラブ ライブ"), vec!["イブ", "ブラ", "ライ", "ラブ"]);
+
+        let query = TitleQuery::parse("School スクール 愛 it's");
+        assert_eq!(query.words, "School it's");
+        assert_eq!(query.bigrams.as_deref(), Some("'クー' & 'スク' & 'ール' & '愛':*"));
+        assert_eq!(TitleQuery::parse("school idol").bigrams, None);
+
+        let stmt = build_title_search_statement("school", false, 10, 0);
+        assert!(stmt.sql.contains("websearch_to_tsquery('simple', $1)"));
+        assert!(!stmt.sql.contains("<%"));
+        let stmt = build_title_search_statement("スクール", true, 10, 0);
+        assert!(stmt.sql.contains("$1::tsquery") && stmt.sql.contains("$2 <% g.title"));
+    }
+
+    #[test]
+    fn test_bitmap_index() {
+        use hitomi_server_rs::search::bitmap_index::BitmapIndex;
+        use hitomi_server_rs::search::parser::{parse, Namespace};
+
+        let mut index = BitmapIndex::default();
+        for (namespace, value, gallery_id) in [
+            (Namespace::Female, "Collar", 1),
+            (Namespace::Female, "collar", 2),
+            (Namespace::Artist, "alp", 2),
+            (Namespace::Artist, "bob", 3),
+            (Namespace::Type, "manga", 3),
+            (Namespace::Language, "japanese", 4),
+        ] {
+            index.insert(namespace, value, gallery_id);
+        }
+
+        let evaluate = |index: &BitmapIndex, query: &str| -> Option<Vec<u32>> {
+            index.evaluate(&parse(query).expect("Failed to parse query")).map(|bitmap| bitmap.iter().collect())
+        };
+        // SQL の検索と同じく名前は完全一致で比較する (クエリ側は小文字になる)
+        assert_eq!(evaluate(&index, "female:collar"), Some(vec![2]));
+        assert_eq!(evaluate(&index, "female:collar -artist:alp"), Some(vec![]));
+        assert_eq!(evaluate(&index, "-female:collar"), Some(vec![1, 3, 4]));
+        assert_eq!(evaluate(&index, "artist:alp | type:manga"), Some(vec![2, 3]));
+        assert_eq!(evaluate(&index, "-(artist:alp | artist:bob) -language:japanese"), Some(vec![1]));
+        assert_eq!(evaluate(&index, "artist:nobody"), Some(vec![]));
+        // タイトル検索はインデックスでは評価できない
+        assert_eq!(evaluate(&index, "artist:alp school"), None);
+
+        // 結果は date の降順、同じ日時は gallery_id の降順に並ぶ
+        for (gallery_id, date) in [(1, 300), (2, 100), (3, 300), (4, 200)] {
+            index.insert_gallery(gallery_id, date);
+        }
+        let search = |index: &BitmapIndex, query: &str, after: Option<(i64, u32)>, offset: usize, limit: usize| -> Option<Vec<u32>> {
+            index.search(&parse(query).expect("Failed to parse query"), after, offset, limit)
+                .map(|page| page.into_iter().map(|(_, gallery_id)| gallery_id).collect())
+        };
+        assert_eq!(search(&index, "-artist:nobody", None, 0, 10), Some(vec![3, 1, 4, 2]));
+        assert_eq!(search(&index, "-artist:nobody", None, 1, 2), Some(vec![1, 4]));
+        assert_eq!(search(&index, "-artist:nobody", None, 4, 2), Some(vec![]));
+        // カーソルより後ろ (date, gallery_id) < (300, 1) から続ける
+        assert_eq!(search(&index, "-artist:nobody", Some((300, 1)), 0, 10), Some(vec![4, 2]));
+
+        index.remove_galleries(&[2u32].into_iter().collect());
+        assert_eq!(evaluate(&index, "female:collar"), Some(vec![]));
+        assert_eq!(search(&index, "-artist:nobody", None, 0, 10), Some(vec![3, 1, 4]));
+        assert_eq!(index.stats().galleries, 3);
+    }
+
+    #[tokio::test]
+    async fn test_bitmap_search_matches_sql() -> Result<()> {
+        use hitomi_server_rs::domain::cursor::{format_date_key, parse_date_key, Keyset};
+        use hitomi_server_rs::search::bitmap_index::{BitmapIndex, LoadFilter};
+        use hitomi_server_rs::search::{compiler, parser::parse};
+
+        let Some(db) = connect_test_schema("test_bitmap_search_matches_sql").await? else { return Ok(()) };
+        db.execute_unprepared(
+            "INSERT INTO languages (name) VALUES ('japanese'), ('english'); \
+             INSERT INTO galleries (gallery_id, title, date, type, external_id, language_id) VALUES \
+                 (1, 'a', '2024-01-02 00:00:00+00', 'manga', '', 1), \
+                 (2, 'b', '2024-01-01 00:00:00+00', 'doujinshi', '', 2), \
+                 (3, 'c', '2024-01-02 00:00:00+00', 'manga', '', 1), \
+                 (4, 'd', '2024-01-03 00:00:00+00', 'doujinshi', '', NULL), \
+                 (5, 'e', '2023-12-31 00:00:00.5+00', 'manga', '', 2); \
+             INSERT INTO tags (name, url, male, female) VALUES \
+                 ('collar', '', false, true), ('collar', '', true, false), ('collar', '', false, false), \
+                 ('collar', '', true, true), ('Glasses', '', false, true); \
+             INSERT INTO gallery_tags (gallery_id, tag_id) VALUES (1, 1), (2, 2), (3, 3), (4, 4), (5, 5), (5, 1); \
+             INSERT INTO artists (artist, url) VALUES ('alp', ''), ('Bob', ''); \
+             INSERT INTO gallery_artists (gallery_id, artist_id) VALUES (1, 1), (2, 2), (4, 1);"
+        ).await?;
+
+        let index = BitmapIndex::load(&db, &LoadFilter::All).await?;
+        for query in [
+            "female:collar", "male:collar", "tag:collar", "female:glasses", "artist:bob", "artist:alp",
+            "-female:collar", "-artist:alp type:manga", "language:japanese | language:english", "-language:japanese",
+        ] {
+            let parsed = parse(query).expect("Failed to parse query");
+            for (offset, limit) in [(0, 10), (1, 2), (3, 10)] {
+                let stmt = compiler::build_search_statement(&parsed, limit, offset);
+                let expected: Vec<u32> = db.query_all_raw(stmt).await?
+                    .iter()
+                    .map(|row| row.try_get::<i32>("", "gallery_id").map(|gallery_id| gallery_id as u32))
+                    .collect::<Result<_, _>>()?;
+                let actual = index.search(&parsed, None, offset as usize, limit as usize)
+                    .map(|page| page.into_iter().map(|(_, gallery_id)| gallery_id).collect::<Vec<_>>());
+                assert_eq!(actual.as_ref(), Some(&expected), "{} (offset {}, limit {})", query, offset, limit);
+            }
+
+            // カーソルでたどったページも同じになる (ビットマップ側で作ったカーソルを SQL に渡す)
+            let mut keyset = Keyset { sort_by: compiler::SORT_KEY, order: compiler::SORT_ORDER, after: None };
+            loop {
+                let stmt = compiler::build_search_keyset_statement(&parsed, &keyset, 2);
+                let rows = db.query_all_raw(stmt).await?;
+                let expected: Vec<(i64, u32)> = rows.iter()
+                    .map(|row| -> Result<(i64, u32)> {
+                        let key: String = row.try_get("", "sort_key")?;
+                        let date = parse_date_key(&key).with_context(|| format!("Failed to parse sort key: {}", key))?;
+                        Ok((date, row.try_get::<i32>("", "gallery_id")? as u32))
+                    })
+                    .collect::<Result<_>>()?;
+                let after = keyset.after.as_ref()
+                    .map(|cursor| (parse_date_key(&cursor.key).expect("cursor key is valid"), cursor.gallery_id as u32));
+                let actual = index.search(&parsed, after, 0, 2).expect("Query should be evaluated by the index");
+                assert_eq!(actual, expected, "{} (after {:?})", query, keyset.after);
+
+                let Some((date, gallery_id)) = actual.last() else { break };
+                let key = format_date_key(*date).expect("date is in range");
+                keyset.after = Some(keyset.next_cursor(key, *gallery_id as i32));
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bitmap_incremental_refresh() -> Result<()> {
+        use hitomi_server_rs::domain::gallery::Gallery;
+        use hitomi_server_rs::import::{session::ImportSession, ImportMode, Loader};
+        use hitomi_server_rs::mapper::galleries_mapper::load_galleries;
+        use hitomi_server_rs::search::bitmap_index::{Refresh, SharedBitmapIndex};
+        use hitomi_server_rs::search::parser::parse;
+
+        let gallery = |gallery_id: i32, tags: &[&str]| -> Gallery {
+            serde_json::from_value(serde_json::json!({
+                "gallery_id": gallery_id, "title": "a", "date": "2024-01-02T03:04:05+00:00", "type": "manga", "id": gallery_id.to_string(),
+                "files": [], "languages": [], "scene_indexes": [], "related": [],
+                "tags": tags.iter().map(|tag| serde_json::json!({"tag": tag, "url": "", "male": false, "female": true})).collect::<Vec<_>>(),
+                "artists": [], "groups": [], "characters": [], "parodies": [],
+            })).expect("Failed to build gallery")
+        };
+
+        let Some(db) = connect_test_schema("test_bitmap_incremental_refresh").await? else { return Ok(()) };
+        let session = ImportSession::new();
+        load_galleries(&db, vec![gallery(1, &["collar"]), gallery(2, &["collar"])], ImportMode::Upsert, Loader::Insert, &session).await?;
+        let index = SharedBitmapIndex::build(&db).await?;
+        let search = |query: &str| -> Vec<u32> {
+            index.evaluate(&parse(query).expect("Failed to parse query")).expect("Query should be evaluated by the index").iter().collect()
+        };
+        assert_eq!(search("female:collar"), vec![1, 2]);
+
+        // 更新したギャラリーは古い検索語から外れ、追加・削除も反映される
+        load_galleries(&db, vec![gallery(1, &["glasses"]), gallery(3, &["collar"])], ImportMode::Upsert, Loader::Copy, &session).await?;
+        db.execute_unprepared("DELETE FROM galleries WHERE gallery_id = 2").await?;
+        index.refresh(&db, Refresh::Incremental).await?;
+        assert_eq!(search("female:collar"), vec![3]);
+        assert_eq!(search("female:glasses"), vec![1]);
+        assert_eq!(index.all().iter().collect::<Vec<_>>(), vec![1, 3]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_gallery_round_trip() -> Result<()> {
+        use hitomi_server_rs::domain::gallery::Gallery;
+        use hitomi_server_rs::import::{session::ImportSession, ImportMode, Loader};
+        use hitomi_server_rs::mapper::galleries_mapper::{find_gallery_by_gallery_id, load_galleries};
+
+        // 関連エンティティは名前順ではない並びにしておく
+        let gallery = |tags: &[(&str, bool, bool)], artists: &[&str]| -> Gallery {
+            serde_json::from_value(serde_json::json!({
+                "gallery_id": 1, "title": "a", "date": "2024-01-02T03:04:05+00:00", "type": "manga", "id": "1",
+                "files": [{"name": "1.webp", "hash": "abc", "width": 10, "height": 20, "hasavif": 1}],
+                // 翻訳版は DB に無い Gallery を指していても、元データのまま返す
+                "languages": [
+                    {"name": "english", "language_localname": "English", "url": "/galleries/999.html", "galleryid": "999"},
+                    {"name": "japanese", "language_localname": "日本語", "url": "/doujinshi/a-japanese-1.html", "galleryid": "1"},
+                ],
+                "scene_indexes": [], "related": [],
+                "language": "japanese", "language_localname": "日本語", "language_url": "/index-japanese.html",
+                "tags": tags.iter().map(|(tag, male, female)| serde_json::json!({"tag": tag, "url": "", "male": male, "female": female})).collect::<Vec<_>>(),
+                "artists": artists.iter().map(|artist| serde_json::json!({"artist": artist, "url": ""})).collect::<Vec<_>>(),
+                "groups": [{"group": "zeta", "url": ""}, {"group": "alpha", "url": ""}],
+                "characters": [{"character": "rin", "url": ""}, {"character": "miku", "url": ""}],
+                "parodies": [{"parody": "vocaloid", "url": ""}, {"parody": "original", "url": ""}],
+            })).expect("Failed to build gallery")
+        };
+
+        for (schema, loader) in [("test_find_gallery_insert", Loader::Insert), ("test_find_gallery_copy", Loader::Copy)] {
+            let Some(db) = connect_test_schema(schema).await? else { return Ok(()) };
+            let session = ImportSession::new();
+
+            let original = gallery(&[("sole female", false, true), ("collar", false, true), ("anal", true, false)], &["zzz", "alp"]);
+            let expected = serde_json::to_value(&original)?;
+            load_galleries(&db, vec![original], ImportMode::Upsert, loader, &session).await?;
+            let found = find_gallery_by_gallery_id(&db, 1).await?.expect("gallery 1 exists");
+            assert_eq!(serde_json::to_value(&found)?, expected, "{:?}", loader);
+            // どちらの loader もコミットした ID を覚え、次のバッチでは覚えた ID を使う
+            assert_eq!(session.sizes().map(|(_, len)| len), [1, 3, 2, 2, 2, 2], "{:?}", loader);
+
+            // 再取り込みで並びが変わったら、既存の関連も新しい並び順で返す
+            let reordered = gallery(&[("anal", true, false), ("glasses", false, true), ("sole female", false, true)], &["alp", "zzz"]);
+            let expected = serde_json::to_value(&reordered)?;
+            load_galleries(&db, vec![reordered], ImportMode::Upsert, loader, &session).await?;
+            let found = find_gallery_by_gallery_id(&db, 1).await?.expect("gallery 1 exists");
+            assert_eq!(serde_json::to_value(&found)?, expected, "{:?}", loader);
+
+            // 覚えている名前でも url が変わったら送り直して上書きする
+            let mut relinked = gallery(&[("anal", true, false), ("glasses", false, true), ("sole female", false, true)], &["alp", "zzz"]);
+            relinked.tags[0].url = "/tag/male:anal-all.html".to_string();
+            relinked.artists[1].url = "/artist/zzz-all.html".to_string();
+            let expected = serde_json::to_value(&relinked)?;
+            load_galleries(&db, vec![relinked], ImportMode::Upsert, loader, &session).await?;
+            let found = find_gallery_by_gallery_id(&db, 1).await?.expect("gallery 1 exists");
+            assert_eq!(serde_json::to_value(&found)?, expected, "{:?}", loader);
+
+            assert!(find_gallery_by_gallery_id(&db, 2).await?.is_none());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reimport_removes_stale_links() -> Result<()> {
+        use hitomi_server_rs::domain::gallery::Gallery;
+        use hitomi_server_rs::import::{session::ImportSession, ImportMode, Loader};
+        use hitomi_server_rs::mapper::galleries_mapper::{load_galleries, sweep_orphaned_entities};
+        use sea_orm::{DbBackend, Statement};
+
+        let gallery = |gallery_id: i32, tags: &[&str]| -> Gallery {
+            serde_json::from_value(serde_json::json!({
+                "gallery_id": gallery_id, "title": "a", "date": "2024-01-02T03:04:05+00:00", "type": "manga", "id": "1",
+                "files": [], "languages": [], "scene_indexes": [], "related": [],
+                "tags": tags.iter().map(|tag| serde_json::json!({"tag": tag, "url": "", "female": true})).collect::<Vec<_>>(),
+            })).expect("Failed to build gallery")
+        };
+
+        for (schema, loader) in [("test_stale_links_insert", Loader::Insert), ("test_stale_links_copy", Loader::Copy)] {
+            let Some(db) = connect_test_schema(schema).await? else { return Ok(()) };
+            let session = ImportSession::new();
+            let linked_tags = |gallery_id: i32| {
+                let db = db.clone();
+                async move {
+                    db.query_all_raw(Statement::from_string(
+                        DbBackend::Postgres,
+                        format!("SELECT t.name FROM gallery_tags j JOIN galleries g ON g.id = j.gallery_id \
+                                 JOIN tags t ON t.id = j.tag_id WHERE g.gallery_id = {gallery_id} ORDER BY j.position"),
+                    )).await?
+                        .iter()
+                        .map(|row| row.try_get::<String>("", "name"))
+                        .collect::<Result<Vec<_>, _>>()
+                }
+            };
+
+            load_galleries(&db, vec![gallery(1, &["collar", "glasses"]), gallery(2, &["collar"])], ImportMode::Upsert, loader, &session).await?;
+            assert_eq!(linked_tags(1).await?, vec!["collar", "glasses"], "{:?}", loader);
+
+            // "glasses" を外して取り込み直すと中間テーブルの行が消え、他のギャラリーの関連は残る
+            load_galleries(&db, vec![gallery(1, &["collar"])], ImportMode::Upsert, loader, &session).await?;
+            assert_eq!(linked_tags(1).await?, vec!["collar"], "{:?}", loader);
+            assert_eq!(linked_tags(2).await?, vec!["collar"], "{:?}", loader);
+
+            // どこからも参照されなくなった "glasses" だけが掃除される
+            let removed = sweep_orphaned_entities(&db).await?;
+            assert_eq!(removed.iter().find(|(table, _)| *table == "tags"), Some(&("tags", 1)), "{:?}", loader);
+            assert!(removed.iter().all(|(table, count)| *table == "tags" || *count == 0), "{:?}", loader);
+            let remaining = db.query_all_raw(Statement::from_string(DbBackend::Postgres, "SELECT name FROM tags ORDER BY name"))
+                .await?
+                .iter()
+                .map(|row| row.try_get::<String>("", "name"))
+                .collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(remaining, vec!["collar"], "{:?}", loader);
+        }
+        Ok(())
+    }
+
+    /// DATABASE_URL の DB にテスト用のスキーマを作り直し、search_path をそこに向けて接続する
+    /// DB を使うテストの接続先 (DATABASE_URL が設定されていない場合はテストを飛ばす)
+    fn test_database_url() -> Option<String> {
+        let database_url = env::var("DATABASE_URL").ok();
+        if database_url.is_none() {
+            eprintln!("DATABASE_URL is not set, skipping");
+        }
+        database_url
+    }
+
+    /// テストごとのスキーマにテーブルを作って接続する (DATABASE_URL が無い場合は None)
+    async fn connect_test_schema(schema: &str) -> Result<Option<sea_orm::DatabaseConnection>> {
+        use hitomi_server_rs::import::ImportMode;
+
+        let Some(database_url) = test_database_url() else { return Ok(None) };
+        let admin = Database::connect(&database_url).await?;
+        admin.execute_unprepared(&format!("DROP SCHEMA IF EXISTS {schema} CASCADE")).await?;
+        admin.execute_unprepared(&format!("CREATE SCHEMA {schema}")).await?;
+        // 拡張は public に1つだけ作る (並列のテストと同時に作ろうとして失敗しても、どちらかが作れていればよい)
+        admin.execute_unprepared("CREATE EXTENSION IF NOT EXISTS pg_trgm SCHEMA public").await.ok();
+
+        let mut opt = ConnectOptions::new(&database_url).to_owned();
+        opt.max_connections(5).set_schema_search_path(format!("{schema},public"));
+        let db = Database::connect(opt).await?;
+        hitomi_server_rs::pipeline::import::prepare_tables(&db, ImportMode::Append).await?;
+        Ok(Some(db))
+    }
+
+    #[test]
+    fn test_nozomi_roundtrip() {
+        use hitomi_server_rs::nozomi;
+        use std::path::Path;
+
+        let ids = vec![3_000_000, 1, -1, 0];
+        let bytes = nozomi::encode(&ids);
+        // ビッグエンディアン
+        assert_eq!(&bytes[..4], &3_000_000i32.to_be_bytes());
+        assert_eq!(nozomi::parse(&bytes).expect("Failed to parse nozomi"), ids);
+        assert!(nozomi::parse(&bytes[..5]).is_err());
+        assert_eq!(nozomi::parse(&[]).expect("Failed to parse empty nozomi"), Vec::<i32>::new());
+
+        let name = nozomi::index_name(Path::new("data/nozomi"), Path::new("data/nozomi/tag/female:collar-all.nozomi"));
+        assert_eq!(name.as_deref(), Some("tag/female:collar-all"));
+    }
+
+    #[test]
+    fn test_nozomi_index_path() {
+        use hitomi_server_rs::nozomi::export::index_path;
+        use hitomi_server_rs::search::parser::{Namespace, Term};
+
+        let path = |namespace, value: &str| index_path(&Term { namespace, value: value.to_string() });
+        assert_eq!(path(Namespace::Female, "big breasts").as_deref(), Some("tag/female:big breasts-all.nozomi"));
+        assert_eq!(path(Namespace::Tag, "digital").as_deref(), Some("tag/digital-all.nozomi"));
+        assert_eq!(path(Namespace::Parody, "love live").as_deref(), Some("series/love live-all.nozomi"));
+        assert_eq!(path(Namespace::Type, "manga").as_deref(), Some("type/manga-all.nozomi"));
+        assert_eq!(path(Namespace::Language, "japanese").as_deref(), Some("index-japanese.nozomi"));
+        assert_eq!(path(Namespace::Artist, "a/b").as_deref(), Some("artist/a%2Fb-all.nozomi"));
+        assert_eq!(path(Namespace::Title, "x"), None);
+    }
+
+    #[tokio::test]
+    async fn test_nozomi_incremental_export() -> Result<()> {
+        use hitomi_server_rs::domain::gallery::Gallery;
+        use hitomi_server_rs::import::{session::ImportSession, ImportMode, Loader};
+        use hitomi_server_rs::mapper::galleries_mapper::load_galleries;
+        use hitomi_server_rs::nozomi::{self, export};
+
+        let gallery = |gallery_id: i32, tags: &[&str], artists: &[&str]| -> Gallery {
+            serde_json::from_value(serde_json::json!({
+                "gallery_id": gallery_id, "title": "a", "date": "2024-01-02T03:04:05+00:00", "type": "manga", "id": gallery_id.to_string(),
+                "files": [], "languages": [], "scene_indexes": [], "related": [],
+                "tags": tags.iter().map(|tag| serde_json::json!({"tag": tag, "url": "", "male": false, "female": true})).collect::<Vec<_>>(),
+                "artists": artists.iter().map(|artist| serde_json::json!({"artist": artist, "url": ""})).collect::<Vec<_>>(),
+                "groups": [], "characters": [], "parodies": [],
+            })).expect("Failed to build gallery")
+        };
+
+        let Some(db) = connect_test_schema("test_nozomi_export").await? else { return Ok(()) };
+        let session = ImportSession::new();
+        let dir = std::env::temp_dir().join(format!("nozomi_export_test_{}", std::process::id()));
+        let read = |path: &str| -> Result<Option<Vec<i32>>> {
+            match nozomi::read_file(&dir.join(path)) {
+                std::result::Result::Ok(ids) => Ok(Some(ids)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err.into()),
+            }
+        };
+
+        load_galleries(&db, vec![gallery(1, &["collar"], &["alp"]), gallery(2, &["collar"], &[])], ImportMode::Upsert, Loader::Insert, &session).await?;
+        export::export(&db, &dir, true).await?;
+        assert_eq!(read("tag/female:collar-all.nozomi")?, Some(vec![2, 1]));
+        assert_eq!(read("artist/alp-all.nozomi")?, Some(vec![1]));
+
+        // 更新したギャラリーの古い関連は取り除き、新しい関連のファイルを書く
+        load_galleries(&db, vec![gallery(1, &["glasses"], &["zzz"])], ImportMode::Upsert, Loader::Copy, &session).await?;
+        let stats = export::export(&db, &dir, false).await?;
+        assert_eq!(stats.changed, 1);
+        assert_eq!(read("tag/female:collar-all.nozomi")?, Some(vec![2]));
+        assert_eq!(read("tag/female:glasses-all.nozomi")?, Some(vec![1]));
+        assert_eq!(read("artist/alp-all.nozomi")?, None);
+        assert_eq!(read("artist/zzz-all.nozomi")?, Some(vec![1]));
+
+        // 変更が無ければ書き直さない
+        let stats = export::export(&db, &dir, false).await?;
+        assert_eq!((stats.changed, stats.written), (0, 1));
+
+        // 削除したギャラリーも取り除く
+        db.execute_unprepared("DELETE FROM galleries WHERE gallery_id = 2").await?;
+        export::export(&db, &dir, false).await?;
+        assert_eq!(read("tag/female:collar-all.nozomi")?, None);
+        assert_eq!(read("index-all.nozomi")?, Some(vec![1]));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_expr() {
+        use hitomi_server_rs::domain::dto::{SetsRequest, MAX_STATEMENT_TIMEOUT_MS};
+        use hitomi_server_rs::domain::set_expr::SetExpr;
+        use roaring::RoaringBitmap;
+
+        let parse = |json: serde_json::Value| -> SetExpr { serde_json::from_value(json).expect("Failed to parse set expression") };
+        let bitmap = |ids: &[u32]| ids.iter().copied().collect::<RoaringBitmap>();
+        let universe = bitmap(&[1, 2, 3, 4, 5]);
+
+        let expr = parse(serde_json::json!({"and": [
+            {"term": "female:collar"},
+            {"or": [{"term": "artist:alp"}, {"sql": {"query": "SELECT gallery_id FROM galleries"}}]},
+            {"not": {"term": "type:manga"}},
+        ]}));
+        assert_eq!(expr.operands().len(), 4);
+        assert!(!expr.needs_universe());
+        let results = [bitmap(&[1, 2, 3]), bitmap(&[1]), bitmap(&[2, 3]), bitmap(&[3])];
+        assert_eq!(expr.combine(&results, None), bitmap(&[1, 2]));
+
+        let expr = parse(serde_json::json!({"or": [{"term": "artist:alp"}, {"not": {"term": "type:manga"}}]}));
+        assert!(expr.needs_universe());
+        assert_eq!(expr.combine(&[bitmap(&[1]), bitmap(&[1, 2, 3])], Some(&universe)), bitmap(&[1, 4, 5]));
+
+        let expr = parse(serde_json::json!({"and": [{"not": {"term": "artist:alp"}}, {"not": {"term": "type:manga"}}]}));
+        assert!(expr.needs_universe());
+        assert_eq!(expr.combine(&[bitmap(&[1]), bitmap(&[2])], Some(&universe)), bitmap(&[3, 4, 5]));
+
+        // timeout_ms は SQLRequest と同じ既定値と範囲
+        let request = |json: serde_json::Value| -> SetsRequest { serde_json::from_value(json).expect("Failed to parse SetsRequest") };
+        let sets = request(serde_json::json!({"expr": {"term": "artist:alp"}}));
+        assert!(sets.validate().is_ok());
+        assert_eq!(sets.statement_timeout(), std::time::Duration::from_millis(30_000));
+        for timeout_ms in [0, MAX_STATEMENT_TIMEOUT_MS + 1] {
+            assert!(request(serde_json::json!({"expr": {"term": "artist:alp"}, "timeout_ms": timeout_ms})).validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_import_mode_arg() {
+        use hitomi_server_rs::import::{parse_mode_arg, ImportMode};
+
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let parse = |values: &[&str]| parse_mode_arg(args(values)).expect("Failed to parse mode");
+        assert_eq!(parse(&["bin", "a.json"]), (ImportMode::Upsert, args(&["bin", "a.json"])));
+        assert_eq!(parse(&["bin", "--mode=append", "a.json"]), (ImportMode::Append, args(&["bin", "a.json"])));
+        assert_eq!(parse(&["bin", "a.json", "--mode", "rebuild"]), (ImportMode::Rebuild, args(&["bin", "a.json"])));
+        assert!(parse_mode_arg(args(&["bin", "--mode=drop"])).is_err());
+        assert!(parse_mode_arg(args(&["bin", "--mode"])).is_err());
+    }
+
+    #[test]
+    fn test_tracked_lines() {
+        use hitomi_server_rs::import::checkpoint::TrackedLines;
+        use std::io::{Cursor, Seek, SeekFrom};
+
+        let data = b"{\"a\":1}\r\n\n{\"b\":2}\n\xff\n{\"c\":3}";
+        let mut lines = TrackedLines::new(Cursor::new(data.to_vec()), 0, 0);
+        assert_eq!(lines.next().map(|line| line.ok()), Some(Some("{\"a\":1}".to_string())));
+        assert_eq!(lines.byte_offset(), 9);
+        assert_eq!(lines.next().map(|line| line.ok()), Some(Some(String::new())));
+        assert_eq!(lines.next().map(|line| line.ok()), Some(Some("{\"b\":2}".to_string())));
+        let (offset, line_number) = (lines.byte_offset(), lines.line_number());
+        assert_eq!(line_number, 3);
+
+        // シークして途中から再開しても、同じ行と位置から続く
+        let mut reader = Cursor::new(data.to_vec());
+        reader.seek(SeekFrom::Start(offset)).expect("Failed to seek");
+        let mut resumed = TrackedLines::new(reader, offset, line_number);
+        // UTF-8 として不正な行もバイト数には数える
+        assert!(resumed.next().expect("Expected a line").is_err());
+        assert_eq!(resumed.next().map(|line| line.ok()), Some(Some("{\"c\":3}".to_string())));
+        assert_eq!((resumed.byte_offset(), resumed.line_number()), (data.len() as u64, 5));
+        assert!(resumed.next().is_none());
+    }
+
+    #[test]
+    fn test_dead_letter() {
+        use hitomi_server_rs::import::dead_letter::{self, DeadLetter, DeadLetterWriter, RejectKind, Stage};
+        use hitomi_server_rs::import::record;
+
+        // 404 は読み飛ばし、それ以外のステータスは拒否する
+        let ok = r#"{"gallery_id":7,"status":200,"raw_data":"var galleryinfo = {\"title\":\"t\"}","meta_data":""}"#;
+        assert_eq!(record::resp_to_raw_json(ok).expect("Expected OK"), Some(r#"{"gallery_id":7,"title":"t"}"#.to_string()));
+        let not_found = r#"{"gallery_id":8,"status":404,"raw_data":"","meta_data":""}"#;
+        assert_eq!(record::resp_to_raw_json(not_found).expect("Expected OK"), None);
+        let error = record::resp_to_raw_json(r#"{"gallery_id":9,"status":500,"raw_data":"","meta_data":"x"}"#).expect_err("Expected rejection");
+        assert_eq!((error.kind, error.gallery_id), (RejectKind::UnexpectedStatus, Some(9)));
+        let error = record::resp_to_raw_json(r#"{"gallery_id":10,"status":200,"raw_data":"<html>","meta_data":""}"#).expect_err("Expected rejection");
+        assert_eq!((error.kind, error.gallery_id), (RejectKind::UnexpectedFormat, Some(10)));
+
+        // パースできない行からも gallery_id を拾う
+        let error = record::parse_gallery(r#"{"gallery_id":"11","title":1}"#).expect_err("Expected rejection");
+        assert_eq!((error.kind, error.gallery_id), (RejectKind::Parse, Some(11)));
+        assert_eq!(record::sniff_gallery_id(b"{not json"), None);
+
+        // 書き込んだものがそのまま読める
+        let dir = std::env::temp_dir().join(format!("dead_letter_test_{}", std::process::id()));
+        let writer = DeadLetterWriter::new(dir.join("import_to_db.jsonl"));
+        assert!(!writer.path().exists());
+        let letter = DeadLetter::new(std::path::Path::new("a.json"), 3, Stage::ImportToDb, error, "{\"gallery_id\":\"11\"}");
+        writer.write(&letter).expect("Failed to write");
+        writer.write(&letter).expect("Failed to write");
+        assert_eq!(writer.count(), 2);
+        let letters = dead_letter::read_dead_letters(writer.path()).expect("Failed to read");
+        assert_eq!(letters, vec![letter.clone(), letter]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_resolve_inputs() {
+        use hitomi_server_rs::pipeline;
+        use std::path::{Path, PathBuf};
+
+        let dir = std::env::temp_dir().join(format!("resolve_inputs_test_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).expect("Failed to create dir");
+        for name in ["b.jsonl", "a.jsonl", "c.json", "sub/d.jsonl"] {
+            std::fs::write(dir.join(name), b"").expect("Failed to write");
+        }
+        let arg = |path: PathBuf| path.to_string_lossy().into_owned();
+
+        // ディレクトリは直下の拡張子が一致するファイルだけを名前順に
+        let inputs = pipeline::resolve_inputs(&[arg(dir.clone())], "jsonl").expect("Failed to resolve");
+        assert_eq!(inputs, vec![dir.join("a.jsonl"), dir.join("b.jsonl")]);
+
+        // glob とファイルの指定 (重複は取り除く)
+        let inputs = pipeline::resolve_inputs(&[arg(dir.join("**/*.jsonl")), arg(dir.join("c.json")), arg(dir.join("a.jsonl"))], "jsonl")
+            .expect("Failed to resolve");
+        assert_eq!(inputs, vec![dir.join("a.jsonl"), dir.join("b.jsonl"), dir.join("sub/d.jsonl"), dir.join("c.json")]);
+
+        assert!(pipeline::resolve_inputs(&[arg(dir.join("*.txt"))], "jsonl").is_err());
+        assert!(pipeline::resolve_inputs(&[arg(dir.join("missing.jsonl"))], "jsonl").is_err());
+
+        assert_eq!(pipeline::output_path(Path::new("data/raw_json/a.jsonl"), Path::new("out"), "json"), Path::new("out/a.json"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_resp_to_gallery() {
+        use hitomi_server_rs::import::dead_letter::{RejectKind, Stage};
+        use hitomi_server_rs::import::record;
+
+        // 中間ファイルを経由した場合と同じ Gallery になる (型の揺れや別名も同じく吸収する)
+        let galleryinfo = serde_json::json!({
+            "title": "t", "date": "2022-01-02 09:00:00-06", "files": [], "languages": [], "scene_indexes": [],
+            "type": "manga", "id": 7, "related": [1, "2"], "datepublished": "2021-02-01", "blocked": 0,
+            "tags": [{"tag": "a", "url": "/tag/female%3Aa-all.html", "female": "1"}],
+        });
+        let line = serde_json::json!({
+            "gallery_id": 7, "status": 200, "raw_data": format!("var galleryinfo = {}", galleryinfo), "meta_data": "",
+        }).to_string();
+        let line = line.as_str();
+        let staged = record::resp_to_raw_json(line).expect("Expected OK").expect("Expected gallery");
+        let staged = record::normalize_raw_json(staged.as_bytes()).expect("Expected OK");
+        let gallery = record::resp_to_gallery(line).expect("Expected OK").expect("Expected gallery");
+        assert_eq!(serde_json::to_string(&gallery).expect("Failed to serialize"), staged);
+
+        let not_found = r#"{"gallery_id":8,"status":404,"raw_data":"","meta_data":""}"#;
+        assert!(record::resp_to_gallery(not_found).expect("Expected OK").is_none());
+
+        // 失敗した段階とその段階の入力を返す
+        let (stage, rejection, raw) = record::resp_to_gallery("{").expect_err("Expected rejection");
+        assert_eq!((stage, rejection.kind, raw.as_str()), (Stage::RespToJson, RejectKind::Parse, "{"));
+        let bad = r#"{"gallery_id":9,"status":200,"raw_data":"var galleryinfo = {\"title\":3}","meta_data":""}"#;
+        let (stage, rejection, raw) = record::resp_to_gallery(bad).expect_err("Expected rejection");
+        assert_eq!((stage, rejection.gallery_id), (Stage::NormalizeJson, Some(9)));
+        assert_eq!(record::sniff_gallery_id(raw.as_bytes()), Some(9));
+    }
+
+    #[test]
+    fn test_compressed_io() {
+        use hitomi_server_rs::import::checkpoint::Checkpoint;
+        use hitomi_server_rs::pipeline::{self, compression::{self, Compression, OutputWriter}};
+        use indicatif::ProgressBar;
+        use std::io::{BufRead, Write};
+        use std::path::Path;
+
+        let dir = std::env::temp_dir().join(format!("compressed_io_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("Failed to create dir");
+
+        // 書いた形式をマジックナンバーから判定して読める
+        for compression in [Compression::None, Compression::Zstd, Compression::Gzip] {
+            let path = compression.output_path(&dir.join("a.jsonl"));
+            let mut writer = OutputWriter::create(&path, compression).expect("Failed to create");
+            writer.write_all(b"{\"a\":1}\n{\"b\":2}\n{\"c\":3}\n").expect("Failed to write");
+            writer.finish().expect("Failed to finish");
+
+            let (detected, reader) = compression::open_input(&path, None).expect("Failed to open");
+            assert_eq!(detected, compression);
+            assert_eq!(reader.lines().count(), 3);
+
+            // 圧縮されていても、チェックポイントの行から読み直せる
+            let start = Checkpoint { byte_offset: 8, line_number: 1, ..Default::default() };
+            let mut lines = compression::open_lines(&path, start, &ProgressBar::hidden()).expect("Failed to open");
+            assert_eq!(lines.next().map(|line| line.ok()), Some(Some("{\"b\":2}".to_string())));
+            assert_eq!(lines.line_number(), 2);
+        }
+
+        // 圧縮の拡張子は入力の選択と出力のファイル名では無視する
+        let inputs = pipeline::resolve_inputs(&[dir.to_string_lossy().into_owned()], "jsonl").expect("Failed to resolve");
+        assert_eq!(inputs, vec![dir.join("a.jsonl"), dir.join("a.jsonl.gz"), dir.join("a.jsonl.zst")]);
+        assert!(!pipeline::has_extension(Path::new("a.json.zst"), "jsonl"));
+        assert_eq!(pipeline::output_path(Path::new("raw/a.jsonl.zst"), Path::new("out"), "json"), Path::new("out/a.json"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_binary_copy() {
+        use chrono::{DateTime, NaiveDate};
+        use hitomi_server_rs::import::Loader;
+        use hitomi_server_rs::mapper::pg_copy::BinaryCopy;
+
+        assert_eq!("copy".parse::<Loader>().ok(), Some(Loader::Copy));
+        assert!("bulk".parse::<Loader>().is_err());
+
+        // ヘッダーとトレーラーだけの空のデータ
+        assert_eq!(BinaryCopy::new().finish(), b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0\xff\xff");
+
+        let date = DateTime::parse_from_rfc3339("2000-01-01T09:00:01+09:00").expect("Failed to parse");
+        let mut rows = BinaryCopy::new();
+        rows.row(5)
+            .int4(7)
+            .opt_text(None)
+            .timestamptz(&date)
+            .opt_date(NaiveDate::from_ymd_opt(2000, 1, 3))
+            .int4_array(&[]);
+        rows.row(1).text_array(&["ab".to_string()]);
+        assert_eq!(rows.rows(), 2);
+
+        let data = rows.finish();
+        let body = &data[19..data.len() - 2];
+        let mut expected = vec![0, 5];
+        expected.extend([0, 0, 0, 4, 0, 0, 0, 7]);
+        expected.extend([0xff, 0xff, 0xff, 0xff]);
+        // 2000-01-01 00:00:01 UTC は PostgreSQL の epoch から 1 秒
+        expected.extend([0, 0, 0, 8]);
+        expected.extend(1_000_000i64.to_be_bytes());
+        expected.extend([0, 0, 0, 4, 0, 0, 0, 2]);
+        // 空配列は次元数 0
+        expected.extend([0, 0, 0, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 23]);
+        expected.extend([0, 1]);
+        expected.extend([0, 0, 0, 26, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 25, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 2, b'a', b'b']);
+        assert_eq!(body, expected.as_slice());
+    }
+
+    #[test]
+    fn test_import_session() {
+        use hitomi_server_rs::import::session::ImportSession;
+
+        let session = ImportSession::new();
+        // clone したものは同じキャッシュを共有する
+        let shared = session.clone();
+        shared.artists().extend([("a".to_string(), "/a".to_string(), 1), ("b".to_string(), "/b".to_string(), 2)]);
+        shared.tags().extend([(("t".to_string(), true, false), "/t".to_string(), 3)]);
+
+        assert_eq!(session.artists().get(&"a".to_string(), &"/a".to_string()), Some(1));
+        assert_eq!(session.tags().get(&("t".to_string(), false, false), &"/t".to_string()), None);
+        // url が変わった名前は覚えていないものとして扱う
+        assert_eq!(session.artists().get(&"a".to_string(), &"/a2".to_string()), None);
+
+        // 覚えているものだけを返す
+        let entries = [("b".to_string(), "/b".to_string()), ("c".to_string(), "/c".to_string())];
+        let known = session.artists().lookup(entries.iter().map(|(name, url)| (name, url)));
+        assert_eq!(known.len(), 1);
+        assert_eq!(known.get("b"), Some(&2));
+        // 同じ名前が別の url でも現れる場合は覚えていないものとして扱う
+        let entries = [("b".to_string(), "/b".to_string()), ("b".to_string(), "/b2".to_string())];
+        assert!(session.artists().lookup(entries.iter().map(|(name, url)| (name, url))).is_empty());
+
+        assert_eq!(session.sizes()[1], ("tags", 1));
+        assert_eq!(session.sizes()[2], ("artists", 2));
+
+        shared.clear();
+        assert!(session.artists().is_empty());
+        assert!(session.tags().is_empty());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,83 @@
+use sea_orm::*;
+use std::collections::BTreeMap;
+use crate::domain::facet::{Facet, FacetValue, FacetsResponse};
+use crate::search::parser::format_term;
+
+/// 集計ごとの (名前空間の式, 値の式, matched (エイリアス `m`) からの JOIN, GROUP BY)
+fn facet_source(facet: Facet) -> (&'static str, &'static str, &'static str, &'static str) {
+    match facet {
+        Facet::Tags => (
+            // /search と同じ名前空間にし、どの名前空間でも検索できない (male と female の両方が立つ) タグは数えない
+            "tag_namespace(e.male, e.female)",
+            "e.name",
+            "JOIN gallery_tags j ON j.gallery_id = m.id JOIN tags e ON e.id = j.tag_id AND tag_namespace(e.male, e.female) IS NOT NULL",
+            "e.id",
+        ),
+        Facet::Artists => ("'artist'", "e.artist", "JOIN gallery_artists j ON j.gallery_id = m.id JOIN artists e ON e.id = j.artist_id", "e.id"),
+        Facet::Groups => ("'group'", "e.\"group\"", "JOIN gallery_groups j ON j.gallery_id = m.id JOIN groups e ON e.id = j.group_id", "e.id"),
+        Facet::Characters => ("'character'", "e.character", "JOIN gallery_characters j ON j.gallery_id = m.id JOIN characters e ON e.id = j.character_id", "e.id"),
+        Facet::Parodies => ("'series'", "e.parody", "JOIN gallery_parodies j ON j.gallery_id = m.id JOIN parodies e ON e.id = j.parody_id", "e.id"),
+        Facet::Languages => ("'language'", "e.name", "JOIN languages e ON e.id = m.language_id", "e.id"),
+        Facet::Types => ("'type'", "m.type", "", "m.type"),
+    }
+}
+
+/// 結果セットの各項目の上位 N 件と総数を1つのクエリで集計する Statement を作る
+///
+/// `matched_sql` は galleries (エイリアス `g`) から `g.id, g.language_id, g.type` を返すクエリで、
+/// `values` はそのバインドパラメータ。上位 N 件が 0 の項目は集計しない。
+pub fn build_facets_statement(matched_sql: &str, values: Vec<Value>, limits: &BTreeMap<Facet, u32>) -> Statement {
+    let mut parts = vec!["(SELECT 'total' AS facet, NULL::text AS ns, NULL::text AS value, COUNT(*) AS count FROM matched m)".to_string()];
+    for (facet, limit) in limits {
+        if *limit == 0 {
+            continue;
+        }
+        let (ns, value, joins, group_by) = facet_source(*facet);
+        parts.push(format!(
+            "(SELECT '{name}' AS facet, {ns} AS ns, {value} AS value, COUNT(*) AS count \
+             FROM matched m {joins} GROUP BY {group_by} \
+             ORDER BY count DESC, value LIMIT {limit})",
+            name = facet.name(),
+        ));
+    }
+
+    let sql = format!("WITH matched AS ({}) {}", matched_sql, parts.join(" UNION ALL "));
+    Statement::from_sql_and_values(DbBackend::Postgres, sql, values)
+}
+
+pub async fn find_facets<C: ConnectionTrait>(
+    db: &C,
+    stmt: Statement,
+    limits: &BTreeMap<Facet, u32>,
+) -> Result<FacetsResponse, DbErr> {
+    let mut total = 0;
+    // 0 件の項目も空の配列として返す
+    let mut facets: BTreeMap<Facet, Vec<FacetValue>> = limits.iter()
+        .filter(|(_, limit)| **limit > 0)
+        .map(|(facet, _)| (*facet, vec![]))
+        .collect();
+
+    for row in db.query_all_raw(stmt).await? {
+        let name: String = row.try_get("", "facet")?;
+        let count: i64 = row.try_get("", "count")?;
+        let Some(facet) = Facet::ALL.into_iter().find(|facet| facet.name() == name) else {
+            total = count;
+            continue;
+        };
+        let ns: String = row.try_get("", "ns")?;
+        let value: String = row.try_get("", "value")?;
+        facets.entry(facet).or_default().push(FacetValue {
+            term: format_term(&ns, &value),
+            ns,
+            value,
+            count,
+        });
+    }
+
+    // UNION ALL は順序を保証しないので並べ直す
+    for values in facets.values_mut() {
+        values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    }
+
+    Ok(FacetsResponse { total, facets })
+}
@@ -0,0 +1,183 @@
+use sea_orm::*;
+use crate::import::{ImportMode, Loader};
+use crate::mapper::pg_copy;
+
+/// fbs_galleries の1行（圧縮形式は fbs_compress_types.name に解決済み）
+pub struct FbsGalleryRow {
+    pub gallery_id: i32,
+    pub data: Vec<u8>,
+    pub compress_type: String,
+}
+
+/// gallery_id から圧縮済みの FlatBuffers を取得する
+pub async fn find_fbs_gallery(
+    db: &DatabaseConnection,
+    gallery_id: i32,
+) -> Result<Option<FbsGalleryRow>, DbErr> {
+    let stmt = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT f.gallery_id, f.data, t.name AS compress_type \
+         FROM fbs_galleries f JOIN fbs_compress_types t ON t.id = f.compress_type \
+         WHERE f.gallery_id = $1",
+        vec![gallery_id.into()],
+    );
+
+    let Some(row) = db.query_one_raw(stmt).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(FbsGalleryRow {
+        gallery_id: row.try_get("", "gallery_id")?,
+        data: row.try_get("", "data")?,
+        compress_type: row.try_get("", "compress_type")?,
+    }))
+}
+
+/// 複数の gallery_id に対応する FlatBuffers をまとめて取得する
+/// 戻り値の順序は保証しない（存在しない gallery_id は含まれない）
+pub async fn find_fbs_galleries(
+    db: &DatabaseConnection,
+    gallery_ids: Vec<i32>,
+) -> Result<Vec<FbsGalleryRow>, DbErr> {
+    if gallery_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let stmt = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT f.gallery_id, f.data, t.name AS compress_type \
+         FROM fbs_galleries f JOIN fbs_compress_types t ON t.id = f.compress_type \
+         WHERE f.gallery_id = ANY($1)",
+        vec![gallery_ids.into()],
+    );
+
+    db.query_all_raw(stmt)
+        .await?
+        .into_iter()
+        .map(|row| Ok(FbsGalleryRow {
+            gallery_id: row.try_get("", "gallery_id")?,
+            data: row.try_get("", "data")?,
+            compress_type: row.try_get("", "compress_type")?,
+        }))
+        .collect()
+}
+
+/// fbs_compress_types の ID を取得する (無ければ作成する)
+pub async fn get_or_create_compress_type(
+    db: &DatabaseConnection,
+    name: &str,
+) -> Result<i32, DbErr> {
+    // まず既存のIDを検索
+    let query = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT id FROM fbs_compress_types WHERE name = $1",
+        vec![name.into()],
+    );
+
+    if let Some(row) = db.query_one_raw(query).await? {
+        return row.try_get("", "id");
+    }
+
+    // 存在しない場合は挿入
+    let insert_query = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "INSERT INTO fbs_compress_types (name) VALUES ($1) RETURNING id",
+        vec![name.into()],
+    );
+
+    db.query_one_raw(insert_query)
+        .await?
+        .ok_or_else(|| DbErr::Custom(format!("Failed to insert compress_type: {}", name)))?
+        .try_get("", "id")
+}
+
+/// (gallery_id, 圧縮済みデータ, compress_type) をまとめて挿入する
+///
+/// `ImportMode::Append` の場合は既存の行を残し、それ以外の場合は上書きする。
+pub async fn insert_fbs_batch(
+    db: &DatabaseConnection,
+    batch: Vec<(i32, Vec<u8>, i32)>,
+    mode: ImportMode,
+) -> Result<(), DbErr> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    // バッチインサート用のSQLを構築
+    let mut values_parts = Vec::with_capacity(batch.len());
+    let mut params: Vec<Value> = Vec::with_capacity(batch.len() * 3);
+
+    for (idx, (gallery_id, compressed_data, compress_type_id)) in batch.into_iter().enumerate() {
+        let param_idx = idx * 3;
+        values_parts.push(format!("(${}, ${}, ${})", param_idx + 1, param_idx + 2, param_idx + 3));
+
+        params.push(gallery_id.into());
+        params.push(compressed_data.into());
+        params.push(compress_type_id.into());
+    }
+
+    let sql = format!(
+        "INSERT INTO fbs_galleries (gallery_id, data, compress_type) VALUES {} ON CONFLICT (gallery_id) {}",
+        values_parts.join(", "),
+        on_conflict(mode)
+    );
+
+    db.execute_raw(Statement::from_sql_and_values(DbBackend::Postgres, &sql, params)).await?;
+    Ok(())
+}
+
+/// (gallery_id, 圧縮済みデータ, compress_type) を COPY (バイナリ形式) で一時テーブルに送ってから挿入する
+///
+/// 結果は `insert_fbs_batch` と同じ。
+pub async fn copy_fbs_batch(
+    db: &DatabaseConnection,
+    batch: Vec<(i32, Vec<u8>, i32)>,
+    mode: ImportMode,
+) -> Result<(), DbErr> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    let mut rows = pg_copy::BinaryCopy::new();
+    for (gallery_id, compressed_data, compress_type_id) in &batch {
+        rows.row(3).int4(*gallery_id).bytea(compressed_data).int4(*compress_type_id);
+    }
+
+    let mut txn = db.get_postgres_connection_pool().begin().await.map_err(pg_copy::sqlx_err)?;
+    pg_copy::execute(
+        &mut txn,
+        "CREATE TEMP TABLE copy_fbs_galleries (gallery_id INTEGER NOT NULL, data BYTEA NOT NULL, compress_type INTEGER NOT NULL) \
+         ON COMMIT DROP",
+    ).await?;
+    pg_copy::copy_in(&mut txn, "COPY copy_fbs_galleries FROM STDIN (FORMAT binary)", rows).await?;
+
+    let sql = format!(
+        "INSERT INTO fbs_galleries (gallery_id, data, compress_type) \
+         SELECT gallery_id, data, compress_type FROM copy_fbs_galleries ON CONFLICT (gallery_id) {}",
+        on_conflict(mode)
+    );
+    pg_copy::execute(&mut txn, &sql).await?;
+    txn.commit().await.map_err(pg_copy::sqlx_err)?;
+    Ok(())
+}
+
+/// `loader` で選んだ方法でまとめて挿入する
+pub async fn load_fbs_batch(
+    db: &DatabaseConnection,
+    batch: Vec<(i32, Vec<u8>, i32)>,
+    mode: ImportMode,
+    loader: Loader,
+) -> Result<(), DbErr> {
+    match loader {
+        Loader::Insert => insert_fbs_batch(db, batch, mode).await,
+        Loader::Copy => copy_fbs_batch(db, batch, mode).await,
+    }
+}
+
+/// `ImportMode::Append` の場合は既存の行を残し、それ以外の場合は上書きする
+fn on_conflict(mode: ImportMode) -> &'static str {
+    match mode {
+        ImportMode::Append => "DO NOTHING",
+        ImportMode::Upsert | ImportMode::Rebuild => "DO UPDATE SET data = EXCLUDED.data, compress_type = EXCLUDED.compress_type",
+    }
+}
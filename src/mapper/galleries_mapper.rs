@@ -1,12 +1,21 @@
 use sea_orm::*;
 use crate::domain;
 use crate::entity::{self, prelude::*};
-use sea_orm::sea_query::OnConflict;
+use crate::import::{ImportMode, Loader};
+use crate::import::session::{EntityCache, ImportSession};
+use crate::mapper::pg_copy;
+use sea_orm::sea_query::{Alias, Expr, OnConflict};
 
 /// 複数の Gallery を一括で永続化する
+///
+/// `ImportMode::Append` の場合は既に存在する gallery_id を読み飛ばし、
+/// それ以外の場合は ON CONFLICT (gallery_id) DO UPDATE で上書きする。
+/// 言語・タグなどは `session` が ID を覚えていないものだけを upsert し、コミット後に覚える。
 pub async fn insert_many_galleries(
     db: &DatabaseConnection,
     galleries: Vec<domain::gallery::Gallery>,
+    mode: ImportMode,
+    session: &ImportSession,
 ) -> Result<(), DbErr> {
     if galleries.is_empty() {
         return Ok(());
@@ -15,32 +24,53 @@ pub async fn insert_many_galleries(
     let txn = db.begin().await
         .map_err(|e| DbErr::Custom(format!("Failed to begin transaction: {}", e)))?;
 
+    // 0. Append の場合は既存のギャラリーを除く
+    let galleries = match mode {
+        ImportMode::Append => {
+            let existing: std::collections::HashSet<i32> = Gallery::find()
+                .select_only()
+                .column(entity::gallery::Column::GalleryId)
+                .filter(entity::gallery::Column::GalleryId.is_in(galleries.iter().map(|g| g.gallery_id)))
+                .into_tuple::<i32>()
+                .all(&txn)
+                .await?
+                .into_iter()
+                .collect();
+            galleries.into_iter().filter(|g| !existing.contains(&g.gallery_id)).collect()
+        }
+        ImportMode::Upsert | ImportMode::Rebuild => galleries,
+    };
+    if galleries.is_empty() {
+        txn.rollback().await.ok();
+        return Ok(());
+    }
+
     // 1. 関連エンティティの一括 Upsert と ID マッピングの作成
     // 各エンティティごとにユニークなリストを作成して Upsert し、
     // (name/key -> id) の Map を作る
 
     // Language
-    let languages: std::collections::HashMap<String, i32> = upsert_languages(&txn, &galleries).await
+    let languages: std::collections::HashMap<String, i32> = upsert_languages(&txn, &galleries, session.languages()).await
         .map_err(|e| DbErr::Custom(format!("Failed to upsert languages: {}", e)))?;
 
     // Tag
-    let tags: std::collections::HashMap<(String, bool, bool), i32> = upsert_tags(&txn, &galleries).await
+    let tags: std::collections::HashMap<(String, bool, bool), i32> = upsert_tags(&txn, &galleries, session.tags()).await
         .map_err(|e| DbErr::Custom(format!("Failed to upsert tags: {}", e)))?;
 
     // Artist
-    let artists: std::collections::HashMap<String, i32> = upsert_artists(&txn, &galleries).await
+    let artists: std::collections::HashMap<String, i32> = upsert_artists(&txn, &galleries, session.artists()).await
         .map_err(|e| DbErr::Custom(format!("Failed to upsert artists: {}", e)))?;
 
     // Group
-    let groups: std::collections::HashMap<String, i32> = upsert_groups(&txn, &galleries).await
+    let groups: std::collections::HashMap<String, i32> = upsert_groups(&txn, &galleries, session.groups()).await
         .map_err(|e| DbErr::Custom(format!("Failed to upsert groups: {}", e)))?;
 
     // Character
-    let characters: std::collections::HashMap<String, i32> = upsert_characters(&txn, &galleries).await
+    let characters: std::collections::HashMap<String, i32> = upsert_characters(&txn, &galleries, session.characters()).await
         .map_err(|e| DbErr::Custom(format!("Failed to upsert characters: {}", e)))?;
 
     // Parody
-    let parodies: std::collections::HashMap<String, i32> = upsert_parodies(&txn, &galleries).await
+    let parodies: std::collections::HashMap<String, i32> = upsert_parodies(&txn, &galleries, session.parodies()).await
         .map_err(|e| DbErr::Custom(format!("Failed to upsert parodies: {}", e)))?;
 
     // 2. Gallery の一括 Upsert
@@ -60,7 +90,7 @@ pub async fn insert_many_galleries(
         entity::gallery::ActiveModel {
             gallery_id: Set(g.gallery_id),
             title: Set(g.title.clone()),
-            date: Set(g.date.clone()),
+            date: Set(g.date),
             type_: Set(g.type_.clone()),
             external_id: Set(g.id.clone()),
             scene_indexes: Set(g.scene_indexes.clone()),
@@ -68,10 +98,11 @@ pub async fn insert_many_galleries(
             japanese_title: Set(g.japanese_title.clone()),
             language_id: Set(lang_id),
             translation_group_id: Set(translation_group_id),
+            languages: Set(serde_json::to_value(&g.languages).unwrap()),
             video: Set(g.video.clone()),
             videofilename: Set(g.videofilename.clone()),
             gallery_url: Set(g.gallery_url.clone()),
-            date_published: Set(g.date_published.clone()),
+            date_published: Set(g.date_published),
             blocked: Set(g.blocked),
             files: Set(serde_json::to_value(&g.files).unwrap()),
             ..Default::default()
@@ -94,6 +125,7 @@ pub async fn insert_many_galleries(
             entity::gallery::Column::JapaneseTitle,
             entity::gallery::Column::LanguageId,
             entity::gallery::Column::TranslationGroupId,
+            entity::gallery::Column::Languages,
             entity::gallery::Column::Video,
             entity::gallery::Column::Videofilename,
             entity::gallery::Column::GalleryUrl,
@@ -101,6 +133,8 @@ pub async fn insert_many_galleries(
             entity::gallery::Column::Blocked,
             entity::gallery::Column::Files,
         ])
+        // 増分エクスポートが更新された行を見つけられるように、更新したトランザクションを記録する
+        .value(Alias::new("change_xid"), Expr::cust("pg_current_xact_id()"))
         .to_owned();
 
     // insert_many を実行（ON CONFLICT 付き）
@@ -144,9 +178,9 @@ pub async fn insert_many_galleries(
         )));
     }
 
-    // 3. 中間テーブルの一括 Insert
+    // 3. 中間テーブルの同期
     // 中間テーブルは (gallery_id, other_id) のペア。
-    // 重複エラーを避けるため、ON CONFLICT DO NOTHING を使う。
+    // 入ってきた Gallery に無くなった関連は削除し、残りは Gallery での並び順 (position) とともに挿入する。
 
     let mut gallery_tags = Vec::new();
     let mut gallery_artists = Vec::new();
@@ -157,106 +191,484 @@ pub async fn insert_many_galleries(
     for gallery in &galleries {
         if let Some(&gid) = gallery_map.get(&gallery.gallery_id) {
             // Tags
-            for tag in &gallery.tags {
+            for (position, tag) in gallery.tags.iter().enumerate() {
                 if let Some(&tid) = tags.get(&(tag.tag.clone(), tag.male, tag.female)) {
-                    gallery_tags.push(entity::gallery_tag::ActiveModel {
-                        gallery_id: Set(gid),
-                        tag_id: Set(tid),
-                    });
+                    gallery_tags.push((gid, tid, position as i32));
                 }
             }
             // Artists
-            for artist in &gallery.artists {
+            for (position, artist) in gallery.artists.iter().enumerate() {
                 if let Some(&aid) = artists.get(&artist.artist) {
-                    gallery_artists.push(entity::gallery_artist::ActiveModel {
-                        gallery_id: Set(gid),
-                        artist_id: Set(aid),
-                    });
+                    gallery_artists.push((gid, aid, position as i32));
                 }
             }
             // Groups
-            for group in &gallery.groups {
+            for (position, group) in gallery.groups.iter().enumerate() {
                 if let Some(&grid) = groups.get(&group.group) {
-                    gallery_groups.push(entity::gallery_group::ActiveModel {
-                        gallery_id: Set(gid),
-                        group_id: Set(grid),
-                    });
+                    gallery_groups.push((gid, grid, position as i32));
                 }
             }
             // Characters
-            for character in &gallery.characters {
+            for (position, character) in gallery.characters.iter().enumerate() {
                 if let Some(&cid) = characters.get(&character.character) {
-                    gallery_characters.push(entity::gallery_character::ActiveModel {
-                        gallery_id: Set(gid),
-                        character_id: Set(cid),
-                    });
+                    gallery_characters.push((gid, cid, position as i32));
                 }
             }
             // Parodies
-            for parody in &gallery.parodies {
+            for (position, parody) in gallery.parodies.iter().enumerate() {
                 if let Some(&pid) = parodies.get(&parody.parody) {
-                    gallery_parodies.push(entity::gallery_parody::ActiveModel {
-                        gallery_id: Set(gid),
-                        parody_id: Set(pid),
-                    });
+                    gallery_parodies.push((gid, pid, position as i32));
                 }
             }
         }
     }
 
-    // 一括挿入実行 (ON CONFLICT DO NOTHING)
+    // 同じ関連が重複していると ON CONFLICT DO UPDATE が失敗するため、最初の出現だけを残す
+    for links in [&mut gallery_tags, &mut gallery_artists, &mut gallery_groups, &mut gallery_characters, &mut gallery_parodies] {
+        let mut seen = std::collections::HashSet::new();
+        links.retain(|&(gid, other_id, _)| seen.insert((gid, other_id)));
+    }
+
+    // 古い関連の削除 (バッチ内のギャラリーのうち、今回の関連に含まれないもの)
+    let gallery_row_ids: Vec<i32> = gallery_map.values().copied().collect();
+    delete_stale_links(&txn, "gallery_tags", "tag_id", &gallery_row_ids, &gallery_tags).await?;
+    delete_stale_links(&txn, "gallery_artists", "artist_id", &gallery_row_ids, &gallery_artists).await?;
+    delete_stale_links(&txn, "gallery_groups", "group_id", &gallery_row_ids, &gallery_groups).await?;
+    delete_stale_links(&txn, "gallery_characters", "character_id", &gallery_row_ids, &gallery_characters).await?;
+    delete_stale_links(&txn, "gallery_parodies", "parody_id", &gallery_row_ids, &gallery_parodies).await?;
+
+    // 一括挿入実行
+    // 既存の関連は並び順が変わった場合だけ position を更新する
     if !gallery_tags.is_empty() {
-        entity::gallery_tag::Entity::insert_many(gallery_tags)
-            .on_conflict(OnConflict::columns([entity::gallery_tag::Column::GalleryId, entity::gallery_tag::Column::TagId]).do_nothing().to_owned())
+        entity::gallery_tag::Entity::insert_many(gallery_tags.into_iter().map(|(gid, tid, position)| entity::gallery_tag::ActiveModel {
+            gallery_id: Set(gid),
+            tag_id: Set(tid),
+            position: Set(position),
+        }))
+            .on_conflict(position_changed(OnConflict::columns([entity::gallery_tag::Column::GalleryId, entity::gallery_tag::Column::TagId]), entity::gallery_tag::Column::Position, "gallery_tags"))
             .do_nothing()
             .exec(&txn).await?;
     }
     if !gallery_artists.is_empty() {
-        entity::gallery_artist::Entity::insert_many(gallery_artists)
-            .on_conflict(OnConflict::columns([entity::gallery_artist::Column::GalleryId, entity::gallery_artist::Column::ArtistId]).do_nothing().to_owned())
+        entity::gallery_artist::Entity::insert_many(gallery_artists.into_iter().map(|(gid, aid, position)| entity::gallery_artist::ActiveModel {
+            gallery_id: Set(gid),
+            artist_id: Set(aid),
+            position: Set(position),
+        }))
+            .on_conflict(position_changed(OnConflict::columns([entity::gallery_artist::Column::GalleryId, entity::gallery_artist::Column::ArtistId]), entity::gallery_artist::Column::Position, "gallery_artists"))
             .do_nothing()
             .exec(&txn).await?;
     }
     if !gallery_groups.is_empty() {
-        entity::gallery_group::Entity::insert_many(gallery_groups)
-            .on_conflict(OnConflict::columns([entity::gallery_group::Column::GalleryId, entity::gallery_group::Column::GroupId]).do_nothing().to_owned())
+        entity::gallery_group::Entity::insert_many(gallery_groups.into_iter().map(|(gid, grid, position)| entity::gallery_group::ActiveModel {
+            gallery_id: Set(gid),
+            group_id: Set(grid),
+            position: Set(position),
+        }))
+            .on_conflict(position_changed(OnConflict::columns([entity::gallery_group::Column::GalleryId, entity::gallery_group::Column::GroupId]), entity::gallery_group::Column::Position, "gallery_groups"))
             .do_nothing()
             .exec(&txn).await?;
     }
     if !gallery_characters.is_empty() {
-        entity::gallery_character::Entity::insert_many(gallery_characters)
-            .on_conflict(OnConflict::columns([entity::gallery_character::Column::GalleryId, entity::gallery_character::Column::CharacterId]).do_nothing().to_owned())
+        entity::gallery_character::Entity::insert_many(gallery_characters.into_iter().map(|(gid, cid, position)| entity::gallery_character::ActiveModel {
+            gallery_id: Set(gid),
+            character_id: Set(cid),
+            position: Set(position),
+        }))
+            .on_conflict(position_changed(OnConflict::columns([entity::gallery_character::Column::GalleryId, entity::gallery_character::Column::CharacterId]), entity::gallery_character::Column::Position, "gallery_characters"))
             .do_nothing()
             .exec(&txn).await?;
     }
     if !gallery_parodies.is_empty() {
-        entity::gallery_parody::Entity::insert_many(gallery_parodies)
-            .on_conflict(OnConflict::columns([entity::gallery_parody::Column::GalleryId, entity::gallery_parody::Column::ParodyId]).do_nothing().to_owned())
+        entity::gallery_parody::Entity::insert_many(gallery_parodies.into_iter().map(|(gid, pid, position)| entity::gallery_parody::ActiveModel {
+            gallery_id: Set(gid),
+            parody_id: Set(pid),
+            position: Set(position),
+        }))
+            .on_conflict(position_changed(OnConflict::columns([entity::gallery_parody::Column::GalleryId, entity::gallery_parody::Column::ParodyId]), entity::gallery_parody::Column::Position, "gallery_parodies"))
             .do_nothing()
             .exec(&txn).await?;
     }
 
     txn.commit().await?;
+
+    // コミットした ID だけを覚える (ロールバックした ID は存在しない)
+    // url は同じ名前のうち最後に送ったもの (= DB に残ったもの) を覚える
+    session.languages().extend(languages.into_iter().map(|(name, id)| (name, (), id)));
+    session.tags().extend(galleries.iter().flat_map(|g| &g.tags).filter_map(|t| {
+        let key = (t.tag.clone(), t.male, t.female);
+        tags.get(&key).map(|&id| (key, t.url.clone(), id))
+    }));
+    session.artists().extend(galleries.iter().flat_map(|g| &g.artists)
+        .filter_map(|a| artists.get(&a.artist).map(|&id| (a.artist.clone(), a.url.clone(), id))));
+    session.groups().extend(galleries.iter().flat_map(|g| &g.groups)
+        .filter_map(|item| groups.get(&item.group).map(|&id| (item.group.clone(), item.url.clone(), id))));
+    session.characters().extend(galleries.iter().flat_map(|g| &g.characters)
+        .filter_map(|item| characters.get(&item.character).map(|&id| (item.character.clone(), item.url.clone(), id))));
+    session.parodies().extend(galleries.iter().flat_map(|g| &g.parodies)
+        .filter_map(|item| parodies.get(&item.parody).map(|&id| (item.parody.clone(), item.url.clone(), id))));
+    Ok(())
+}
+
+/// `loader` で選んだ方法で複数の Gallery を一括で永続化する
+pub async fn load_galleries(
+    db: &DatabaseConnection,
+    galleries: Vec<domain::gallery::Gallery>,
+    mode: ImportMode,
+    loader: Loader,
+    session: &ImportSession,
+) -> Result<(), DbErr> {
+    match loader {
+        Loader::Insert => insert_many_galleries(db, galleries, mode, session).await,
+        Loader::Copy => copy_many_galleries(db, galleries, mode, session).await,
+    }
+}
+
+/// COPY で取り込む関連エンティティと、その一時テーブル
+struct CopyEntity {
+    /// 一時テーブル (gallery_id, name, url[, male, female], entity_id, position)
+    /// entity_id は `ImportSession` が覚えている ID (覚えていなければ NULL)、position は Gallery での並び順
+    staging: &'static str,
+    table: &'static str,
+    /// エンティティのテーブルに挿入する列と、それに対応する一時テーブルの列
+    columns: &'static str,
+    source: &'static str,
+    /// ユニーク制約の列 (一時テーブル側 / エンティティのテーブル側)
+    distinct: &'static str,
+    conflict: &'static str,
+    /// 一時テーブル s とエンティティのテーブル e の結合条件
+    join: &'static str,
+    junction: &'static str,
+    foreign_key: &'static str,
+}
+
+const COPY_ENTITIES: [CopyEntity; 5] = [
+    CopyEntity {
+        staging: "copy_tags", table: "tags", columns: "name, url, male, female", source: "name, url, male, female",
+        distinct: "name, male, female", conflict: "name, male, female",
+        join: "e.name = s.name AND e.male = s.male AND e.female = s.female",
+        junction: "gallery_tags", foreign_key: "tag_id",
+    },
+    CopyEntity {
+        staging: "copy_artists", table: "artists", columns: "artist, url", source: "name, url",
+        distinct: "name", conflict: "artist", join: "e.artist = s.name",
+        junction: "gallery_artists", foreign_key: "artist_id",
+    },
+    CopyEntity {
+        staging: "copy_groups", table: "groups", columns: "\"group\", url", source: "name, url",
+        distinct: "name", conflict: "\"group\"", join: "e.\"group\" = s.name",
+        junction: "gallery_groups", foreign_key: "group_id",
+    },
+    CopyEntity {
+        staging: "copy_characters", table: "characters", columns: "character, url", source: "name, url",
+        distinct: "name", conflict: "character", join: "e.character = s.name",
+        junction: "gallery_characters", foreign_key: "character_id",
+    },
+    CopyEntity {
+        staging: "copy_parodies", table: "parodies", columns: "parody, url", source: "name, url",
+        distinct: "name", conflict: "parody", join: "e.parody = s.name",
+        junction: "gallery_parodies", foreign_key: "parody_id",
+    },
+];
+
+/// 複数の Gallery を COPY (バイナリ形式) で一時テーブルに送ってから永続化する
+///
+/// 結果は `insert_many_galleries` と同じ。一時テーブルはトランザクションの終わりに削除される。
+/// `insert_many_galleries` と同じく、`session` が ID を覚えていないものだけを upsert し、コミット後に覚える。
+pub async fn copy_many_galleries(
+    db: &DatabaseConnection,
+    galleries: Vec<domain::gallery::Gallery>,
+    mode: ImportMode,
+    session: &ImportSession,
+) -> Result<(), DbErr> {
+    if galleries.is_empty() {
+        return Ok(());
+    }
+
+    let mut txn = db.get_postgres_connection_pool().begin().await.map_err(pg_copy::sqlx_err)?;
+
+    // 0. Append の場合は既存のギャラリーを除く
+    let galleries: Vec<domain::gallery::Gallery> = match mode {
+        ImportMode::Append => {
+            let gallery_ids: Vec<i32> = galleries.iter().map(|g| g.gallery_id).collect();
+            let existing: std::collections::HashSet<i32> = pg_copy::fetch_ids(
+                &mut txn, "SELECT gallery_id FROM galleries WHERE gallery_id = ANY($1)", gallery_ids,
+            ).await?.into_iter().collect();
+            galleries.into_iter().filter(|g| !existing.contains(&g.gallery_id)).collect()
+        }
+        ImportMode::Upsert | ImportMode::Rebuild => galleries,
+    };
+    if galleries.is_empty() {
+        return Ok(());
+    }
+
+    // 1. 一時テーブルの作成
+    execute_all(&mut txn, [
+        "CREATE TEMP TABLE copy_galleries (gallery_id INTEGER NOT NULL, title TEXT NOT NULL, date TIMESTAMPTZ NOT NULL, \
+         type TEXT NOT NULL, external_id TEXT NOT NULL, scene_indexes INTEGER[] NOT NULL, related_ids TEXT[] NOT NULL, \
+         japanese_title TEXT, language TEXT, language_local_name TEXT, language_url TEXT, language_id INTEGER, \
+         translation_group_id TEXT[] NOT NULL, video TEXT, videofilename TEXT, gallery_url TEXT, date_published DATE, \
+         blocked BOOLEAN NOT NULL, files JSONB NOT NULL, languages JSONB NOT NULL) ON COMMIT DROP".to_string(),
+        "CREATE TEMP TABLE copy_tags (gallery_id INTEGER NOT NULL, name TEXT NOT NULL, url TEXT NOT NULL, \
+         male BOOLEAN NOT NULL, female BOOLEAN NOT NULL, entity_id INTEGER, position INTEGER NOT NULL) ON COMMIT DROP".to_string(),
+    ].into_iter().chain(COPY_ENTITIES[1..].iter().map(|entity| format!(
+        "CREATE TEMP TABLE {} (gallery_id INTEGER NOT NULL, name TEXT NOT NULL, url TEXT NOT NULL, entity_id INTEGER, \
+         position INTEGER NOT NULL) ON COMMIT DROP",
+        entity.staging,
+    ))).collect()).await?;
+
+    // 2. 一時テーブルへの COPY
+    let mut gallery_rows = pg_copy::BinaryCopy::new();
+    let mut tag_rows = pg_copy::BinaryCopy::new();
+    let mut artist_rows = pg_copy::BinaryCopy::new();
+    let mut group_rows = pg_copy::BinaryCopy::new();
+    let mut character_rows = pg_copy::BinaryCopy::new();
+    let mut parody_rows = pg_copy::BinaryCopy::new();
+
+    // ID と url を覚えている名前は entity_id を埋めておき、マージしない
+    let tag_keys: Vec<((String, bool, bool), &String)> = galleries.iter()
+        .flat_map(|g| g.tags.iter().map(|t| ((t.tag.clone(), t.male, t.female), &t.url)))
+        .collect();
+    let known_tags = session.tags().lookup(tag_keys.iter().map(|(key, url)| (key, *url)));
+    let known_artists = session.artists().lookup(galleries.iter().flat_map(|g| g.artists.iter().map(|a| (&a.artist, &a.url))));
+    let known_groups = session.groups().lookup(galleries.iter().flat_map(|g| g.groups.iter().map(|item| (&item.group, &item.url))));
+    let known_characters = session.characters().lookup(galleries.iter().flat_map(|g| g.characters.iter().map(|item| (&item.character, &item.url))));
+    let known_parodies = session.parodies().lookup(galleries.iter().flat_map(|g| g.parodies.iter().map(|item| (&item.parody, &item.url))));
+
+    for g in &galleries {
+        let translation_group_id: Vec<String> = g.languages.iter()
+            .map(|lang| lang.galleryid.clone())
+            .collect();
+        gallery_rows.row(20)
+            .int4(g.gallery_id)
+            .text(&g.title)
+            .timestamptz(&g.date)
+            .text(&g.type_)
+            .text(&g.id)
+            .int4_array(&g.scene_indexes)
+            .text_array(&g.related)
+            .opt_text(g.japanese_title.as_deref())
+            .opt_text(g.language.as_deref())
+            .opt_text(g.language_localname.as_deref())
+            .opt_text(g.language_url.as_deref())
+            .opt_int4(g.language.as_ref().and_then(|l| session.languages().get(l, &())))
+            .text_array(&translation_group_id)
+            .opt_text(g.video.as_deref())
+            .opt_text(g.videofilename.as_deref())
+            .opt_text(g.gallery_url.as_deref())
+            .opt_date(g.date_published)
+            .bool(g.blocked)
+            .jsonb(&serde_json::to_value(&g.files).unwrap())
+            .jsonb(&serde_json::to_value(&g.languages).unwrap());
+
+        for (position, tag) in g.tags.iter().enumerate() {
+            let tag_id = known_tags.get(&(tag.tag.clone(), tag.male, tag.female)).copied();
+            tag_rows.row(7).int4(g.gallery_id).text(&tag.tag).text(&tag.url).bool(tag.male).bool(tag.female).opt_int4(tag_id).int4(position as i32);
+        }
+        for (position, artist) in g.artists.iter().enumerate() {
+            let artist_id = known_artists.get(&artist.artist).copied();
+            artist_rows.row(5).int4(g.gallery_id).text(&artist.artist).text(&artist.url).opt_int4(artist_id).int4(position as i32);
+        }
+        for (position, group) in g.groups.iter().enumerate() {
+            let group_id = known_groups.get(&group.group).copied();
+            group_rows.row(5).int4(g.gallery_id).text(&group.group).text(&group.url).opt_int4(group_id).int4(position as i32);
+        }
+        for (position, character) in g.characters.iter().enumerate() {
+            let character_id = known_characters.get(&character.character).copied();
+            character_rows.row(5).int4(g.gallery_id).text(&character.character).text(&character.url).opt_int4(character_id).int4(position as i32);
+        }
+        for (position, parody) in g.parodies.iter().enumerate() {
+            let parody_id = known_parodies.get(&parody.parody).copied();
+            parody_rows.row(5).int4(g.gallery_id).text(&parody.parody).text(&parody.url).opt_int4(parody_id).int4(position as i32);
+        }
+    }
+
+    pg_copy::copy_in(&mut txn, "COPY copy_galleries FROM STDIN (FORMAT binary)", gallery_rows).await?;
+    for (entity, rows) in COPY_ENTITIES.iter().zip([tag_rows, artist_rows, group_rows, character_rows, parody_rows]) {
+        if rows.rows() > 0 {
+            pg_copy::copy_in(&mut txn, &format!("COPY {} FROM STDIN (FORMAT binary)", entity.staging), rows).await?;
+        }
+    }
+
+    // 3. 関連エンティティのマージ (ID と url を覚えていないものだけ)
+    // バッチ内で重複する場合は後の行を使う (COPY した順に並ぶので ctid の降順)。
+    // 既存の言語はそのまま、それ以外は url を上書きする
+    let mut statements = vec![
+        "INSERT INTO languages (name, local_name, url) \
+         SELECT DISTINCT ON (language) language, language_local_name, language_url FROM copy_galleries \
+         WHERE language IS NOT NULL AND language_id IS NULL ORDER BY language, ctid DESC ON CONFLICT (name) DO NOTHING".to_string(),
+    ];
+    statements.extend(COPY_ENTITIES.iter().map(|entity| format!(
+        "INSERT INTO {table} ({columns}) SELECT DISTINCT ON ({distinct}) {source} FROM {staging} \
+         WHERE entity_id IS NULL ORDER BY {distinct}, ctid DESC ON CONFLICT ({conflict}) DO UPDATE SET url = EXCLUDED.url",
+        table = entity.table, columns = entity.columns, distinct = entity.distinct,
+        source = entity.source, staging = entity.staging, conflict = entity.conflict,
+    )));
+    execute_all(&mut txn, statements).await?;
+
+    // 4. マージしたエンティティの ID を一時テーブルに埋め、コミット後に覚えるために受け取る
+    let languages: Vec<(i32, String)> = pg_copy::fetch_all(
+        &mut txn,
+        "UPDATE copy_galleries c SET language_id = l.id FROM languages l \
+         WHERE l.name = c.language AND c.language_id IS NULL RETURNING l.id, l.name",
+    ).await?;
+    let resolve = |entity: &CopyEntity, key: &str| format!(
+        "UPDATE {staging} s SET entity_id = e.id FROM {table} e WHERE {join} AND s.entity_id IS NULL RETURNING e.id, {key}",
+        staging = entity.staging, table = entity.table, join = entity.join,
+    );
+    // url はマージ後に DB に残ったもの
+    let tags: Vec<(i32, String, bool, bool, String)> = pg_copy::fetch_all(&mut txn, &resolve(&COPY_ENTITIES[0], "s.name, s.male, s.female, e.url")).await?;
+    let artists: Vec<(i32, String, String)> = pg_copy::fetch_all(&mut txn, &resolve(&COPY_ENTITIES[1], "s.name, e.url")).await?;
+    let groups: Vec<(i32, String, String)> = pg_copy::fetch_all(&mut txn, &resolve(&COPY_ENTITIES[2], "s.name, e.url")).await?;
+    let characters: Vec<(i32, String, String)> = pg_copy::fetch_all(&mut txn, &resolve(&COPY_ENTITIES[3], "s.name, e.url")).await?;
+    let parodies: Vec<(i32, String, String)> = pg_copy::fetch_all(&mut txn, &resolve(&COPY_ENTITIES[4], "s.name, e.url")).await?;
+
+    // 5. Gallery のマージ (ON CONFLICT (gallery_id) DO UPDATE)
+    let mut statements = vec![
+        "INSERT INTO galleries (gallery_id, title, date, type, external_id, scene_indexes, related_ids, japanese_title, \
+         language_id, translation_group_id, languages, video, videofilename, gallery_url, date_published, blocked, files) \
+         SELECT c.gallery_id, c.title, c.date, c.type, c.external_id, c.scene_indexes, c.related_ids, c.japanese_title, \
+         c.language_id, c.translation_group_id, c.languages, c.video, c.videofilename, c.gallery_url, c.date_published, \
+         c.blocked, c.files \
+         FROM copy_galleries c \
+         ON CONFLICT (gallery_id) DO UPDATE SET title = EXCLUDED.title, date = EXCLUDED.date, type = EXCLUDED.type, \
+         external_id = EXCLUDED.external_id, scene_indexes = EXCLUDED.scene_indexes, related_ids = EXCLUDED.related_ids, \
+         japanese_title = EXCLUDED.japanese_title, language_id = EXCLUDED.language_id, \
+         translation_group_id = EXCLUDED.translation_group_id, languages = EXCLUDED.languages, video = EXCLUDED.video, \
+         videofilename = EXCLUDED.videofilename, gallery_url = EXCLUDED.gallery_url, \
+         date_published = EXCLUDED.date_published, blocked = EXCLUDED.blocked, files = EXCLUDED.files, \
+         change_xid = pg_current_xact_id()".to_string(),
+    ];
+
+    // 6. 中間テーブルの同期 (今回の関連に含まれないものを削除してから挿入する)
+    // 既存の関連は並び順が変わった場合だけ position を更新する
+    for entity in &COPY_ENTITIES {
+        statements.push(format!(
+            "DELETE FROM {junction} j USING galleries g, copy_galleries c \
+             WHERE g.id = j.gallery_id AND g.gallery_id = c.gallery_id AND NOT EXISTS (\
+             SELECT 1 FROM {staging} s WHERE s.gallery_id = c.gallery_id AND s.entity_id = j.{foreign_key})",
+            junction = entity.junction, staging = entity.staging, foreign_key = entity.foreign_key,
+        ));
+        statements.push(format!(
+            "INSERT INTO {junction} (gallery_id, {foreign_key}, position) \
+             SELECT g.id, s.entity_id, min(s.position) FROM {staging} s JOIN galleries g ON g.gallery_id = s.gallery_id \
+             GROUP BY g.id, s.entity_id \
+             ON CONFLICT (gallery_id, {foreign_key}) DO UPDATE SET position = EXCLUDED.position \
+             WHERE {junction}.position <> EXCLUDED.position",
+            junction = entity.junction, staging = entity.staging, foreign_key = entity.foreign_key,
+        ));
+    }
+    execute_all(&mut txn, statements).await?;
+
+    txn.commit().await.map_err(pg_copy::sqlx_err)?;
+
+    // コミットした ID だけを覚える (ロールバックした ID は存在しない)
+    session.languages().extend(languages.into_iter().map(|(id, name)| (name, (), id)));
+    session.tags().extend(tags.into_iter().map(|(id, name, male, female, url)| ((name, male, female), url, id)));
+    session.artists().extend(artists.into_iter().map(|(id, name, url)| (name, url, id)));
+    session.groups().extend(groups.into_iter().map(|(id, name, url)| (name, url, id)));
+    session.characters().extend(characters.into_iter().map(|(id, name, url)| (name, url, id)));
+    session.parodies().extend(parodies.into_iter().map(|(id, name, url)| (name, url, id)));
     Ok(())
 }
 
+/// galleries.change_xid が `$1` (前回読み込んだときの `snapshot_xmin`) 以上の行 = 前回以降に追加・更新された可能性がある行
+pub const CHANGED_SINCE: &str = "change_xid >= $1::bigint::text::xid8";
+
+/// トランザクションのスナップショットの xmin
+///
+/// これより前のトランザクションの変更はすべてスナップショットに含まれるので、次回は
+/// `CHANGED_SINCE` でこの値以上の行を読めば、見えていなかった変更を漏らさない。
+/// REPEATABLE READ のトランザクションで最初に呼ぶこと。
+pub async fn snapshot_xmin<C: ConnectionTrait>(db: &C) -> Result<i64, DbErr> {
+    let row = db.query_one_raw(Statement::from_string(
+        DbBackend::Postgres,
+        "SELECT pg_snapshot_xmin(pg_current_snapshot())::text::bigint AS xmin",
+    )).await?.ok_or_else(|| DbErr::RecordNotFound("snapshot xmin".to_string()))?;
+    row.try_get("", "xmin")
+}
+
+/// どのギャラリーからも参照されていない tags / artists / groups / characters / parodies を削除する
+///
+/// 戻り値は (テーブル名, 削除した行数) の一覧。
+pub async fn sweep_orphaned_entities(db: &DatabaseConnection) -> Result<Vec<(&'static str, u64)>, DbErr> {
+    let targets = [
+        ("tags", "gallery_tags", "tag_id"),
+        ("artists", "gallery_artists", "artist_id"),
+        ("groups", "gallery_groups", "group_id"),
+        ("characters", "gallery_characters", "character_id"),
+        ("parodies", "gallery_parodies", "parody_id"),
+    ];
+
+    let txn = db.begin().await?;
+    let mut removed = Vec::with_capacity(targets.len());
+    for (table, junction, foreign_key) in targets {
+        let result = txn.execute_raw(Statement::from_string(
+            DbBackend::Postgres,
+            format!("DELETE FROM {table} e WHERE NOT EXISTS (SELECT 1 FROM {junction} j WHERE j.{foreign_key} = e.id)"),
+        )).await?;
+        removed.push((table, result.rows_affected()));
+    }
+    txn.commit().await?;
+    Ok(removed)
+}
+
 // ========================================
 // Private Helper Functions (Batch)
 // ========================================
 
+/// `gallery_ids` のギャラリーの関連のうち、`links` に含まれないものを削除する
+async fn delete_stale_links(
+    db: &DatabaseTransaction,
+    junction: &str,
+    foreign_key: &str,
+    gallery_ids: &[i32],
+    links: &[(i32, i32, i32)],
+) -> Result<(), DbErr> {
+    let (link_gallery_ids, link_other_ids): (Vec<i32>, Vec<i32>) = links.iter().map(|&(gid, other_id, _)| (gid, other_id)).unzip();
+    db.execute_raw(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        format!(
+            "DELETE FROM {junction} j WHERE j.gallery_id = ANY($1) AND NOT EXISTS (\
+             SELECT 1 FROM unnest($2::int[], $3::int[]) AS keep(gallery_id, other_id) \
+             WHERE keep.gallery_id = j.gallery_id AND keep.other_id = j.{foreign_key})"
+        ),
+        vec![gallery_ids.to_vec().into(), link_gallery_ids.into(), link_other_ids.into()],
+    )).await?;
+    Ok(())
+}
+
+/// 中間テーブルの ON CONFLICT で、並び順が変わった場合だけ position を更新する
+/// (変わっていない行は書き換えないので、再取り込みで不要な行が増えない)
+fn position_changed(mut on_conflict: OnConflict, position: impl sea_orm::sea_query::IntoIden, junction: &str) -> OnConflict {
+    on_conflict
+        .update_column(position)
+        .action_and_where(Expr::cust(format!("{junction}.position <> EXCLUDED.position")))
+        .to_owned()
+}
+
+/// `statements` を順に実行する (COPY 用のトランザクション)
+async fn execute_all(txn: &mut sqlx::PgConnection, statements: Vec<String>) -> Result<(), DbErr> {
+    for sql in &statements {
+        pg_copy::execute(txn, sql).await?;
+    }
+    Ok(())
+}
+
 async fn upsert_languages(
     db: &DatabaseTransaction,
     galleries: &[domain::gallery::Gallery],
+    cache: &EntityCache<String>,
 ) -> Result<std::collections::HashMap<String, i32>, DbErr> {
-    let mut map = std::collections::HashMap::new();
+    // ID を覚えている名前は送らない
+    let mut map = cache.lookup(galleries.iter().filter_map(|g| g.language.as_ref()).map(|name| (name, &())));
     let mut to_insert = std::collections::HashMap::new();
 
     for g in galleries {
-        if let Some(name) = &g.language {
-            if !map.contains_key(name) {
+        if let Some(name) = &g.language
+            && !map.contains_key(name) {
                 to_insert.insert(name.clone(), (g.language_localname.clone(), g.language_url.clone()));
             }
-        }
     }
 
     if to_insert.is_empty() {
@@ -313,8 +725,13 @@ async fn upsert_languages(
 async fn upsert_tags(
     db: &DatabaseTransaction,
     galleries: &[domain::gallery::Gallery],
+    cache: &EntityCache<(String, bool, bool), String>,
 ) -> Result<std::collections::HashMap<(String, bool, bool), i32>, DbErr> {
-    let mut map = std::collections::HashMap::new();
+    // ID と url を覚えているタグは送らない
+    let keys: Vec<((String, bool, bool), &String)> = galleries.iter()
+        .flat_map(|g| g.tags.iter().map(|t| ((t.tag.clone(), t.male, t.female), &t.url)))
+        .collect();
+    let mut map = cache.lookup(keys.iter().map(|(key, url)| (key, *url)));
     let mut to_insert = std::collections::HashMap::new();
 
     for g in galleries {
@@ -374,8 +791,10 @@ async fn upsert_tags(
 async fn upsert_artists(
     db: &DatabaseTransaction,
     galleries: &[domain::gallery::Gallery],
+    cache: &EntityCache<String, String>,
 ) -> Result<std::collections::HashMap<String, i32>, DbErr> {
-    let mut map = std::collections::HashMap::new();
+    // ID と url を覚えている名前は送らない
+    let mut map = cache.lookup(galleries.iter().flat_map(|g| g.artists.iter().map(|a| (&a.artist, &a.url))));
     let mut to_insert = std::collections::HashMap::new();
 
     for g in galleries {
@@ -426,8 +845,10 @@ async fn upsert_artists(
 async fn upsert_groups(
     db: &DatabaseTransaction,
     galleries: &[domain::gallery::Gallery],
+    cache: &EntityCache<String, String>,
 ) -> Result<std::collections::HashMap<String, i32>, DbErr> {
-    let mut map = std::collections::HashMap::new();
+    // ID と url を覚えている名前は送らない
+    let mut map = cache.lookup(galleries.iter().flat_map(|g| g.groups.iter().map(|item| (&item.group, &item.url))));
     let mut to_insert = std::collections::HashMap::new();
 
     for g in galleries {
@@ -478,8 +899,10 @@ async fn upsert_groups(
 async fn upsert_characters(
     db: &DatabaseTransaction,
     galleries: &[domain::gallery::Gallery],
+    cache: &EntityCache<String, String>,
 ) -> Result<std::collections::HashMap<String, i32>, DbErr> {
-    let mut map = std::collections::HashMap::new();
+    // ID と url を覚えている名前は送らない
+    let mut map = cache.lookup(galleries.iter().flat_map(|g| g.characters.iter().map(|item| (&item.character, &item.url))));
     let mut to_insert = std::collections::HashMap::new();
 
     for g in galleries {
@@ -530,8 +953,10 @@ async fn upsert_characters(
 async fn upsert_parodies(
     db: &DatabaseTransaction,
     galleries: &[domain::gallery::Gallery],
+    cache: &EntityCache<String, String>,
 ) -> Result<std::collections::HashMap<String, i32>, DbErr> {
-    let mut map = std::collections::HashMap::new();
+    // ID と url を覚えている名前は送らない
+    let mut map = cache.lookup(galleries.iter().flat_map(|g| g.parodies.iter().map(|item| (&item.parody, &item.url))));
     let mut to_insert = std::collections::HashMap::new();
 
     for g in galleries {
@@ -605,33 +1030,33 @@ pub async fn insert_gallery(
     let gallery_id = upsert_gallery(&txn, &gallery, language_id).await?;
 
     // 3. Tag の upsert と link
-    for tag in &gallery.tags {
+    for (position, tag) in gallery.tags.iter().enumerate() {
         let tag_id = upsert_tag(&txn, &tag.tag, &tag.url, tag.male, tag.female).await?;
-        link_gallery_tag(&txn, gallery_id, tag_id).await?;
+        link_gallery_tag(&txn, gallery_id, tag_id, position as i32).await?;
     }
 
     // 4. Artist の upsert と link
-    for artist in &gallery.artists {
+    for (position, artist) in gallery.artists.iter().enumerate() {
         let artist_id = upsert_artist(&txn, &artist.artist, &artist.url).await?;
-        link_gallery_artist(&txn, gallery_id, artist_id).await?;
+        link_gallery_artist(&txn, gallery_id, artist_id, position as i32).await?;
     }
 
     // 5. Group の upsert と link
-    for group in &gallery.groups {
+    for (position, group) in gallery.groups.iter().enumerate() {
         let group_id = upsert_group(&txn, &group.group, &group.url).await?;
-        link_gallery_group(&txn, gallery_id, group_id).await?;
+        link_gallery_group(&txn, gallery_id, group_id, position as i32).await?;
     }
 
     // 6. Character の upsert と link
-    for character in &gallery.characters {
+    for (position, character) in gallery.characters.iter().enumerate() {
         let character_id = upsert_character(&txn, &character.character, &character.url).await?;
-        link_gallery_character(&txn, gallery_id, character_id).await?;
+        link_gallery_character(&txn, gallery_id, character_id, position as i32).await?;
     }
 
     // 7. Parody の upsert と link
-    for parody in &gallery.parodies {
+    for (position, parody) in gallery.parodies.iter().enumerate() {
         let parody_id = upsert_parody(&txn, &parody.parody, &parody.url).await?;
-        link_gallery_parody(&txn, gallery_id, parody_id).await?;
+        link_gallery_parody(&txn, gallery_id, parody_id, position as i32).await?;
     }
 
     txn.commit().await?;
@@ -663,7 +1088,7 @@ async fn upsert_gallery(
         id: existing.as_ref().map(|g| Set(g.id)).unwrap_or(NotSet),
         gallery_id: Set(gallery.gallery_id),
         title: Set(gallery.title.clone()),
-        date: Set(gallery.date.clone()),
+        date: Set(gallery.date),
         type_: Set(gallery.type_.clone()),
         external_id: Set(gallery.id.clone()),
         scene_indexes: Set(gallery.scene_indexes.clone()),
@@ -671,16 +1096,24 @@ async fn upsert_gallery(
         japanese_title: Set(gallery.japanese_title.clone()),
         language_id: Set(language_id),
         translation_group_id: Set(translation_group_id),
+        languages: Set(serde_json::to_value(&gallery.languages).unwrap()),
         video: Set(gallery.video.clone()),
         videofilename: Set(gallery.videofilename.clone()),
         gallery_url: Set(gallery.gallery_url.clone()),
-        date_published: Set(gallery.date_published.clone()),
+        date_published: Set(gallery.date_published),
         blocked: Set(gallery.blocked),
         files: Set(serde_json::to_value(&gallery.files).unwrap()),
     };
 
     let result = if existing.is_some() {
-        gallery_model.update(db).await?
+        let result = gallery_model.update(db).await?;
+        // 増分エクスポートが更新された行を見つけられるように、更新したトランザクションを記録する
+        db.execute_raw(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "UPDATE galleries SET change_xid = pg_current_xact_id() WHERE id = $1",
+            [result.id.into()],
+        )).await?;
+        result
     } else {
         gallery_model.insert(db).await?
     };
@@ -763,6 +1196,7 @@ async fn link_gallery_tag(
     db: &DatabaseTransaction,
     gallery_id: i32,
     tag_id: i32,
+    position: i32,
 ) -> Result<(), DbErr> {
     // 既存チェック
     let existing = entity::gallery_tag::Entity::find()
@@ -771,12 +1205,22 @@ async fn link_gallery_tag(
         .one(db)
         .await?;
 
-    if existing.is_none() {
-        let link = entity::gallery_tag::ActiveModel {
-            gallery_id: Set(gallery_id),
-            tag_id: Set(tag_id),
-        };
-        link.insert(db).await?;
+    match existing {
+        None => {
+            let link = entity::gallery_tag::ActiveModel {
+                gallery_id: Set(gallery_id),
+                tag_id: Set(tag_id),
+                position: Set(position),
+            };
+            link.insert(db).await?;
+        }
+        // 並び順が変わった場合だけ更新する
+        Some(link) if link.position != position => {
+            let mut link: entity::gallery_tag::ActiveModel = link.into();
+            link.position = Set(position);
+            link.update(db).await?;
+        }
+        Some(_) => {}
     }
 
     Ok(())
@@ -812,6 +1256,7 @@ async fn link_gallery_artist(
     db: &DatabaseTransaction,
     gallery_id: i32,
     artist_id: i32,
+    position: i32,
 ) -> Result<(), DbErr> {
     let existing = entity::gallery_artist::Entity::find()
         .filter(entity::gallery_artist::Column::GalleryId.eq(gallery_id))
@@ -819,12 +1264,22 @@ async fn link_gallery_artist(
         .one(db)
         .await?;
 
-    if existing.is_none() {
-        let link = entity::gallery_artist::ActiveModel {
-            gallery_id: Set(gallery_id),
-            artist_id: Set(artist_id),
-        };
-        link.insert(db).await?;
+    match existing {
+        None => {
+            let link = entity::gallery_artist::ActiveModel {
+                gallery_id: Set(gallery_id),
+                artist_id: Set(artist_id),
+                position: Set(position),
+            };
+            link.insert(db).await?;
+        }
+        // 並び順が変わった場合だけ更新する
+        Some(link) if link.position != position => {
+            let mut link: entity::gallery_artist::ActiveModel = link.into();
+            link.position = Set(position);
+            link.update(db).await?;
+        }
+        Some(_) => {}
     }
 
     Ok(())
@@ -860,6 +1315,7 @@ async fn link_gallery_group(
     db: &DatabaseTransaction,
     gallery_id: i32,
     group_id: i32,
+    position: i32,
 ) -> Result<(), DbErr> {
     let existing = entity::gallery_group::Entity::find()
         .filter(entity::gallery_group::Column::GalleryId.eq(gallery_id))
@@ -867,12 +1323,22 @@ async fn link_gallery_group(
         .one(db)
         .await?;
 
-    if existing.is_none() {
-        let link = entity::gallery_group::ActiveModel {
-            gallery_id: Set(gallery_id),
-            group_id: Set(group_id),
-        };
-        link.insert(db).await?;
+    match existing {
+        None => {
+            let link = entity::gallery_group::ActiveModel {
+                gallery_id: Set(gallery_id),
+                group_id: Set(group_id),
+                position: Set(position),
+            };
+            link.insert(db).await?;
+        }
+        // 並び順が変わった場合だけ更新する
+        Some(link) if link.position != position => {
+            let mut link: entity::gallery_group::ActiveModel = link.into();
+            link.position = Set(position);
+            link.update(db).await?;
+        }
+        Some(_) => {}
     }
 
     Ok(())
@@ -908,6 +1374,7 @@ async fn link_gallery_character(
     db: &DatabaseTransaction,
     gallery_id: i32,
     character_id: i32,
+    position: i32,
 ) -> Result<(), DbErr> {
     let existing = entity::gallery_character::Entity::find()
         .filter(entity::gallery_character::Column::GalleryId.eq(gallery_id))
@@ -915,12 +1382,22 @@ async fn link_gallery_character(
         .one(db)
         .await?;
 
-    if existing.is_none() {
-        let link = entity::gallery_character::ActiveModel {
-            gallery_id: Set(gallery_id),
-            character_id: Set(character_id),
-        };
-        link.insert(db).await?;
+    match existing {
+        None => {
+            let link = entity::gallery_character::ActiveModel {
+                gallery_id: Set(gallery_id),
+                character_id: Set(character_id),
+                position: Set(position),
+            };
+            link.insert(db).await?;
+        }
+        // 並び順が変わった場合だけ更新する
+        Some(link) if link.position != position => {
+            let mut link: entity::gallery_character::ActiveModel = link.into();
+            link.position = Set(position);
+            link.update(db).await?;
+        }
+        Some(_) => {}
     }
 
     Ok(())
@@ -956,6 +1433,7 @@ async fn link_gallery_parody(
     db: &DatabaseTransaction,
     gallery_id: i32,
     parody_id: i32,
+    position: i32,
 ) -> Result<(), DbErr> {
     let existing = entity::gallery_parody::Entity::find()
         .filter(entity::gallery_parody::Column::GalleryId.eq(gallery_id))
@@ -963,15 +1441,109 @@ async fn link_gallery_parody(
         .one(db)
         .await?;
 
-    if existing.is_none() {
-        let link = entity::gallery_parody::ActiveModel {
-            gallery_id: Set(gallery_id),
-            parody_id: Set(parody_id),
-        };
-        link.insert(db).await?;
+    match existing {
+        None => {
+            let link = entity::gallery_parody::ActiveModel {
+                gallery_id: Set(gallery_id),
+                parody_id: Set(parody_id),
+                position: Set(position),
+            };
+            link.insert(db).await?;
+        }
+        // 並び順が変わった場合だけ更新する
+        Some(link) if link.position != position => {
+            let mut link: entity::gallery_parody::ActiveModel = link.into();
+            link.position = Set(position);
+            link.update(db).await?;
+        }
+        Some(_) => {}
     }
 
     Ok(())
 }
 
 
+
+// ========================================
+// Read Functions
+// ========================================
+
+/// gallery_id から Gallery を復元する（insert_many_galleries の逆変換）
+/// 該当する Gallery が存在しない場合は None を返す
+pub async fn find_gallery_by_gallery_id(
+    db: &DatabaseConnection,
+    gallery_id: i32,
+) -> Result<Option<domain::gallery::Gallery>, DbErr> {
+    let Some((gallery, language)) = Gallery::find()
+        .filter(entity::gallery::Column::GalleryId.eq(gallery_id))
+        .find_also_related(Language)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    // 関連エンティティは中間テーブル経由で取得（中間テーブルの position 順 = 元データでの並び順）
+    let tags = gallery.find_related(Tag)
+        .order_by_asc(entity::gallery_tag::Column::Position)
+        .all(db)
+        .await?;
+    let artists = gallery.find_related(Artist)
+        .order_by_asc(entity::gallery_artist::Column::Position)
+        .all(db)
+        .await?;
+    let groups = gallery.find_related(Group)
+        .order_by_asc(entity::gallery_group::Column::Position)
+        .all(db)
+        .await?;
+    let characters = gallery.find_related(Character)
+        .order_by_asc(entity::gallery_character::Column::Position)
+        .all(db)
+        .await?;
+    let parodies = gallery.find_related(Parody)
+        .order_by_asc(entity::gallery_parody::Column::Position)
+        .all(db)
+        .await?;
+
+    // languages は取り込み時のエントリをそのまま保存しているので、そのまま返す
+    let languages: Vec<domain::gallery::Language> = serde_json::from_value(gallery.languages)
+        .map_err(|e| DbErr::Json(format!("Failed to deserialize languages of gallery {}: {}", gallery.gallery_id, e)))?;
+    let files: Vec<domain::gallery::File> = serde_json::from_value(gallery.files)
+        .map_err(|e| DbErr::Json(format!("Failed to deserialize files of gallery {}: {}", gallery.gallery_id, e)))?;
+
+    Ok(Some(domain::gallery::Gallery {
+        gallery_id: gallery.gallery_id,
+        title: gallery.title,
+        date: gallery.date,
+        files,
+        languages,
+        scene_indexes: gallery.scene_indexes,
+        type_: gallery.type_,
+        id: gallery.external_id,
+        related: gallery.related_ids,
+        japanese_title: gallery.japanese_title,
+        language: language.as_ref().map(|l| l.name.clone()),
+        language_localname: language.as_ref().and_then(|l| l.local_name.clone()),
+        language_url: language.as_ref().and_then(|l| l.url.clone()),
+        video: gallery.video,
+        videofilename: gallery.videofilename,
+        artists: artists.into_iter()
+            .map(|a| domain::gallery::Artist { artist: a.artist, url: a.url })
+            .collect(),
+        groups: groups.into_iter()
+            .map(|g| domain::gallery::Group { group: g.group, url: g.url })
+            .collect(),
+        characters: characters.into_iter()
+            .map(|c| domain::gallery::Character { character: c.character, url: c.url })
+            .collect(),
+        parodies: parodies.into_iter()
+            .map(|p| domain::gallery::Parody { parody: p.parody, url: p.url })
+            .collect(),
+        tags: tags.into_iter()
+            .map(|t| domain::gallery::Tag { tag: t.name, url: t.url, male: t.male, female: t.female })
+            .collect(),
+        gallery_url: gallery.gallery_url,
+        date_published: gallery.date_published,
+        blocked: gallery.blocked,
+    }))
+}
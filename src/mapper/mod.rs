@@ -1 +1,6 @@
 pub mod galleries_mapper;
+pub mod fbs_galleries_mapper;
+pub mod pg_copy;
+
+pub mod suggest_mapper;
+pub mod facets_mapper;
\ No newline at end of file
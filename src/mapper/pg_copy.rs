@@ -0,0 +1,184 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use futures::future::{BoxFuture, FutureExt};
+use sea_orm::sqlx::{self, PgConnection};
+use sea_orm::{DbErr, RuntimeErr};
+use std::sync::Arc;
+
+/// バイナリ形式の COPY のヘッダー (シグネチャ + フラグ + ヘッダー拡張の長さ)
+const HEADER: &[u8] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+/// 配列の要素の型 (pg_type の OID)
+const INT4_OID: i32 = 23;
+const TEXT_OID: i32 = 25;
+/// jsonb のバイナリ形式のバージョン
+const JSONB_VERSION: u8 = 1;
+
+/// PostgreSQL の epoch (2000-01-01) の Unix 時刻 (秒)
+const PG_EPOCH_SECONDS: i64 = 946_684_800;
+
+/// `COPY ... FROM STDIN (FORMAT binary)` に送るデータを組み立てる
+///
+/// 各行は `row(列数)` で始め、テーブルの列の順に値を書く。値の型は COPY 先の列の型と一致させる。
+pub struct BinaryCopy {
+    buf: Vec<u8>,
+    rows: usize,
+}
+
+impl Default for BinaryCopy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BinaryCopy {
+    pub fn new() -> Self {
+        Self { buf: HEADER.to_vec(), rows: 0 }
+    }
+
+    /// 書いた行数
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn row(&mut self, fields: i16) -> &mut Self {
+        self.rows += 1;
+        self.buf.extend_from_slice(&fields.to_be_bytes());
+        self
+    }
+
+    pub fn null(&mut self) -> &mut Self {
+        self.buf.extend_from_slice(&(-1i32).to_be_bytes());
+        self
+    }
+
+    fn field(&mut self, data: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(&(data.len() as i32).to_be_bytes());
+        self.buf.extend_from_slice(data);
+        self
+    }
+
+    pub fn int4(&mut self, value: i32) -> &mut Self {
+        self.field(&value.to_be_bytes())
+    }
+
+    pub fn opt_int4(&mut self, value: Option<i32>) -> &mut Self {
+        match value {
+            Some(value) => self.int4(value),
+            None => self.null(),
+        }
+    }
+
+    pub fn bool(&mut self, value: bool) -> &mut Self {
+        self.field(&[value as u8])
+    }
+
+    pub fn text(&mut self, value: &str) -> &mut Self {
+        self.field(value.as_bytes())
+    }
+
+    pub fn opt_text(&mut self, value: Option<&str>) -> &mut Self {
+        match value {
+            Some(value) => self.text(value),
+            None => self.null(),
+        }
+    }
+
+    pub fn bytea(&mut self, value: &[u8]) -> &mut Self {
+        self.field(value)
+    }
+
+    /// 2000-01-01 00:00:00 UTC からのマイクロ秒
+    pub fn timestamptz(&mut self, value: &DateTime<FixedOffset>) -> &mut Self {
+        self.field(&(value.timestamp_micros() - PG_EPOCH_SECONDS * 1_000_000).to_be_bytes())
+    }
+
+    /// 2000-01-01 からの日数
+    pub fn opt_date(&mut self, value: Option<NaiveDate>) -> &mut Self {
+        let Some(value) = value else {
+            return self.null();
+        };
+        let epoch = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        self.field(&((value - epoch).num_days() as i32).to_be_bytes())
+    }
+
+    pub fn jsonb(&mut self, value: &serde_json::Value) -> &mut Self {
+        let mut data = vec![JSONB_VERSION];
+        serde_json::to_writer(&mut data, value).expect("serializing a JSON value cannot fail");
+        self.field(&data)
+    }
+
+    pub fn int4_array(&mut self, values: &[i32]) -> &mut Self {
+        let data = encode_array(INT4_OID, values.iter().map(|v| v.to_be_bytes().to_vec()));
+        self.field(&data)
+    }
+
+    pub fn text_array(&mut self, values: &[String]) -> &mut Self {
+        let data = encode_array(TEXT_OID, values.iter().map(|v| v.as_bytes().to_vec()));
+        self.field(&data)
+    }
+
+    /// トレーラーを付けて、送るバイト列にする
+    pub fn finish(mut self) -> Vec<u8> {
+        self.buf.extend_from_slice(&(-1i16).to_be_bytes());
+        self.buf
+    }
+}
+
+/// 1次元の配列 (NULL を含まない) のバイナリ表現
+fn encode_array(element_oid: i32, elements: impl ExactSizeIterator<Item = Vec<u8>>) -> Vec<u8> {
+    let len = elements.len() as i32;
+    let mut data = Vec::new();
+    // 空配列は次元数 0 で表す
+    let ndim: i32 = if len == 0 { 0 } else { 1 };
+    data.extend_from_slice(&ndim.to_be_bytes());
+    data.extend_from_slice(&0i32.to_be_bytes()); // NULL を含むか
+    data.extend_from_slice(&element_oid.to_be_bytes());
+    if len > 0 {
+        data.extend_from_slice(&len.to_be_bytes());
+        data.extend_from_slice(&1i32.to_be_bytes()); // 下限
+    }
+    for element in elements {
+        data.extend_from_slice(&(element.len() as i32).to_be_bytes());
+        data.extend_from_slice(&element);
+    }
+    data
+}
+
+/// `statement` (`COPY <table> (...) FROM STDIN (FORMAT binary)`) で `data` を送り、コピーした行数を返す
+pub async fn copy_in(conn: &mut PgConnection, statement: &str, data: BinaryCopy) -> Result<u64, DbErr> {
+    let mut copy = conn.copy_in_raw(statement).await.map_err(sqlx_err)?;
+    copy.send(data.finish()).await.map_err(sqlx_err)?;
+    copy.finish().await.map_err(sqlx_err)
+}
+
+/// `sql` を実行する
+///
+/// sqlx の Executor を直接 await すると、tokio::spawn するタスクの中で Send を証明できなくなるので Box にする。
+pub fn execute<'c>(conn: &'c mut PgConnection, sql: &'c str) -> BoxFuture<'c, Result<(), DbErr>> {
+    async move {
+        // 一時テーブルは毎回作り直すので、プリペアドステートメントをキャッシュしない
+        sqlx::query(sql).persistent(false).execute(conn).await.map_err(sqlx_err)?;
+        Ok(())
+    }.boxed()
+}
+
+/// `sql` ($1 に `ids` を渡す) の結果の1列目を i32 の一覧で返す
+pub fn fetch_ids<'c>(conn: &'c mut PgConnection, sql: &'c str, ids: Vec<i32>) -> BoxFuture<'c, Result<Vec<i32>, DbErr>> {
+    async move {
+        sqlx::query_scalar::<_, i32>(sql).bind(ids).fetch_all(conn).await.map_err(sqlx_err)
+    }.boxed()
+}
+
+/// `sql` の結果の各行を `T` (列の順に並べたタプルなど) で返す
+pub fn fetch_all<'c, T>(conn: &'c mut PgConnection, sql: &'c str) -> BoxFuture<'c, Result<Vec<T>, DbErr>>
+where
+    T: for<'r> sqlx::FromRow<'r, sqlx::postgres::PgRow> + Send + Unpin + 'c,
+{
+    async move {
+        sqlx::query_as::<_, T>(sql).persistent(false).fetch_all(conn).await.map_err(sqlx_err)
+    }.boxed()
+}
+
+/// sqlx のエラーを sea-orm のエラーにする
+pub fn sqlx_err(e: sqlx::Error) -> DbErr {
+    DbErr::Exec(RuntimeErr::SqlxError(Arc::new(e)))
+}
@@ -0,0 +1,70 @@
+use sea_orm::*;
+use crate::domain::dto::Suggestion;
+use crate::search::compiler::escape_like;
+use crate::search::parser::{format_term, Namespace};
+
+/// pg_trgm の GIN インデックスで部分一致を探せる最短の文字数 (これより短い入力は前方一致で探す)
+pub const MIN_TRIGRAM_LENGTH: usize = 3;
+
+/// 値を suggest_entries から探し、紐づくギャラリー数の多い順に返す
+/// (同数の場合は前方一致を優先する)
+///
+/// ギャラリー数は取り込みのたびに更新する集計済みの値を使う。
+/// 入力が `MIN_TRIGRAM_LENGTH` 文字未満の場合は前方一致 (btree)、それ以上は部分一致 (pg_trgm) で探す。
+pub async fn find_suggestions(
+    db: &DatabaseConnection,
+    namespace: Namespace,
+    value: &str,
+    limit: u32,
+) -> Result<Vec<Suggestion>, DbErr> {
+    // type は集計していない
+    if namespace == Namespace::Type {
+        return Ok(vec![]);
+    }
+    let Some(ns) = namespace.name() else {
+        return Ok(vec![]);
+    };
+
+    let escaped = escape_like(value);
+    let prefix = format!("{}%", escaped.to_lowercase());
+    let (condition, pattern) = if value.chars().count() < MIN_TRIGRAM_LENGTH {
+        ("lower(s.name) LIKE $2", prefix.clone())
+    } else {
+        ("s.name ILIKE $2", format!("%{}%", escaped))
+    };
+    let stmt = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        format!(
+            "SELECT s.name AS value, s.gallery_count AS count \
+             FROM suggest_entries s \
+             WHERE s.ns = $1 AND {condition} \
+             ORDER BY s.gallery_count DESC, lower(s.name) LIKE $3 DESC, s.name \
+             LIMIT $4"
+        ),
+        vec![ns.into(), pattern.into(), prefix.into(), (limit as i64).into()],
+    );
+
+    db.query_all_raw(stmt)
+        .await?
+        .into_iter()
+        .map(|row| {
+            let value: String = row.try_get("", "value")?;
+            Ok(Suggestion {
+                ns,
+                term: format_term(ns, &value),
+                value,
+                count: row.try_get("", "count")?,
+            })
+        })
+        .collect()
+}
+
+/// サジェストのギャラリー数を集計し直す (集計中も古い値で検索できる)
+///
+/// ギャラリー数は結果整合で、import / ingest / replay_dead_letters の最後 (失敗した場合も含む) と
+/// `hitomi refresh-suggest` でしか更新されない。それ以外の経路で関連テーブルを書き換えた場合は
+/// `hitomi refresh-suggest` を実行するまで古い値を返す。
+pub async fn refresh_suggest_entries<C: ConnectionTrait>(db: &C) -> Result<(), DbErr> {
+    db.execute_unprepared("REFRESH MATERIALIZED VIEW CONCURRENTLY suggest_entries").await?;
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use futures::StreamExt;
+use sea_orm::{AccessMode, DatabaseConnection, DatabaseTransaction, DbBackend, DbErr, IsolationLevel, Statement, StreamTrait, TransactionTrait, Value};
+use serde::{Deserialize, Serialize};
+use crate::mapper::galleries_mapper::{self, CHANGED_SINCE};
+use crate::nozomi;
+use crate::search::parser::{Namespace, Term};
+
+/// 全ギャラリーのインデックス (前回のエクスポートとの差分で削除を検出するのにも使う)
+pub const INDEX_ALL: &str = "index-all.nozomi";
+
+/// 前回のエクスポートの状態 (出力ディレクトリに置く)
+pub const STATE_FILE: &str = ".export_state.json";
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+pub struct ExportState {
+    /// 前回読み込んだスナップショットの xmin (galleries.change_xid がこれ以上の行を次回読み直す)
+    /// (この値の無い古い状態ファイルは 0 になり、すべての行を読み直す)
+    #[serde(default)]
+    pub change_xmin: i64,
+}
+
+#[derive(Serialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExportStats {
+    pub galleries: usize,
+    /// 前回以降に追加・更新されたギャラリー数 (全件の場合は 0)
+    pub changed: usize,
+    pub written: usize,
+    pub removed: usize,
+    pub change_xmin: i64,
+}
+
+/// 検索語 → (date, gallery_id) の一覧
+type Postings = HashMap<Term, Vec<(i64, i32)>>;
+
+/// 名前空間ごとの (ジャンクションテーブル, エンティティのテーブル, 外部キー, 名前の列)
+const JUNCTIONS: [(Namespace, &str, &str, &str, &str); 4] = [
+    (Namespace::Artist, "gallery_artists", "artists", "artist_id", "artist"),
+    (Namespace::Group, "gallery_groups", "groups", "group_id", "\"group\""),
+    (Namespace::Character, "gallery_characters", "characters", "character_id", "character"),
+    (Namespace::Parody, "gallery_parodies", "parodies", "parody_id", "parody"),
+];
+
+/// hitomi と同じディレクトリ構成での相対パス
+///
+/// - tag: `tag/female:collar-all.nozomi` (名前空間の無いタグは `tag/collar-all.nozomi`)
+/// - artist / group / character / series / type: `artist/alp-all.nozomi`
+/// - language: `index-japanese.nozomi`
+pub fn index_path(term: &Term) -> Option<String> {
+    // パス区切りになる文字だけは URL と同じようにエスケープする
+    let value = term.value.replace('%', "%25").replace('/', "%2F");
+    let path = match term.namespace {
+        Namespace::Tag => format!("tag/{value}-all"),
+        Namespace::Male | Namespace::Female => format!("tag/{}:{value}-all", term.namespace.name()?),
+        Namespace::Language => format!("index-{value}"),
+        Namespace::Title => return None,
+        namespace => format!("{}/{value}-all", namespace.name()?),
+    };
+    Some(format!("{path}.{}", nozomi::EXTENSION))
+}
+
+/// 出力ディレクトリに `.nozomi` ファイルを書き出す
+///
+/// `full` でなく前回の状態がある場合は、前回以降に追加・更新された galleries (galleries.change_xid で判定) の
+/// 検索語のインデックスだけを書き直す。書き直さなかったファイルからは、削除された gallery_id と
+/// 更新された gallery_id (今は別の検索語に移った、または関連が削除された) を取り除く。
+pub async fn export(db: &DatabaseConnection, out_dir: &Path, full: bool) -> io::Result<ExportStats> {
+    let previous = if full { None } else { read_state(out_dir)? };
+    let since = previous.map(|state| state.change_xmin);
+    let txn = db.begin_with_config(Some(IsolationLevel::RepeatableRead), Some(AccessMode::ReadOnly))
+        .await
+        .map_err(db_error)?;
+    let change_xmin = galleries_mapper::snapshot_xmin(&txn).await.map_err(db_error)?;
+
+    // 全ギャラリーを新しい順に
+    let mut all = Vec::new();
+    {
+        let stmt = Statement::from_string(
+            DbBackend::Postgres,
+            "SELECT gallery_id, (EXTRACT(EPOCH FROM date) * 1000)::BIGINT AS ts FROM galleries",
+        );
+        let mut rows = txn.stream_raw(stmt).await.map_err(db_error)?;
+        while let Some(row) = rows.next().await {
+            let row = row.map_err(db_error)?;
+            all.push((row.try_get::<i64>("", "ts").map_err(db_error)?, row.try_get::<i32>("", "gallery_id").map_err(db_error)?));
+        }
+    }
+    let all = sorted_ids(all);
+
+    let changed = match since {
+        Some(since) => load_changed(&txn, since).await.map_err(db_error)?,
+        None => HashSet::new(),
+    };
+    let postings = match since {
+        Some(_) if changed.is_empty() => Postings::new(),
+        _ => load_postings(&txn, since).await.map_err(db_error)?,
+    };
+    txn.rollback().await.ok();
+
+    let mut stats = ExportStats { galleries: all.len(), changed: changed.len(), change_xmin, ..Default::default() };
+    let mut written = HashSet::new();
+    for (term, ids) in postings {
+        let Some(path) = index_path(&term) else { continue };
+        write_index(out_dir, &path, &sorted_ids(ids))?;
+        written.insert(out_dir.join(path));
+        stats.written += 1;
+    }
+
+    let existing = existing_files(out_dir)?;
+    match previous {
+        // 全件の場合は、今回書かなかったファイル (ギャラリーが無くなった検索語) を消す
+        None => {
+            for path in existing.iter().filter(|path| !written.contains(*path) && !path.ends_with(INDEX_ALL)) {
+                std::fs::remove_file(path)?;
+                stats.removed += 1;
+            }
+        }
+        // 増分の場合は、消えた gallery_id と更新された gallery_id を書き直していないファイルから取り除く
+        // (更新されたギャラリーの今の検索語のファイルはすべて書き直したので、残りのファイルにあるのは古い関連)
+        Some(_) => {
+            let current: HashSet<i32> = all.iter().copied().collect();
+            let mut stale: HashSet<i32> = match nozomi::read_file(&out_dir.join(INDEX_ALL)) {
+                Ok(ids) => ids.into_iter().filter(|id| !current.contains(id)).collect(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => HashSet::new(),
+                Err(err) => return Err(err),
+            };
+            stale.extend(&changed);
+            if !stale.is_empty() {
+                for path in existing.iter().filter(|path| !written.contains(*path) && !path.ends_with(INDEX_ALL)) {
+                    let ids = nozomi::read_file(path)?;
+                    let kept: Vec<i32> = ids.iter().copied().filter(|id| !stale.contains(id)).collect();
+                    if kept.is_empty() {
+                        std::fs::remove_file(path)?;
+                        stats.removed += 1;
+                    } else if kept.len() != ids.len() {
+                        write_atomic(path, &nozomi::encode(&kept))?;
+                        stats.written += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    write_index(out_dir, INDEX_ALL, &all)?;
+    stats.written += 1;
+    write_atomic(&out_dir.join(STATE_FILE), &serde_json::to_vec(&ExportState { change_xmin })?)?;
+    Ok(stats)
+}
+
+/// 前回以降に追加・更新された gallery_id
+async fn load_changed(txn: &DatabaseTransaction, since: i64) -> Result<HashSet<i32>, DbErr> {
+    let stmt = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        format!("SELECT gallery_id FROM galleries WHERE {CHANGED_SINCE}"),
+        [since.into()],
+    );
+    let mut changed = HashSet::new();
+    let mut rows = txn.stream_raw(stmt).await?;
+    while let Some(row) = rows.next().await {
+        changed.insert(row?.try_get::<i32>("", "gallery_id")?);
+    }
+    Ok(changed)
+}
+
+/// 検索語ごとの gallery_id を読み込む
+///
+/// `since` を指定した場合は、それ以降に追加・更新された行が持つ検索語だけを
+/// (他の行も含めて) 読み込む。
+async fn load_postings(txn: &DatabaseTransaction, since: Option<i64>) -> Result<Postings, DbErr> {
+    let values: Vec<Value> = since.into_iter().map(Into::into).collect();
+    let touched = |column: &str, table: &str, key: &str| match since {
+        Some(_) => format!("{column} IN (SELECT {key} FROM {table} WHERE gallery_id IN (SELECT id FROM galleries WHERE {CHANGED_SINCE}))"),
+        None => "TRUE".to_string(),
+    };
+    let mut postings = Postings::new();
+
+    let galleries_touched = |column: &str| match since {
+        Some(_) => format!("g.{column} IN (SELECT {column} FROM galleries WHERE {CHANGED_SINCE})"),
+        None => "TRUE".to_string(),
+    };
+    let mut statements = vec![
+        (
+            Some(Namespace::Type),
+            format!("SELECT g.gallery_id, g.type AS name, FALSE AS male, FALSE AS female, \
+                     (EXTRACT(EPOCH FROM g.date) * 1000)::BIGINT AS ts FROM galleries g WHERE {}", galleries_touched("type")),
+        ),
+        (
+            Some(Namespace::Language),
+            format!("SELECT g.gallery_id, e.name, FALSE AS male, FALSE AS female, \
+                     (EXTRACT(EPOCH FROM g.date) * 1000)::BIGINT AS ts \
+                     FROM galleries g JOIN languages e ON e.id = g.language_id WHERE {}", galleries_touched("language_id")),
+        ),
+        (
+            // 名前空間は male / female の列で決まる
+            None,
+            format!("SELECT g.gallery_id, e.name, e.male, e.female, (EXTRACT(EPOCH FROM g.date) * 1000)::BIGINT AS ts \
+                     FROM gallery_tags j JOIN galleries g ON g.id = j.gallery_id JOIN tags e ON e.id = j.tag_id WHERE {}",
+                    touched("j.tag_id", "gallery_tags", "tag_id")),
+        ),
+    ];
+    for (namespace, junction, table, foreign_key, name_column) in JUNCTIONS {
+        statements.push((
+            Some(namespace),
+            format!("SELECT g.gallery_id, e.{name_column} AS name, FALSE AS male, FALSE AS female, \
+                     (EXTRACT(EPOCH FROM g.date) * 1000)::BIGINT AS ts \
+                     FROM {junction} j JOIN galleries g ON g.id = j.gallery_id JOIN {table} e ON e.id = j.{foreign_key} WHERE {}",
+                    touched(&format!("j.{foreign_key}"), junction, foreign_key)),
+        ));
+    }
+
+    for (namespace, sql) in statements {
+        let mut rows = txn.stream_raw(Statement::from_sql_and_values(DbBackend::Postgres, sql, values.clone())).await?;
+        while let Some(row) = rows.next().await {
+            let row = row?;
+            // 名前空間は /search と同じく from_tag_flags で決め、male と female の両方が立つタグは書き出さない
+            let Some(namespace) = namespace.or(Namespace::from_tag_flags(row.try_get("", "male")?, row.try_get("", "female")?)) else {
+                continue;
+            };
+            let term = Term { namespace, value: row.try_get::<String>("", "name")?.to_lowercase() };
+            postings.entry(term).or_default().push((row.try_get("", "ts")?, row.try_get("", "gallery_id")?));
+        }
+    }
+    Ok(postings)
+}
+
+/// hitomi と同じく新しい順 (同じ日時は gallery_id の降順) に並べる
+fn sorted_ids(mut ids: Vec<(i64, i32)>) -> Vec<i32> {
+    ids.sort_unstable_by(|a, b| b.cmp(a));
+    ids.dedup();
+    ids.into_iter().map(|(_, gallery_id)| gallery_id).collect()
+}
+
+fn read_state(out_dir: &Path) -> io::Result<Option<ExportState>> {
+    match std::fs::read(out_dir.join(STATE_FILE)) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn write_index(out_dir: &Path, path: &str, ids: &[i32]) -> io::Result<()> {
+    let path = out_dir.join(path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    write_atomic(&path, &nozomi::encode(ids))
+}
+
+/// 配信中のファイルが途中まで書かれた状態で見えないように、一時ファイルから rename する
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)
+}
+
+fn existing_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().and_then(|s| s.to_str()) == Some(nozomi::EXTENSION) {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn db_error(err: DbErr) -> io::Error {
+    io::Error::other(err)
+}
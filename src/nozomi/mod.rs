@@ -0,0 +1,50 @@
+pub mod export;
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// hitomi の `.nozomi` ファイルの拡張子
+pub const EXTENSION: &str = "nozomi";
+
+/// ビッグエンディアンの i32 列をパースする
+pub fn parse(bytes: &[u8]) -> io::Result<Vec<i32>> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("nozomi length {} is not a multiple of 4", bytes.len()),
+        ));
+    }
+    Ok(bytes.chunks_exact(4)
+        .map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+/// gallery_id をビッグエンディアンの i32 列としてエンコードする
+pub fn encode(ids: &[i32]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(ids.len() * 4);
+    for gallery_id in ids {
+        buffer.extend_from_slice(&gallery_id.to_be_bytes());
+    }
+    buffer
+}
+
+pub fn read_from<R: Read>(mut reader: R) -> io::Result<Vec<i32>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    parse(&bytes)
+}
+
+pub fn write_to<W: Write>(mut writer: W, ids: &[i32]) -> io::Result<()> {
+    writer.write_all(&encode(ids))
+}
+
+pub fn read_file(path: &Path) -> io::Result<Vec<i32>> {
+    read_from(std::fs::File::open(path)?)
+}
+
+/// ルートからの相対パスを拡張子を除いて `/` 区切りにしたもの (例: `tag/female:collar-all`)
+pub fn index_name(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?.with_extension("");
+    let parts: Option<Vec<&str>> = relative.components().map(|c| c.as_os_str().to_str()).collect();
+    Some(parts?.join("/"))
+}
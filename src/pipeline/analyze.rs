@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use genson_rs::SchemaBuilder;
+use rayon::prelude::*;
+use std::fs::{File, read_to_string};
+use std::io::{BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use crate::pipeline;
+use crate::pipeline::compression;
+
+/// raw JSON のスキーマをファイルごとに `<schema_dir>/<stem>.json` に書き、`merged_schema_path` にまとめる
+///
+/// 入力は圧縮されていてもよい (zstd / gzip)。
+/// `force` が false の場合、出力済みのファイルは読み飛ばす。
+pub fn run(inputs: &[PathBuf], schema_dir: &Path, merged_schema_path: &Path, force: bool) -> Result<()> {
+    if !schema_dir.exists() {
+        std::fs::create_dir_all(schema_dir)?;
+    }
+
+    inputs.par_iter().for_each(|jsonl| {
+        let output_schema = pipeline::output_path(jsonl, schema_dir, "json");
+
+        if let Err(e) = jsonl_to_schema(jsonl, &output_schema, force) {
+            eprintln!("Error processing {:?}: {:?}", jsonl, e);
+        } else {
+            println!("Processed {:?}", jsonl);
+        }
+    });
+    merge_schemas(schema_dir, merged_schema_path, force)?;
+    println!("Schema analysis completed.");
+    Ok(())
+}
+
+
+fn jsonl_to_schema(jsonl_path: &Path, schema_path: &Path, force: bool) -> Result<()> {
+    //! jsonlに含まれる各jsonのスキーマを分析
+
+    if schema_path.exists() && !force {
+        println!("Output file {:?} already exists. Skipping.", schema_path);
+        return Ok(());
+    }
+    let (_, mut jsonl_reader) = compression::open_input(jsonl_path, None)
+        .with_context(|| format!("Failed to open input file: {:?}", jsonl_path))?;
+
+    // 一時ファイルに書き込み
+    let output_tmp_file = schema_path.with_extension("jsonl.tmp");
+
+    let mut schema_writer = BufWriter::new(File::create(&output_tmp_file)?);
+    let mut schema_builder = SchemaBuilder::new(Some("AUTO"));
+    let mut buf = Vec::with_capacity(10*1024*1024);
+
+    // JSONLファイルを1行ずつ読み込み、スキーマに追加
+    loop {
+        buf.clear(); // 次の行を読み込む前に必ずクリアする
+        let size = jsonl_reader.read_until(b'\n', &mut buf)?;
+        if size == 0 { break; } // 終端に達したら終了
+
+        {
+            match simd_json::to_borrowed_value(&mut buf) {
+                Ok(value) => {
+                    schema_builder.add_object(&value);
+                },
+                Err(e) => {
+                    eprintln!("Failed to parse JSON line in {:?}: {:?}", jsonl_path, e);
+                }
+            }
+        }
+    }
+    let schema_json_str = schema_builder.to_json();
+    schema_writer.write_all(schema_json_str.as_bytes())?;
+    schema_writer.flush()?;
+
+    std::fs::rename(output_tmp_file, schema_path)
+        .with_context(|| format!("Failed to rename temp file to output file: {:?}", schema_path))?;
+
+    Ok(())
+}
+
+fn merge_schemas(schema_dir: &Path, merged_schema_path: &Path, force: bool) -> Result<()> {
+    //! 複数のスキーマファイルをマージして1つのスキーマファイルにまとめる
+
+    if merged_schema_path.exists() && !force {
+        println!("Merged schema file {:?} already exists. Skipping.", merged_schema_path);
+        return Ok(());
+    }
+
+    let mut merged_schema_builder = SchemaBuilder::new(Some("AUTO"));
+
+    let entries: Vec<_> = std::fs::read_dir(schema_dir)?
+        .filter_map(|e| e.ok())
+        .collect();
+
+    for entry in entries {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) == Some("json") {
+
+            let json_str = read_to_string(&path)
+                .with_context(|| format!("Failed to read schema file: {:?}", path))?;
+            let schema = serde_json::from_str::<serde_json::Value>(&json_str)
+                .with_context(|| format!("Failed to parse schema JSON in file: {:?}", path))?;
+            merged_schema_builder.add_schema(schema);
+
+        }
+    }
+
+    let merged_schema_json_str = merged_schema_builder.to_json();
+    let mut merged_schema_writer = BufWriter::new(File::create(merged_schema_path)?);
+    merged_schema_writer.write_all(merged_schema_json_str.as_bytes())?;
+    merged_schema_writer.flush()?;
+
+    Ok(())
+}
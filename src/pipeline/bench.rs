@@ -0,0 +1,72 @@
+use anyhow::Result;
+use sea_orm::DatabaseConnection;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::import::{ImportMode, Loader};
+use crate::import::dead_letter::{DeadLetterWriter, Stage};
+use crate::pipeline::{import, import_fbs, ImportOptions, ImportStats};
+
+const LOADERS: [Loader; 2] = [Loader::Insert, Loader::Copy];
+
+/// 取り込み先と、その取り込みにかかった時間
+pub struct BenchResult {
+    pub target: &'static str,
+    pub stats: ImportStats,
+}
+
+/// 同じ入力を insert と COPY で順に取り込み、かかった時間を比べる
+///
+/// どちらの loader も取り込みごとに新しい `ImportSession` で ID を覚えながら書き込むので、差は書き込み方だけになる。
+/// 計測のたびにテーブルを作り直す (rebuild) ので、計測用の DB を指定すること。
+/// `fbs_db` を渡した場合は fbs_galleries への取り込みも計測する。
+pub async fn run(
+    db: &DatabaseConnection,
+    fbs_db: Option<&DatabaseConnection>,
+    inputs: &[PathBuf],
+    dead_letter_dir: &Path,
+) -> Result<Vec<BenchResult>> {
+    let mut results = Vec::new();
+
+    for loader in LOADERS {
+        println!("== relational ({}) ==", loader.name());
+        let options = ImportOptions { mode: ImportMode::Rebuild, restart: true, loader };
+        let dead_letters = DeadLetterWriter::in_dir(dead_letter_dir, Stage::ImportToDb);
+        let stats = import::run(db, inputs, options, false, &dead_letters).await?;
+        results.push(BenchResult { target: "relational", stats });
+    }
+
+    if let Some(fbs_db) = fbs_db {
+        for loader in LOADERS {
+            println!("== fbs ({}) ==", loader.name());
+            let options = ImportOptions { mode: ImportMode::Rebuild, restart: true, loader };
+            let dead_letters = Arc::new(DeadLetterWriter::in_dir(dead_letter_dir, Stage::ImportBinToDb));
+            let stats = import_fbs::run(fbs_db, inputs, options, dead_letters).await?;
+            results.push(BenchResult { target: "fbs", stats });
+        }
+    }
+
+    print_report(&results);
+    Ok(results)
+}
+
+/// 取り込み先ごとに、insert に対する速度の比を付けて表にする
+fn print_report(results: &[BenchResult]) {
+    println!();
+    println!("{:<12} {:<8} {:>10} {:>10} {:>12} {:>8}", "target", "loader", "rows", "seconds", "rows/s", "speedup");
+    for result in results {
+        let baseline = results.iter()
+            .find(|r| r.target == result.target && r.stats.loader == Loader::Insert)
+            .map(|r| r.stats.elapsed.as_secs_f64())
+            .unwrap_or_default();
+        let speedup = baseline / result.stats.elapsed.as_secs_f64().max(f64::EPSILON);
+        println!(
+            "{:<12} {:<8} {:>10} {:>10.2} {:>12.0} {:>7.2}x",
+            result.target,
+            result.stats.loader.name(),
+            result.stats.rows,
+            result.stats.elapsed.as_secs_f64(),
+            result.stats.rows_per_sec(),
+            speedup,
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use indicatif::ProgressBar;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::import::checkpoint::{Checkpoint, TrackedLines};
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// zstd の圧縮レベル (FlatBuffers の圧縮と同じ)
+const ZSTD_LEVEL: i32 = 3;
+
+/// パイプラインのファイルの圧縮形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Zstd,
+    Gzip,
+}
+
+impl Compression {
+    /// 先頭のバイト列 (マジックナンバー) から判定する
+    pub fn detect(head: &[u8]) -> Self {
+        if head.starts_with(&ZSTD_MAGIC) {
+            Self::Zstd
+        } else if head.starts_with(&GZIP_MAGIC) {
+            Self::Gzip
+        } else {
+            Self::None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Zstd => "zstd",
+            Self::Gzip => "gzip",
+        }
+    }
+
+    /// ファイル名に付ける拡張子 (`.zst` / `.gz`)
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Self::None => "",
+            Self::Zstd => ".zst",
+            Self::Gzip => ".gz",
+        }
+    }
+
+    /// 出力ファイルのパスに拡張子を付ける
+    pub fn output_path(&self, path: &Path) -> PathBuf {
+        let mut path = path.as_os_str().to_owned();
+        path.push(self.suffix());
+        PathBuf::from(path)
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "none" => Ok(Self::None),
+            "zstd" => Ok(Self::Zstd),
+            "gzip" => Ok(Self::Gzip),
+            _ => Err(format!("Unknown compression '{}' (expected none, zstd or gzip)", value)),
+        }
+    }
+}
+
+/// 圧縮の拡張子 (`.zst` / `.gz`) を取り除いたファイル名
+pub fn strip_compression_suffix(file_name: &str) -> &str {
+    [Compression::Zstd, Compression::Gzip].iter()
+        .find_map(|compression| file_name.strip_suffix(compression.suffix()))
+        .unwrap_or(file_name)
+}
+
+/// 圧縮の有無を判定して、展開したストリームを返す
+///
+/// `pb` を渡した場合は、展開前の (ファイルから読んだ) バイト数を進める。
+pub fn open_input(path: &Path, pb: Option<&ProgressBar>) -> io::Result<(Compression, Box<dyn BufRead + Send>)> {
+    let file = File::open(path)?;
+    let file: Box<dyn Read + Send> = match pb {
+        Some(pb) => Box::new(pb.wrap_read(file)),
+        None => Box::new(file),
+    };
+    wrap_reader(BufReader::new(file))
+}
+
+fn wrap_reader<R: BufRead + Send + 'static>(mut reader: R) -> io::Result<(Compression, Box<dyn BufRead + Send>)> {
+    let compression = Compression::detect(reader.fill_buf()?);
+    let reader: Box<dyn BufRead + Send> = match compression {
+        Compression::None => Box::new(reader),
+        Compression::Zstd => Box::new(BufReader::new(zstd::stream::read::Decoder::with_buffer(reader)?)),
+        Compression::Gzip => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))),
+    };
+    Ok((compression, reader))
+}
+
+/// チェックポイントの位置から行を読む
+///
+/// 非圧縮のファイルはその位置まで seek する。圧縮されたファイルは seek できないので、
+/// 先頭から展開してチェックポイントの行まで読み飛ばす (プログレスバーは読んだ分だけ進む)。
+pub fn open_lines(path: &Path, start: Checkpoint, pb: &ProgressBar) -> io::Result<TrackedLines<Box<dyn BufRead + Send>>> {
+    let mut file = File::open(path)?;
+    let mut head = Vec::with_capacity(ZSTD_MAGIC.len());
+    (&mut file).take(ZSTD_MAGIC.len() as u64).read_to_end(&mut head)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    if Compression::detect(&head) == Compression::None {
+        file.seek(SeekFrom::Start(start.byte_offset))?;
+        pb.inc(start.byte_offset);
+        let reader: Box<dyn BufRead + Send> = Box::new(BufReader::new(pb.wrap_read(file)));
+        return Ok(TrackedLines::new(reader, start.byte_offset, start.line_number));
+    }
+
+    let (_, reader) = wrap_reader(BufReader::new(pb.wrap_read(file)))?;
+    let mut lines = TrackedLines::new(reader, 0, 0);
+    while lines.line_number() < start.line_number {
+        if lines.next().is_none() {
+            break;
+        }
+    }
+    Ok(lines)
+}
+
+/// 出力ファイル (指定した形式で圧縮する)
+///
+/// 圧縮した場合はフッターを書くため、最後に必ず `finish` を呼ぶ。
+pub enum OutputWriter {
+    Plain(BufWriter<File>),
+    Zstd(zstd::stream::write::Encoder<'static, BufWriter<File>>),
+    Gzip(flate2::write::GzEncoder<BufWriter<File>>),
+}
+
+impl OutputWriter {
+    pub fn create(path: &Path, compression: Compression) -> io::Result<Self> {
+        let writer = BufWriter::new(File::create(path)?);
+        Ok(match compression {
+            Compression::None => Self::Plain(writer),
+            Compression::Zstd => Self::Zstd(zstd::stream::write::Encoder::new(writer, ZSTD_LEVEL)?),
+            Compression::Gzip => Self::Gzip(flate2::write::GzEncoder::new(writer, flate2::Compression::default())),
+        })
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        let mut writer = match self {
+            Self::Plain(writer) => writer,
+            Self::Zstd(encoder) => encoder.finish()?,
+            Self::Gzip(encoder) => encoder.finish()?,
+        };
+        writer.flush()
+    }
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            Self::Zstd(encoder) => encoder.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            Self::Zstd(encoder) => encoder.flush(),
+            Self::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use sea_orm::{ConnectionTrait, DatabaseConnection, Statement};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use crate::domain::gallery::Gallery;
+use crate::import::ImportMode;
+use crate::import::checkpoint::{CheckpointStore, FileFingerprint};
+use crate::import::dead_letter::{DeadLetter, DeadLetterWriter, RejectKind, Rejection, Stage};
+use crate::import::record;
+use crate::import::session::ImportSession;
+use crate::mapper::{galleries_mapper, suggest_mapper};
+use crate::pipeline::{ImportOptions, ImportStats};
+use crate::pipeline::compression;
+
+const BATCH_SIZE: usize = 500;
+
+/// 正規化済みの Gallery JSON をリレーショナルテーブルに取り込む
+///
+/// `sweep` の場合は取り込み後に参照されなくなったタグなどを削除する。
+/// サジェストのギャラリー数は取り込みが途中で失敗しても集計し直す。
+pub async fn run(db: &DatabaseConnection, inputs: &[PathBuf], options: ImportOptions, sweep: bool, dead_letters: &DeadLetterWriter) -> Result<ImportStats> {
+    let result = import_inputs(db, inputs, options, sweep, dead_letters).await;
+
+    // 5. サジェストのギャラリー数を集計し直す (失敗するまでにコミットしたバッチも反映する)
+    refresh_suggest_entries(db, result).await
+}
+
+async fn import_inputs(db: &DatabaseConnection, inputs: &[PathBuf], options: ImportOptions, sweep: bool, dead_letters: &DeadLetterWriter) -> Result<ImportStats> {
+    let mode = options.mode;
+    println!("Import mode: {}, loader: {}", mode.name(), options.loader.name());
+
+    // 1. テーブル作成 (rebuild の場合は削除してから)
+    prepare_tables(db, mode).await?;
+
+    // 2. チェックポイント (中断した取り込みの再開位置)
+    let checkpoints = CheckpointStore::new(db.clone(), "relational");
+    checkpoints.create_table().await.context("Failed to create checkpoint table")?;
+    if mode == ImportMode::Rebuild || options.restart {
+        let cleared = checkpoints.clear().await.context("Failed to clear checkpoints")?;
+        println!("Cleared {} checkpoints", cleared);
+    }
+
+    // 3. 全ファイルサイズの合計でプログレスバーを設定して順次処理
+    let total_size: u64 = inputs.iter()
+        .map(|path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+        .sum();
+    let pb = ProgressBar::new(total_size);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+        .unwrap()
+        .progress_chars("#>-"));
+
+    // 言語・タグなどの ID はファイルをまたいで覚えておく
+    let session = ImportSession::new();
+    let started = Instant::now();
+    let mut rows = 0;
+    for path in inputs {
+        rows += import_jsonl_to_db(db, path, pb.clone(), options, &checkpoints, dead_letters, &session).await?;
+    }
+    let stats = ImportStats { loader: options.loader, rows, elapsed: started.elapsed() };
+
+    pb.finish_with_message("All imports completed");
+
+    println!("Import completed successfully");
+    println!("{}", stats);
+    if dead_letters.count() > 0 {
+        println!("{} rejected records were written to {:?}", dead_letters.count(), dead_letters.path());
+    }
+
+    // 4. 参照されていないエンティティの削除
+    if sweep {
+        sweep_orphaned_entities(db).await?;
+    }
+
+    Ok(stats)
+}
+
+/// 参照されなくなったタグなどを削除する
+pub async fn sweep_orphaned_entities(db: &DatabaseConnection) -> Result<()> {
+    println!("Sweeping orphaned entities...");
+    for (table, removed) in galleries_mapper::sweep_orphaned_entities(db).await
+        .context("Failed to sweep orphaned entities")? {
+        println!("  {}: {} removed", table, removed);
+    }
+    Ok(())
+}
+
+/// 書き込みの後にサジェストのギャラリー数を集計し直す
+///
+/// 書き込みが失敗していても集計し、書き込みのエラーを優先して返す。
+pub async fn refresh_suggest_entries<T>(db: &DatabaseConnection, result: Result<T>) -> Result<T> {
+    println!("Refreshing suggestion counts...");
+    let refreshed = suggest_mapper::refresh_suggest_entries(db).await
+        .context("Failed to refresh suggestion counts");
+    let value = result?;
+    refreshed?;
+    Ok(value)
+}
+
+/// テーブルを作成する (rebuild の場合は削除してから)
+pub async fn prepare_tables(db: &DatabaseConnection, mode: ImportMode) -> Result<()> {
+    if mode == ImportMode::Rebuild {
+        println!("Dropping tables...");
+        drop_tables(db).await?;
+    }
+
+    println!("Creating tables...");
+    create_tables(db).await?;
+    println!("Tables created successfully");
+    Ok(())
+}
+
+async fn create_tables(db: &DatabaseConnection) -> Result<()> {
+    let schema = include_str!("../../sql/schema.sql");
+    let statements: Vec<&str> = schema
+        .split(';')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    for sql in statements {
+        db.execute_raw(Statement::from_string(
+            sea_orm::DbBackend::Postgres,
+            sql.to_string(),
+        ))
+        .await
+        .with_context(|| format!("Failed to execute: {}", sql))?;
+    }
+
+    Ok(())
+}
+
+async fn drop_tables(db: &DatabaseConnection) -> Result<()> {
+    let statements = vec![
+        "DROP MATERIALIZED VIEW IF EXISTS suggest_entries",
+        "DROP TABLE IF EXISTS gallery_files CASCADE",
+        "DROP TABLE IF EXISTS files CASCADE",
+        "DROP TABLE IF EXISTS gallery_tags CASCADE",
+        "DROP TABLE IF EXISTS gallery_artists CASCADE",
+        "DROP TABLE IF EXISTS gallery_groups CASCADE",
+        "DROP TABLE IF EXISTS gallery_characters CASCADE",
+        "DROP TABLE IF EXISTS gallery_parodies CASCADE",
+        "DROP TABLE IF EXISTS galleries CASCADE",
+        "DROP TABLE IF EXISTS languages CASCADE",
+        "DROP TABLE IF EXISTS tags CASCADE",
+        "DROP TABLE IF EXISTS artists CASCADE",
+        "DROP TABLE IF EXISTS groups CASCADE",
+        "DROP TABLE IF EXISTS characters CASCADE",
+        "DROP TABLE IF EXISTS parodies CASCADE",
+    ];
+
+    for sql in statements {
+        db.execute_raw(Statement::from_string(
+            sea_orm::DbBackend::Postgres,
+            sql.to_string(),
+        ))
+        .await
+        .with_context(|| format!("Failed to execute: {}", sql))?;
+    }
+
+    Ok(())
+}
+
+async fn import_jsonl_to_db(
+    db: &DatabaseConnection,
+    jsonl_path: &Path,
+    pb: ProgressBar,
+    options: ImportOptions,
+    checkpoints: &CheckpointStore,
+    dead_letters: &DeadLetterWriter,
+    session: &ImportSession,
+) -> Result<u64> {
+    // 前回の続きから読む (完了済みのファイルは読み飛ばす)
+    let fingerprint = FileFingerprint::of(jsonl_path)
+        .with_context(|| format!("Failed to stat file: {:?}", jsonl_path))?;
+    let mut checkpoint = checkpoints.load(jsonl_path, fingerprint).await?.unwrap_or_default();
+    if checkpoint.completed {
+        pb.inc(fingerprint.size);
+        pb.println(format!("Skipping completed file: {:?}", jsonl_path));
+        return Ok(0);
+    }
+
+    if checkpoint.line_number > 0 {
+        pb.println(format!("Resuming {:?} from line {}", jsonl_path, checkpoint.line_number + 1));
+    }
+
+    // 圧縮されていれば展開し、プログレスバーでラップ
+    let mut lines = compression::open_lines(jsonl_path, checkpoint, &pb)
+        .with_context(|| format!("Failed to open file: {:?}", jsonl_path))?;
+
+    // 処理できなかった行はデッドレターに書いて次へ進む
+    let reject = |line_number: u64, rejection: Rejection, raw: String| {
+        pb.println(format!("Rejected line {}: {}", line_number, rejection));
+        dead_letters.write(&DeadLetter::new(jsonl_path, line_number, Stage::ImportToDb, rejection, raw))
+    };
+
+    let mut rows = 0;
+    let mut chunk = Vec::with_capacity(BATCH_SIZE);
+    // 挿入に失敗した場合にデッドレターに書く (行番号, 元の行)
+    let mut chunk_lines = Vec::with_capacity(BATCH_SIZE);
+
+    while let Some(line) = lines.next() {
+        let line_number = lines.line_number();
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                reject(line_number, Rejection::new(RejectKind::Read, None, e.to_string()), String::new())?;
+                continue;
+            }
+        };
+        
+        // 空行はスキップ
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // JSONをパース
+        match record::parse_gallery(&line) {
+            Ok(gallery) => {
+                chunk.push(gallery);
+                chunk_lines.push((line_number, line));
+                if chunk.len() >= BATCH_SIZE {
+                    rows += insert_chunk(db, std::mem::take(&mut chunk), std::mem::take(&mut chunk_lines), options, session, &reject).await?;
+
+                    // コミットしたバッチの直後を記録する
+                    checkpoint.byte_offset = lines.byte_offset();
+                    checkpoint.line_number = line_number;
+                    checkpoint.batches += 1;
+                    checkpoints.save(jsonl_path, fingerprint, &checkpoint).await?;
+                }
+            }
+            Err(rejection) => reject(line_number, rejection, line)?,
+        }
+    }
+
+    if !chunk.is_empty() {
+        rows += insert_chunk(db, chunk, chunk_lines, options, session, &reject).await?;
+        checkpoint.batches += 1;
+    }
+
+    checkpoint.byte_offset = lines.byte_offset();
+    checkpoint.line_number = lines.line_number();
+    checkpoint.completed = true;
+    checkpoints.save(jsonl_path, fingerprint, &checkpoint).await?;
+
+    Ok(rows)
+}
+
+/// バッチを挿入し、失敗した場合はバッチ内の全レコードをデッドレターに書く
+///
+/// 戻り値は書き込んだ行数 (失敗した場合は 0)。
+async fn insert_chunk(
+    db: &DatabaseConnection,
+    chunk: Vec<Gallery>,
+    chunk_lines: Vec<(u64, String)>,
+    options: ImportOptions,
+    session: &ImportSession,
+    reject: &impl Fn(u64, Rejection, String) -> std::io::Result<()>,
+) -> Result<u64> {
+    let gallery_ids: Vec<i32> = chunk.iter().map(|gallery| gallery.gallery_id).collect();
+    if let Err(e) = galleries_mapper::load_galleries(db, chunk, options.mode, options.loader, session).await {
+        for ((line_number, raw), gallery_id) in chunk_lines.into_iter().zip(gallery_ids) {
+            reject(line_number, Rejection::new(RejectKind::Insert, Some(gallery_id), e.to_string()), raw)?;
+        }
+        return Ok(0);
+    }
+    Ok(gallery_ids.len() as u64)
+}
@@ -0,0 +1,243 @@
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use sea_orm::{ConnectionTrait, DatabaseConnection, Statement};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use crate::fbs::compress::CompressType;
+use crate::import::ImportMode;
+use crate::import::checkpoint::{Checkpoint, CheckpointStore, FileFingerprint};
+use crate::import::dead_letter::{DeadLetter, DeadLetterWriter, RejectKind, Rejection, Stage};
+use crate::import::record;
+use crate::mapper::fbs_galleries_mapper;
+use crate::pipeline::{ImportOptions, ImportStats};
+use crate::pipeline::compression;
+
+const BATCH_SIZE: usize = 500;
+
+/// 正規化済みの Gallery JSON を FlatBuffers + zstd にして fbs_galleries に取り込む
+pub async fn run(db: &DatabaseConnection, inputs: &[PathBuf], options: ImportOptions, dead_letters: Arc<DeadLetterWriter>) -> Result<ImportStats> {
+    let mode = options.mode;
+    println!("Import mode: {}, loader: {}", mode.name(), options.loader.name());
+
+    // 1. テーブル作成 (rebuild の場合は削除してから) と compress_type の取得
+    let compress_type_id = prepare_tables(db, mode).await?;
+
+    // 2. チェックポイント (中断した取り込みの再開位置)
+    let checkpoints = CheckpointStore::new(db.clone(), "fbs");
+    checkpoints.create_table().await.context("Failed to create checkpoint table")?;
+    if mode == ImportMode::Rebuild || options.restart {
+        let cleared = checkpoints.clear().await.context("Failed to clear checkpoints")?;
+        println!("Cleared {} checkpoints", cleared);
+    }
+
+    // 3. 全ファイルサイズの合計でプログレスバーを設定して順次処理
+    let total_size: u64 = inputs.iter()
+        .map(|path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+        .sum();
+    let pb = ProgressBar::new(total_size);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+        .unwrap()
+        .progress_chars("#>-"));
+
+    let started = Instant::now();
+    let mut rows = 0;
+    for path in inputs {
+        rows += import_jsonl_to_fbs_db(db, path, pb.clone(), compress_type_id, options, &checkpoints, &dead_letters).await?;
+    }
+    let stats = ImportStats { loader: options.loader, rows, elapsed: started.elapsed() };
+
+    pb.finish_with_message("All imports completed");
+
+    println!("Import completed successfully");
+    println!("{}", stats);
+    if dead_letters.count() > 0 {
+        println!("{} rejected records were written to {:?}", dead_letters.count(), dead_letters.path());
+    }
+
+    Ok(stats)
+}
+
+/// テーブルを作成し (rebuild の場合は削除してから)、zstd の compress_type の ID を返す
+pub async fn prepare_tables(db: &DatabaseConnection, mode: ImportMode) -> Result<i32> {
+    if mode == ImportMode::Rebuild {
+        println!("Dropping tables...");
+        drop_tables(db).await?;
+    }
+
+    println!("Creating tables...");
+    create_tables(db).await?;
+    println!("Tables created successfully");
+
+    // compress_typeのIDを取得または作成
+    let compress_type_id = fbs_galleries_mapper::get_or_create_compress_type(db, CompressType::Zstd.name()).await?;
+    println!("Using compress_type_id: {} for zstd", compress_type_id);
+    Ok(compress_type_id)
+}
+
+async fn create_tables(db: &DatabaseConnection) -> Result<()> {
+    let schema = include_str!("../../sql/fbs_schema.sql");
+    let statements: Vec<&str> = schema
+        .split(';')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    for sql in statements {
+        db.execute_raw(Statement::from_string(
+            sea_orm::DbBackend::Postgres,
+            sql.to_string(),
+        ))
+        .await
+        .with_context(|| format!("Failed to execute: {}", sql))?;
+    }
+
+    Ok(())
+}
+
+async fn drop_tables(db: &DatabaseConnection) -> Result<()> {
+    // 外部キー制約のため、参照する側(fbs_galleries)を先にdrop
+    let statements = vec![
+        "DROP TABLE IF EXISTS fbs_galleries CASCADE",
+        "DROP TABLE IF EXISTS fbs_compress_types CASCADE",
+    ];
+
+    for sql in statements {
+        db.execute_raw(Statement::from_string(
+            sea_orm::DbBackend::Postgres,
+            sql.to_string(),
+        ))
+        .await
+        .with_context(|| format!("Failed to execute: {}", sql))?;
+    }
+
+    Ok(())
+}
+
+/// Consumer に送る1バッチ
+struct Batch {
+    rows: Vec<(i32, Vec<u8>, i32)>,
+    /// 挿入に失敗した場合にデッドレターに書く (行番号, 元の行)
+    lines: Vec<(u64, String)>,
+    /// バッチの最終行の直後の位置
+    byte_offset: u64,
+    line_number: u64,
+}
+
+async fn import_jsonl_to_fbs_db(
+    db: &DatabaseConnection,
+    jsonl_path: &Path,
+    pb: ProgressBar,
+    compress_type_id: i32,
+    options: ImportOptions,
+    checkpoints: &CheckpointStore,
+    dead_letters: &Arc<DeadLetterWriter>,
+) -> Result<u64> {
+    // 0. 前回の続きから読む (完了済みのファイルは読み飛ばす)
+    let fingerprint = FileFingerprint::of(jsonl_path)
+        .with_context(|| format!("Failed to stat file: {:?}", jsonl_path))?;
+    let mut checkpoint = checkpoints.load(jsonl_path, fingerprint).await?.unwrap_or_default();
+    if checkpoint.completed {
+        pb.inc(fingerprint.size);
+        pb.println(format!("Skipping completed file: {:?}", jsonl_path));
+        return Ok(0);
+    }
+    if checkpoint.line_number > 0 {
+        pb.println(format!("Resuming {:?} from line {}", jsonl_path, checkpoint.line_number + 1));
+    }
+
+    // 1. moveのためにclone/to_path_buf
+    let path_buf = jsonl_path.to_path_buf();
+    let db_clone = db.clone();
+    let pb_clone = pb.clone();
+    let reader_dead_letters = dead_letters.clone();
+    let start = checkpoint;
+
+    // 2. チャンネルの作成（バッファを持たせて流量調整）
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<Batch>(5);
+
+    // 3. 読み込み・パース・FBS変換用のタスクを分離して実行 (Producer)
+    let reader_handle = tokio::spawn(async move {
+        let mut batch = Batch { rows: Vec::with_capacity(BATCH_SIZE), lines: Vec::with_capacity(BATCH_SIZE), byte_offset: 0, line_number: 0 };
+        // 圧縮されていれば展開する (圧縮されたファイルは再開位置まで読み飛ばす)
+        let mut lines = compression::open_lines(&path_buf, start, &pb_clone)
+            .with_context(|| format!("Failed to open file: {:?}", path_buf))?;
+
+        // 処理できなかった行はデッドレターに書いて次へ進む
+        let reject = |line_number: u64, rejection: Rejection, raw: String| {
+            pb_clone.println(format!("Rejected line {}: {}", line_number, rejection));
+            reader_dead_letters.write(&DeadLetter::new(&path_buf, line_number, Stage::ImportBinToDb, rejection, raw))
+        };
+
+        while let Some(line) = lines.next() {
+            let line_number = lines.line_number();
+            let line = match line {
+                Ok(l) => l,
+                Err(e) => {
+                    reject(line_number, Rejection::new(RejectKind::Read, None, e.to_string()), String::new())?;
+                    continue;
+                }
+            };
+
+            // 空行はスキップ
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // JSONをパースし、FlatBuffersに変換してzstdで圧縮
+            let compressed = record::parse_gallery(&line)
+                .and_then(|gallery| Ok((gallery.gallery_id, record::compress_gallery(&gallery)?)));
+            let (gallery_id, compressed_data) = match compressed {
+                Ok(compressed) => compressed,
+                Err(rejection) => {
+                    reject(line_number, rejection, line)?;
+                    continue;
+                }
+            };
+
+            batch.rows.push((gallery_id, compressed_data, compress_type_id));
+            batch.lines.push((line_number, line));
+
+            if batch.rows.len() >= BATCH_SIZE {
+                // DBタスクへ送信
+                batch.byte_offset = lines.byte_offset();
+                batch.line_number = lines.line_number();
+                let next = Batch { rows: Vec::with_capacity(BATCH_SIZE), lines: Vec::with_capacity(BATCH_SIZE), byte_offset: 0, line_number: 0 };
+                if tx.send(std::mem::replace(&mut batch, next)).await.is_err() {
+                    break;
+                }
+            }
+        }
+        if !batch.rows.is_empty() {
+            batch.byte_offset = lines.byte_offset();
+            batch.line_number = lines.line_number();
+            tx.send(batch).await.ok();
+        }
+        Ok::<(u64, u64), anyhow::Error>((lines.byte_offset(), lines.line_number()))
+    });
+
+    // 4. メインタスクでDBインサートをひたすら実行し、バッチごとに位置を記録する (Consumer)
+    let mut rows = 0;
+    while let Some(batch) = rx.recv().await {
+        let gallery_ids: Vec<i32> = batch.rows.iter().map(|(gallery_id, _, _)| *gallery_id).collect();
+        match fbs_galleries_mapper::load_fbs_batch(&db_clone, batch.rows, options.mode, options.loader).await {
+            Ok(()) => rows += gallery_ids.len() as u64,
+            Err(e) => {
+                // バッチ全体が失敗するので、バッチ内の全レコードをデッドレターに書く
+                pb.println(format!("Insert error: {:?}", e));
+                for ((line_number, raw), gallery_id) in batch.lines.into_iter().zip(gallery_ids) {
+                    let rejection = Rejection::new(RejectKind::Insert, Some(gallery_id), e.to_string());
+                    dead_letters.write(&DeadLetter::new(jsonl_path, line_number, Stage::ImportBinToDb, rejection, raw))?;
+                }
+            }
+        }
+        checkpoint = Checkpoint { byte_offset: batch.byte_offset, line_number: batch.line_number, batches: checkpoint.batches + 1, completed: false };
+        checkpoints.save(jsonl_path, fingerprint, &checkpoint).await?;
+    }
+
+    let (byte_offset, line_number) = reader_handle.await??; // 読み込み完了を待機
+    checkpoint = Checkpoint { byte_offset, line_number, completed: true, ..checkpoint };
+    checkpoints.save(jsonl_path, fingerprint, &checkpoint).await?;
+    Ok(rows)
+}
@@ -0,0 +1,377 @@
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use sea_orm::DatabaseConnection;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use crate::domain::gallery::Gallery;
+use crate::import::ImportMode;
+use crate::import::checkpoint::{Checkpoint, CheckpointStore, FileFingerprint};
+use crate::import::dead_letter::{DeadLetter, DeadLetterWriter, RejectKind, Rejection, Stage};
+use crate::import::record;
+use crate::import::session::ImportSession;
+use crate::mapper::{fbs_galleries_mapper, galleries_mapper};
+use crate::pipeline::{compression, import, import_fbs, ImportOptions};
+
+const BATCH_SIZE: usize = 500;
+/// 取り込み先ごとのチャンネルに溜めるバッチ数 (読み込みが DB より速い場合はここで待つ)
+const CHANNEL_CAPACITY: usize = 5;
+
+/// 段階ごとのデッドレター
+///
+/// 中間ファイルを作らないので、失敗した段階の入力 (RESP の行、raw JSON、正規化した JSON) を
+/// その段階のファイルに書き、replay_dead_letters でそのまま再処理できるようにする。
+pub struct IngestDeadLetters {
+    resp: DeadLetterWriter,
+    normalize: DeadLetterWriter,
+    import: DeadLetterWriter,
+    import_fbs: DeadLetterWriter,
+}
+
+impl IngestDeadLetters {
+    pub fn in_dir(dir: &Path) -> Self {
+        Self {
+            resp: DeadLetterWriter::in_dir(dir, Stage::RespToJson),
+            normalize: DeadLetterWriter::in_dir(dir, Stage::NormalizeJson),
+            import: DeadLetterWriter::in_dir(dir, Stage::ImportToDb),
+            import_fbs: DeadLetterWriter::in_dir(dir, Stage::ImportBinToDb),
+        }
+    }
+
+    pub fn writer(&self, stage: Stage) -> &DeadLetterWriter {
+        match stage {
+            Stage::RespToJson => &self.resp,
+            Stage::NormalizeJson => &self.normalize,
+            Stage::ImportToDb => &self.import,
+            Stage::ImportBinToDb => &self.import_fbs,
+        }
+    }
+
+    fn all(&self) -> [&DeadLetterWriter; 4] {
+        [&self.resp, &self.normalize, &self.import, &self.import_fbs]
+    }
+}
+
+/// RESP を中間ファイル無しでリレーショナルテーブルと fbs_galleries に取り込む
+///
+/// 読み込み・パース・FlatBuffers 変換を1つのタスクで行い、2つの取り込み先には
+/// それぞれ容量付きのチャンネルで並行に書き込む。`fbs_db` が None の場合は fbs_galleries には書かない。
+/// チェックポイントは両方の取り込み先がコミットした位置までを記録する。
+/// サジェストのギャラリー数は取り込みが途中で失敗しても集計し直す。
+pub async fn run(
+    db: &DatabaseConnection,
+    fbs_db: Option<&DatabaseConnection>,
+    inputs: &[PathBuf],
+    options: ImportOptions,
+    sweep: bool,
+    dead_letters: Arc<IngestDeadLetters>,
+) -> Result<()> {
+    let result = ingest_inputs(db, fbs_db, inputs, options, sweep, dead_letters).await;
+
+    // 5. サジェストのギャラリー数を集計し直す (失敗するまでにコミットしたバッチも反映する)
+    import::refresh_suggest_entries(db, result).await
+}
+
+async fn ingest_inputs(
+    db: &DatabaseConnection,
+    fbs_db: Option<&DatabaseConnection>,
+    inputs: &[PathBuf],
+    options: ImportOptions,
+    sweep: bool,
+    dead_letters: Arc<IngestDeadLetters>,
+) -> Result<()> {
+    let mode = options.mode;
+    println!("Import mode: {}, loader: {}", mode.name(), options.loader.name());
+
+    // 1. テーブル作成 (rebuild の場合は削除してから)
+    import::prepare_tables(db, mode).await?;
+    let fbs = match fbs_db {
+        Some(fbs_db) => Some((fbs_db.clone(), import_fbs::prepare_tables(fbs_db, mode).await?)),
+        None => None,
+    };
+
+    // 2. チェックポイント (中断した取り込みの再開位置)
+    let checkpoints = CheckpointStore::new(db.clone(), "ingest");
+    checkpoints.create_table().await.context("Failed to create checkpoint table")?;
+    if mode == ImportMode::Rebuild || options.restart {
+        let cleared = checkpoints.clear().await.context("Failed to clear checkpoints")?;
+        println!("Cleared {} checkpoints", cleared);
+    }
+
+    // 3. 全ファイルサイズの合計でプログレスバーを設定して順次処理
+    let total_size: u64 = inputs.iter()
+        .map(|path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+        .sum();
+    let pb = ProgressBar::new(total_size);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+        .unwrap()
+        .progress_chars("#>-"));
+
+    // 言語・タグなどの ID はファイルをまたいで覚えておく
+    let relational = (db.clone(), ImportSession::new());
+    for path in inputs {
+        ingest_resp_file(relational.clone(), fbs.clone(), path, pb.clone(), options, &checkpoints, &dead_letters).await?;
+    }
+
+    pb.finish_with_message("All imports completed");
+
+    println!("Ingest completed successfully");
+    for writer in dead_letters.all() {
+        if writer.count() > 0 {
+            println!("{} rejected records were written to {:?}", writer.count(), writer.path());
+        }
+    }
+
+    // 4. 参照されていないエンティティの削除
+    if sweep {
+        import::sweep_orphaned_entities(db).await?;
+    }
+
+    Ok(())
+}
+
+/// リレーショナルテーブルに送る1バッチ
+struct GalleryBatch {
+    seq: u64,
+    galleries: Vec<Gallery>,
+    /// 挿入に失敗した場合にデッドレターに書く (RESP の行番号, RESP の行)
+    lines: Vec<(u64, String)>,
+    position: (u64, u64),
+}
+
+/// fbs_galleries に送る1バッチ (rows と lines は同じ順に並ぶ)
+struct FbsBatch {
+    seq: u64,
+    rows: Vec<(i32, Vec<u8>, i32)>,
+    lines: Vec<(u64, String)>,
+    position: (u64, u64),
+}
+
+/// 取り込み先がバッチをコミットしたことの通知
+struct Ack {
+    sink: usize,
+    seq: u64,
+    /// バッチの最終行の直後の位置 (バイト位置, 行番号)
+    position: (u64, u64),
+}
+
+const RELATIONAL_SINK: usize = 0;
+const FBS_SINK: usize = 1;
+
+async fn ingest_resp_file(
+    relational: (DatabaseConnection, ImportSession),
+    fbs: Option<(DatabaseConnection, i32)>,
+    resp_path: &Path,
+    pb: ProgressBar,
+    options: ImportOptions,
+    checkpoints: &CheckpointStore,
+    dead_letters: &Arc<IngestDeadLetters>,
+) -> Result<()> {
+    // 0. 前回の続きから読む (完了済みのファイルは読み飛ばす)
+    let fingerprint = FileFingerprint::of(resp_path)
+        .with_context(|| format!("Failed to stat file: {:?}", resp_path))?;
+    let mut checkpoint = checkpoints.load(resp_path, fingerprint).await?.unwrap_or_default();
+    if checkpoint.completed {
+        pb.inc(fingerprint.size);
+        pb.println(format!("Skipping completed file: {:?}", resp_path));
+        return Ok(());
+    }
+    if checkpoint.line_number > 0 {
+        pb.println(format!("Resuming {:?} from line {}", resp_path, checkpoint.line_number + 1));
+    }
+
+    // 1. 取り込み先ごとのタスク (Consumer)。チャンネルに容量を持たせて流量調整する
+    let (ack_tx, mut ack_rx) = mpsc::unbounded_channel::<Ack>();
+    let (gallery_tx, gallery_rx) = mpsc::channel::<GalleryBatch>(CHANNEL_CAPACITY);
+    let gallery_handle = tokio::spawn(insert_galleries(
+        relational, gallery_rx, ack_tx.clone(), options, resp_path.to_path_buf(), dead_letters.clone(), pb.clone(),
+    ));
+    let (fbs_tx, fbs_handle, compress_type_id) = match fbs {
+        Some((fbs_db, compress_type_id)) => {
+            let (fbs_tx, fbs_rx) = mpsc::channel::<FbsBatch>(CHANNEL_CAPACITY);
+            let fbs_handle = tokio::spawn(insert_fbs(
+                fbs_db, fbs_rx, ack_tx.clone(), options, resp_path.to_path_buf(), dead_letters.clone(), pb.clone(),
+            ));
+            (Some(fbs_tx), Some(fbs_handle), Some(compress_type_id))
+        }
+        None => (None, None, None),
+    };
+    let sinks = if fbs_tx.is_some() { 2 } else { 1 };
+    drop(ack_tx);
+
+    // 2. 読み込み・パース・FBS変換用のタスク (Producer)
+    // ファイルの読み込みと展開はブロックするので、ブロッキング用のスレッドで行う
+    let path_buf = resp_path.to_path_buf();
+    let reader_dead_letters = dead_letters.clone();
+    let pb_clone = pb.clone();
+    let start = checkpoint;
+    let reader_handle = tokio::task::spawn_blocking(move || {
+        // 圧縮されていれば展開する (圧縮されたファイルは再開位置まで読み飛ばす)
+        let mut lines = compression::open_lines(&path_buf, start, &pb_clone)
+            .with_context(|| format!("Failed to open file: {:?}", path_buf))?;
+
+        // 処理できなかった行は、失敗した段階のデッドレターに書いて次へ進む
+        let reject = |stage: Stage, line_number: u64, rejection: Rejection, raw: String| {
+            pb_clone.println(format!("Rejected line {} ({}): {}", line_number, stage.name(), rejection));
+            reader_dead_letters.writer(stage).write(&DeadLetter::new(&path_buf, line_number, stage, rejection, raw))
+        };
+
+        let mut seq = 0;
+        let mut galleries = Vec::with_capacity(BATCH_SIZE);
+        let mut gallery_lines = Vec::with_capacity(BATCH_SIZE);
+        let mut rows = Vec::with_capacity(BATCH_SIZE);
+        let mut row_lines = Vec::with_capacity(BATCH_SIZE);
+
+        loop {
+            let line = lines.next();
+            let end = line.is_none();
+            if let Some(line) = line {
+                let line_number = lines.line_number();
+                let line = match line {
+                    Ok(line) => line,
+                    Err(e) => {
+                        reject(Stage::RespToJson, line_number, Rejection::new(RejectKind::Read, None, e.to_string()), String::new())?;
+                        continue;
+                    }
+                };
+
+                // 空行はスキップ
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let gallery = match record::resp_to_gallery(&line) {
+                    Ok(Some(gallery)) => gallery,
+                    // 404は多いので無視
+                    Ok(None) => continue,
+                    Err((stage, rejection, raw)) => {
+                        reject(stage, line_number, rejection, raw)?;
+                        continue;
+                    }
+                };
+
+                // FlatBuffersに変換してzstdで圧縮 (失敗してもリレーショナルテーブルには取り込む)
+                if let Some(compress_type_id) = compress_type_id {
+                    match record::compress_gallery(&gallery) {
+                        Ok(data) => {
+                            rows.push((gallery.gallery_id, data, compress_type_id));
+                            row_lines.push((line_number, line.clone()));
+                        }
+                        Err(rejection) => reject(Stage::ImportBinToDb, line_number, rejection, normalized_line(&line))?,
+                    }
+                }
+                galleries.push(gallery);
+                gallery_lines.push((line_number, line));
+
+                if galleries.len() < BATCH_SIZE {
+                    continue;
+                }
+            }
+
+            // 両方の取り込み先に同じ seq のバッチを送る (空でも送って位置を揃える)
+            if !galleries.is_empty() || !rows.is_empty() {
+                let position = (lines.byte_offset(), lines.line_number());
+                let batch = GalleryBatch { seq, galleries: std::mem::take(&mut galleries), lines: std::mem::take(&mut gallery_lines), position };
+                if gallery_tx.blocking_send(batch).is_err() {
+                    break;
+                }
+                if let Some(fbs_tx) = &fbs_tx {
+                    let batch = FbsBatch { seq, rows: std::mem::take(&mut rows), lines: std::mem::take(&mut row_lines), position };
+                    if fbs_tx.blocking_send(batch).is_err() {
+                        break;
+                    }
+                }
+                seq += 1;
+            }
+            if end {
+                break;
+            }
+        }
+        Ok::<(u64, u64), anyhow::Error>((lines.byte_offset(), lines.line_number()))
+    });
+
+    // 3. 両方の取り込み先がコミットした位置までチェックポイントを進める
+    let mut acked: [Option<Ack>; 2] = [None, None];
+    let mut saved_seq = None;
+    while let Some(ack) = ack_rx.recv().await {
+        let sink = ack.sink;
+        acked[sink] = Some(ack);
+        let committed = acked[..sinks].iter()
+            .map(|ack| ack.as_ref().map(|ack| (ack.seq, ack.position)))
+            .collect::<Option<Vec<_>>>()
+            .and_then(|acks| acks.into_iter().min_by_key(|(seq, _)| *seq));
+        if let Some((seq, (byte_offset, line_number))) = committed
+            && saved_seq.is_none_or(|saved| seq > saved) {
+            checkpoint = Checkpoint { byte_offset, line_number, batches: checkpoint.batches + 1, completed: false };
+            checkpoints.save(resp_path, fingerprint, &checkpoint).await?;
+            saved_seq = Some(seq);
+        }
+    }
+
+    let (byte_offset, line_number) = reader_handle.await??; // 読み込み完了を待機
+    gallery_handle.await??;
+    if let Some(fbs_handle) = fbs_handle {
+        fbs_handle.await??;
+    }
+    checkpoint = Checkpoint { byte_offset, line_number, completed: true, ..checkpoint };
+    checkpoints.save(resp_path, fingerprint, &checkpoint).await?;
+    Ok(())
+}
+
+/// リレーショナルテーブルへの書き込み (失敗したバッチは全レコードをデッドレターに書く)
+async fn insert_galleries(
+    (db, session): (DatabaseConnection, ImportSession),
+    mut rx: mpsc::Receiver<GalleryBatch>,
+    ack_tx: mpsc::UnboundedSender<Ack>,
+    options: ImportOptions,
+    resp_path: PathBuf,
+    dead_letters: Arc<IngestDeadLetters>,
+    pb: ProgressBar,
+) -> Result<()> {
+    while let Some(batch) = rx.recv().await {
+        let gallery_ids: Vec<i32> = batch.galleries.iter().map(|gallery| gallery.gallery_id).collect();
+        if !batch.galleries.is_empty()
+            && let Err(e) = galleries_mapper::load_galleries(&db, batch.galleries, options.mode, options.loader, &session).await {
+            pb.println(format!("Insert error: {:?}", e));
+            for ((line_number, line), gallery_id) in batch.lines.iter().zip(gallery_ids) {
+                let rejection = Rejection::new(RejectKind::Insert, Some(gallery_id), e.to_string());
+                dead_letters.import.write(&DeadLetter::new(&resp_path, *line_number, Stage::ImportToDb, rejection, normalized_line(line)))?;
+            }
+        }
+        ack_tx.send(Ack { sink: RELATIONAL_SINK, seq: batch.seq, position: batch.position }).ok();
+    }
+    Ok(())
+}
+
+/// fbs_galleries への書き込み (失敗したバッチは全レコードをデッドレターに書く)
+async fn insert_fbs(
+    db: DatabaseConnection,
+    mut rx: mpsc::Receiver<FbsBatch>,
+    ack_tx: mpsc::UnboundedSender<Ack>,
+    options: ImportOptions,
+    resp_path: PathBuf,
+    dead_letters: Arc<IngestDeadLetters>,
+    pb: ProgressBar,
+) -> Result<()> {
+    while let Some(batch) = rx.recv().await {
+        let gallery_ids: Vec<i32> = batch.rows.iter().map(|(gallery_id, _, _)| *gallery_id).collect();
+        if !batch.rows.is_empty()
+            && let Err(e) = fbs_galleries_mapper::load_fbs_batch(&db, batch.rows, options.mode, options.loader).await {
+            pb.println(format!("Insert error: {:?}", e));
+            for ((line_number, line), gallery_id) in batch.lines.iter().zip(gallery_ids) {
+                let rejection = Rejection::new(RejectKind::Insert, Some(gallery_id), e.to_string());
+                dead_letters.import_fbs.write(&DeadLetter::new(&resp_path, *line_number, Stage::ImportBinToDb, rejection, normalized_line(line)))?;
+            }
+        }
+        ack_tx.send(Ack { sink: FBS_SINK, seq: batch.seq, position: batch.position }).ok();
+    }
+    Ok(())
+}
+
+/// 取り込み段階のデッドレターには、replay で読めるように正規化した JSON を書く
+fn normalized_line(resp_line: &str) -> String {
+    record::resp_to_raw_json(resp_line).ok().flatten()
+        .and_then(|raw_json| record::normalize_raw_json(raw_json.as_bytes()).ok())
+        .unwrap_or_else(|| resp_line.to_string())
+}
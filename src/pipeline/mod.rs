@@ -0,0 +1,124 @@
+pub mod resp_to_json;
+pub mod normalize;
+pub mod analyze;
+pub mod compression;
+pub mod import;
+pub mod import_fbs;
+pub mod ingest;
+pub mod bench;
+
+use anyhow::{bail, Context, Result};
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::import::{ImportMode, Loader};
+
+/// 各段階の既定の入出力先
+pub const RESP_JSON_DIR: &str = "data/resp_json/";
+pub const RAW_JSON_DIR: &str = "data/raw_json/";
+pub const NORMALIZED_JSON_DIR: &str = "data/normalized_json/";
+pub const SCHEMA_DIR: &str = "data/schema/";
+pub const MERGED_SCHEMA_PATH: &str = "data/merged_schema.json";
+
+/// DB への取り込みの設定 (import / import-fbs 共通)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportOptions {
+    pub mode: ImportMode,
+    /// チェックポイントを無視して最初から取り込む
+    pub restart: bool,
+    pub loader: Loader,
+}
+
+/// 取り込みにかかった時間 (insert と COPY の比較に使う)
+#[derive(Debug, Clone, Copy)]
+pub struct ImportStats {
+    pub loader: Loader,
+    /// 書き込んだ行数 (失敗したバッチや読み飛ばしたファイルは含まない)
+    pub rows: u64,
+    pub elapsed: Duration,
+}
+
+impl ImportStats {
+    pub fn rows_per_sec(&self) -> f64 {
+        self.rows as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for ImportStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} rows loaded with {} in {:.2}s ({:.0} rows/s)",
+            self.rows, self.loader.name(), self.elapsed.as_secs_f64(), self.rows_per_sec())
+    }
+}
+
+/// 入力の指定 (ファイル、ディレクトリ、glob パターン) をファイルの一覧にする
+///
+/// ディレクトリは直下の `extension` のファイル (`.zst` / `.gz` で圧縮されたものを含む) だけを、名前順に読む。
+/// glob は一致したファイルをすべて読む。重複は取り除く。
+pub fn resolve_inputs(inputs: &[String], extension: &str) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+                .with_context(|| format!("Failed to read directory: {:?}", path))?
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && has_extension(p, extension))
+                .collect();
+            entries.sort();
+            paths.extend(entries);
+        } else if path.is_file() {
+            paths.push(path.to_path_buf());
+        } else if input.contains(['*', '?', '[']) {
+            let mut entries = Vec::new();
+            for entry in glob::glob(input).with_context(|| format!("Invalid glob pattern: {}", input))? {
+                let entry = entry?;
+                if entry.is_file() {
+                    entries.push(entry);
+                }
+            }
+            if entries.is_empty() {
+                bail!("No files match {}", input);
+            }
+            entries.sort();
+            paths.extend(entries);
+        } else {
+            bail!("Input does not exist: {}", input);
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    paths.retain(|p| seen.insert(p.clone()));
+    Ok(paths)
+}
+
+/// 圧縮の拡張子を除いた拡張子が `extension` のファイルか (`a.jsonl`, `a.jsonl.zst` など)
+pub fn has_extension(path: &Path, extension: &str) -> bool {
+    path.file_name().and_then(|s| s.to_str())
+        .map(compression::strip_compression_suffix)
+        .and_then(|file_name| Path::new(file_name).extension())
+        .is_some_and(|ext| ext == extension)
+}
+
+/// 入力ファイルに対応する出力ファイル (`<output_dir>/<入力の stem>.<extension>`)
+///
+/// 入力の圧縮の拡張子は stem に含めない。
+pub fn output_path(input: &Path, output_dir: &Path, extension: &str) -> PathBuf {
+    let file_name = input.file_name().and_then(|s| s.to_str()).unwrap_or("unknown");
+    let file_stem = Path::new(compression::strip_compression_suffix(file_name))
+        .file_stem().and_then(|s| s.to_str()).unwrap_or("unknown");
+    output_dir.join(format!("{}.{}", file_stem, extension))
+}
+
+/// 取り込み用の DB 接続
+pub async fn connect(database_url: &str, max_connections: u32) -> Result<DatabaseConnection> {
+    let mut opt = ConnectOptions::new(database_url).to_owned();
+    opt.max_connections(max_connections);
+    opt.connect_timeout(std::time::Duration::from_secs(10));
+    opt.acquire_timeout(std::time::Duration::from_secs(10));
+    opt.set_schema_search_path("public");
+    Database::connect(opt).await
+        .context("Failed to connect to database")
+}
@@ -0,0 +1,82 @@
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use crate::import::dead_letter::{DeadLetter, DeadLetterWriter, Stage};
+use crate::import::record;
+use crate::pipeline;
+use crate::pipeline::compression::{self, Compression, OutputWriter};
+
+/// raw JSON を正規化した Gallery JSON (`<output_dir>/<stem>.json`) にする
+///
+/// 入力は圧縮されていてもよい (zstd / gzip)。出力は `compression` で圧縮し、拡張子を付ける。
+/// `force` が false の場合、出力済みのファイルは読み飛ばす。
+pub fn run(inputs: &[PathBuf], output_dir: &Path, compression: Compression, force: bool, dead_letters: &DeadLetterWriter) -> Result<()> {
+    if !output_dir.exists() {
+        std::fs::create_dir_all(output_dir)?;
+    }
+
+    inputs.par_iter().for_each(|raw_jsonl| {
+        let normalized_jsonl = compression.output_path(&pipeline::output_path(raw_jsonl, output_dir, "json"));
+
+        if let Err(e) = normalize_jsonl(raw_jsonl, &normalized_jsonl, compression, force, dead_letters) {
+            eprintln!("Error processing {:?}: {:?}", raw_jsonl, e);
+        } else {
+            println!("Processed {:?}", raw_jsonl);
+        }
+    });
+    println!("Normalization completed.");
+    if dead_letters.count() > 0 {
+        println!("{} rejected records were written to {:?}", dead_letters.count(), dead_letters.path());
+    }
+    Ok(())
+}
+
+
+fn normalize_jsonl(raw_jsonl_path: &Path, normalized_jsonl_path: &Path, compression: Compression, force: bool, dead_letters: &DeadLetterWriter) -> Result<()> {
+    //! jsonlに含まれる各jsonを正規化して出力
+    //! 正規化できなかった行はデッドレターに書く
+
+    if normalized_jsonl_path.exists() && !force {
+        println!("Output file {:?} already exists. Skipping.", normalized_jsonl_path);
+        return Ok(());
+    }
+    let (_, mut raw_jsonl_reader) = compression::open_input(raw_jsonl_path, None)
+        .with_context(|| format!("Failed to open input file: {:?}", raw_jsonl_path))?;
+
+    // 一時ファイルに書き込み
+    let output_tmp_file = normalized_jsonl_path.with_extension("jsonl.tmp");
+
+    let mut writer = OutputWriter::create(&output_tmp_file, compression)?;
+    let mut buf = Vec::with_capacity(10*1024*1024);
+    let mut line_number = 0;
+
+    // JSONLファイルを1行ずつ読み込み、正規化して書き込み
+    loop {
+        buf.clear(); // 次の行を読み込む前に必ずクリアする
+        let size = raw_jsonl_reader.read_until(b'\n', &mut buf)?;
+        if size == 0 { break; } // 終端に達したら終了
+        line_number += 1;
+        if buf.trim_ascii().is_empty() { continue; } // 空行はスキップ
+
+        {
+            match record::normalize_raw_json(&buf) {
+                Ok(json_str) => {
+                    writer.write_all(json_str.as_bytes())?;
+                    writer.write_all(b"\n")?;
+                },
+                Err(rejection) => {
+                    eprintln!("Rejected line {} of {:?}: {}", line_number, raw_jsonl_path, rejection);
+                    let raw = String::from_utf8_lossy(&buf).trim_end_matches(['\r', '\n']).to_string();
+                    dead_letters.write(&DeadLetter::new(raw_jsonl_path, line_number, Stage::NormalizeJson, rejection, raw))?;
+                }
+            }
+        }
+    }
+    writer.finish()?;
+
+    std::fs::rename(output_tmp_file, normalized_jsonl_path)
+        .with_context(|| format!("Failed to rename temp file to output file: {:?}", normalized_jsonl_path))?;
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::import::checkpoint::TrackedLines;
+use crate::import::dead_letter::{DeadLetter, DeadLetterWriter, RejectKind, Rejection, Stage};
+use crate::import::record;
+use crate::pipeline;
+use crate::pipeline::compression::{self, Compression, OutputWriter};
+
+/// RESP ファイルを raw JSON (`<output_dir>/<stem>.jsonl`) に変換する
+///
+/// 入力は圧縮されていてもよい (zstd / gzip)。出力は `compression` で圧縮し、拡張子を付ける。
+/// `force` が false の場合、出力済みのファイルは読み飛ばす。
+pub fn run(inputs: &[PathBuf], output_dir: &Path, compression: Compression, force: bool, dead_letters: &DeadLetterWriter) -> Result<()> {
+    if !output_dir.exists() {
+        std::fs::create_dir_all(output_dir)?;
+    }
+
+    inputs.iter().for_each(|path| {
+        let output_file_path = compression.output_path(&pipeline::output_path(path, output_dir, "jsonl"));
+
+        if let Err(e) = process_resp_file(path, &output_file_path, compression, force, dead_letters) {
+            eprintln!("Error processing {:?}: {:?}", path, e);
+        } else {
+            println!("Processed {:?}", path);
+        }
+    });
+
+    if dead_letters.count() > 0 {
+        println!("{} rejected records were written to {:?}", dead_letters.count(), dead_letters.path());
+    }
+
+    Ok(())
+}
+
+
+fn process_resp_file(input_path: &Path, output_path: &Path, compression: Compression, force: bool, dead_letters: &DeadLetterWriter) -> Result<()> {
+    //! RESPファイルを読み込み、JSONL形式で出力する
+    //! 既に出力ファイルが存在する場合はスキップする (force の場合は上書き)
+    //! 処理できなかった行はデッドレターに書く
+
+    if output_path.exists() && !force {
+        println!("Output file {:?} already exists. Skipping.", output_path);
+        return Ok(());
+    }
+    let (_, reader) = compression::open_input(input_path, None)
+        .with_context(|| format!("Failed to open input file: {:?}", input_path))?;
+
+    // 一時ファイルに書き込み
+    let output_tmp_file = output_path.with_extension("jsonl.tmp");
+
+    let mut writer = OutputWriter::create(&output_tmp_file, compression)?;
+
+    let mut lines = TrackedLines::new(reader, 0, 0);
+    while let Some(line) = lines.next() {
+        let line_number = lines.line_number();
+        let (rejection, raw) = match line {
+            Ok(line) => match record::resp_to_raw_json(&line) {
+                Ok(Some(json)) => {
+                    writer.write_all(json.as_bytes())?;
+                    writer.write_all(b"\n")?;
+                    continue;
+                }
+                // 404は多いので無視
+                Ok(None) => continue,
+                Err(rejection) => (rejection, line),
+            },
+            Err(e) => (Rejection::new(RejectKind::Read, None, e.to_string()), String::new()),
+        };
+        eprintln!("Rejected line {} of {:?}: {}", line_number, input_path, rejection);
+        dead_letters.write(&DeadLetter::new(input_path, line_number, Stage::RespToJson, rejection, raw))?;
+    }
+    writer.finish()?;
+
+    // 一時ファイルを最終出力ファイルにリネーム
+    std::fs::rename(output_tmp_file, output_path)
+        .with_context(|| format!("Failed to rename temp file to output file: {:?}", output_path))?;
+
+    Ok(())
+}
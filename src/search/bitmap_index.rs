@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Instant;
+use chrono::{DateTime, FixedOffset};
+use futures::StreamExt;
+use roaring::{MultiOps, RoaringBitmap};
+use sea_orm::{AccessMode, DatabaseConnection, DbBackend, DbErr, IsolationLevel, Statement, StreamTrait, TransactionTrait, Value};
+use serde::Serialize;
+use crate::mapper::galleries_mapper::{self, CHANGED_SINCE};
+use crate::search::parser::{Namespace, Query, Term};
+
+/// 検索語 → gallery_id のビットマップ
+///
+/// tag / artist / group / character / parody / language / type をすべて持ち、
+/// タイトル以外の検索クエリは DB に問い合わせずに評価できる。
+/// 名前の比較やタグの分類、結果の順序は `compiler::build_search_statement` と同じにしている。
+#[derive(Debug, Default)]
+pub struct BitmapIndex {
+    terms: HashMap<Term, RoaringBitmap>,
+    /// 全ギャラリー (NOT の評価に使う)
+    all: RoaringBitmap,
+    /// gallery_id → galleries.date (UNIX 時間のマイクロ秒、結果の並べ替えに使う)
+    dates: HashMap<u32, i64>,
+    /// 最後にすべて、または増分を読み込んだスナップショットの xmin
+    /// (増分更新では galleries.change_xid がこれ以上の行だけ読む)
+    change_xmin: i64,
+}
+
+/// 読み込む galleries の範囲
+#[derive(Debug, Clone)]
+pub enum LoadFilter {
+    All,
+    /// galleries.change_xid がこれ以上の行 (前回の読み込み以降に追加・更新された行)
+    ChangedSince(i64),
+    GalleryIds(Vec<i32>),
+}
+
+impl LoadFilter {
+    fn condition(&self) -> (String, Vec<Value>) {
+        match self {
+            Self::All => ("TRUE".to_string(), vec![]),
+            Self::ChangedSince(xmin) => (format!("g.{CHANGED_SINCE}"), vec![(*xmin).into()]),
+            Self::GalleryIds(ids) => ("g.gallery_id = ANY($1)".to_string(), vec![ids.clone().into()]),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct IndexStats {
+    pub galleries: u64,
+    pub terms: usize,
+    pub change_xmin: i64,
+}
+
+/// 名前空間ごとの (ジャンクションテーブル, エンティティのテーブル, 外部キー, 名前の列)
+const JUNCTIONS: [(Namespace, &str, &str, &str, &str); 4] = [
+    (Namespace::Artist, "gallery_artists", "artists", "artist_id", "artist"),
+    (Namespace::Group, "gallery_groups", "groups", "group_id", "\"group\""),
+    (Namespace::Character, "gallery_characters", "characters", "character_id", "character"),
+    (Namespace::Parody, "gallery_parodies", "parodies", "parody_id", "parody"),
+];
+
+impl BitmapIndex {
+    /// 指定した範囲の galleries とジャンクションテーブルを読み込む
+    /// (1つの REPEATABLE READ トランザクションで読むので、テーブル間の整合性は取れている)
+    pub async fn load(db: &DatabaseConnection, filter: &LoadFilter) -> Result<Self, DbErr> {
+        let txn = db.begin_with_config(Some(IsolationLevel::RepeatableRead), Some(AccessMode::ReadOnly)).await?;
+        let (condition, values) = filter.condition();
+        let mut index = Self { change_xmin: galleries_mapper::snapshot_xmin(&txn).await?, ..Self::default() };
+
+        let stmt = Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            format!(
+                "SELECT g.gallery_id, g.date, g.type, l.name AS language \
+                 FROM galleries g LEFT JOIN languages l ON l.id = g.language_id WHERE {condition}"
+            ),
+            values.clone(),
+        );
+        {
+            let mut rows = txn.stream_raw(stmt).await?;
+            while let Some(row) = rows.next().await {
+                let row = row?;
+                let gallery_id = row.try_get::<i32>("", "gallery_id")? as u32;
+                let date: DateTime<FixedOffset> = row.try_get("", "date")?;
+                index.insert_gallery(gallery_id, date.timestamp_micros());
+                index.insert(Namespace::Type, &row.try_get::<String>("", "type")?, gallery_id);
+                if let Some(language) = row.try_get::<Option<String>>("", "language")? {
+                    index.insert(Namespace::Language, &language, gallery_id);
+                }
+            }
+        }
+
+        let stmt = Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            format!(
+                "SELECT g.gallery_id, e.name, e.male, e.female \
+                 FROM gallery_tags j JOIN galleries g ON g.id = j.gallery_id JOIN tags e ON e.id = j.tag_id WHERE {condition}"
+            ),
+            values.clone(),
+        );
+        {
+            let mut rows = txn.stream_raw(stmt).await?;
+            while let Some(row) = rows.next().await {
+                let row = row?;
+                // male と female が両方 true のタグは SQL の検索でもどの名前空間にも一致しない
+                let Some(namespace) = Namespace::from_tag_flags(row.try_get("", "male")?, row.try_get("", "female")?) else {
+                    continue;
+                };
+                index.insert(namespace, &row.try_get::<String>("", "name")?, row.try_get::<i32>("", "gallery_id")? as u32);
+            }
+        }
+
+        for (namespace, junction, table, foreign_key, name_column) in JUNCTIONS {
+            let stmt = Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                format!(
+                    "SELECT g.gallery_id, e.{name_column} AS name \
+                     FROM {junction} j JOIN galleries g ON g.id = j.gallery_id JOIN {table} e ON e.id = j.{foreign_key} WHERE {condition}"
+                ),
+                values.clone(),
+            );
+            let mut rows = txn.stream_raw(stmt).await?;
+            while let Some(row) = rows.next().await {
+                let row = row?;
+                index.insert(namespace, &row.try_get::<String>("", "name")?, row.try_get::<i32>("", "gallery_id")? as u32);
+            }
+        }
+
+        txn.rollback().await.ok();
+        Ok(index)
+    }
+
+    /// ギャラリーと並べ替えに使う日時を追加する
+    pub fn insert_gallery(&mut self, gallery_id: u32, date_micros: i64) {
+        self.all.insert(gallery_id);
+        self.dates.insert(gallery_id, date_micros);
+    }
+
+    /// 検索語を追加する (SQL の検索と同じく、名前は DB の値と完全一致で比較する)
+    pub fn insert(&mut self, namespace: Namespace, value: &str, gallery_id: u32) {
+        self.all.insert(gallery_id);
+        let term = Term { namespace, value: value.to_string() };
+        self.terms.entry(term).or_default().insert(gallery_id);
+    }
+
+    /// 別に読み込んだインデックスを取り込む (change_xmin は変えない)
+    pub fn merge(&mut self, other: BitmapIndex) {
+        for (term, bitmap) in other.terms {
+            *self.terms.entry(term).or_default() |= bitmap;
+        }
+        self.all |= other.all;
+        self.dates.extend(other.dates);
+    }
+
+    /// ギャラリーをすべての検索語から取り除く
+    pub fn remove_galleries(&mut self, gallery_ids: &RoaringBitmap) {
+        if gallery_ids.is_empty() {
+            return;
+        }
+        self.terms.retain(|_, bitmap| {
+            *bitmap -= gallery_ids;
+            !bitmap.is_empty()
+        });
+        self.all -= gallery_ids;
+        self.dates.retain(|gallery_id, _| !gallery_ids.contains(*gallery_id));
+    }
+
+    pub fn stats(&self) -> IndexStats {
+        IndexStats { galleries: self.all.len(), terms: self.terms.len(), change_xmin: self.change_xmin }
+    }
+
+    /// 検索クエリを評価し、`after` より後ろの `offset` から `limit` 件を (date, gallery_id) で返す
+    /// (順序は SQL の検索と同じく date の降順、同じ日時は gallery_id の降順)
+    pub fn search(&self, query: &Query, after: Option<(i64, u32)>, offset: usize, limit: usize) -> Option<Vec<(i64, u32)>> {
+        let bitmap = self.evaluate(query)?;
+        let mut keys: Vec<(i64, u32)> = bitmap.iter()
+            .map(|gallery_id| (self.dates.get(&gallery_id).copied().unwrap_or(i64::MIN), gallery_id))
+            .filter(|key| after.is_none_or(|after| *key < after))
+            .collect();
+
+        // 必要な先頭の範囲だけを選んでから並べる
+        let end = offset.saturating_add(limit).min(keys.len());
+        if end == 0 {
+            return Some(Vec::new());
+        }
+        if end < keys.len() {
+            keys.select_nth_unstable_by(end - 1, |a, b| b.cmp(a));
+            keys.truncate(end);
+        }
+        keys.sort_unstable_by(|a, b| b.cmp(a));
+        Some(keys.into_iter().skip(offset).collect())
+    }
+
+    /// 検索クエリを評価する (タイトル検索を含む場合はインデックスでは評価できないので None)
+    pub fn evaluate(&self, query: &Query) -> Option<RoaringBitmap> {
+        match query {
+            Query::Term(term) if term.namespace == Namespace::Title => None,
+            Query::Term(term) => Some(self.terms.get(term).cloned().unwrap_or_default()),
+            Query::Not(inner) => Some(&self.all - &self.evaluate(inner)?),
+            Query::Or(items) => Some(items.iter().map(|item| self.evaluate(item)).collect::<Option<Vec<_>>>()?.union()),
+            Query::And(items) => {
+                // NOT はまとめて最後に引く (全体の補集合を作らないため)
+                let mut positives = Vec::new();
+                let mut negatives = Vec::new();
+                for item in items {
+                    match item {
+                        Query::Not(inner) => negatives.push(self.evaluate(inner)?),
+                        item => positives.push(self.evaluate(item)?),
+                    }
+                }
+                let base = if positives.is_empty() { self.all.clone() } else { positives.intersection() };
+                Some(base - negatives.union())
+            }
+        }
+    }
+}
+
+/// サーバー全体で共有するインデックス
+pub struct SharedBitmapIndex {
+    index: RwLock<BitmapIndex>,
+    /// 更新を直列化する (読み込み中も検索はできる)
+    refresh_lock: tokio::sync::Mutex<()>,
+}
+
+/// 更新の方法
+#[derive(Debug, Clone)]
+pub enum Refresh {
+    /// 追加・更新された行 (galleries.change_xid で判定) を読み直し、削除された行を取り除く
+    Incremental,
+    /// 指定したギャラリーを読み直す (更新された行)
+    Galleries(Vec<i32>),
+    /// すべて読み直す
+    Full,
+}
+
+impl SharedBitmapIndex {
+    pub async fn build(db: &DatabaseConnection) -> Result<Self, DbErr> {
+        Ok(Self {
+            index: RwLock::new(BitmapIndex::load(db, &LoadFilter::All).await?),
+            refresh_lock: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn evaluate(&self, query: &Query) -> Option<RoaringBitmap> {
+        self.index.read().expect("bitmap index lock poisoned").evaluate(query)
+    }
+
+    pub fn search(&self, query: &Query, after: Option<(i64, u32)>, offset: usize, limit: usize) -> Option<Vec<(i64, u32)>> {
+        self.index.read().expect("bitmap index lock poisoned").search(query, after, offset, limit)
+    }
+
+    pub fn stats(&self) -> IndexStats {
+        self.index.read().expect("bitmap index lock poisoned").stats()
+    }
+
+    /// 全ギャラリー (NOT の評価用)
+    pub fn all(&self) -> RoaringBitmap {
+        self.index.read().expect("bitmap index lock poisoned").all.clone()
+    }
+
+    /// DB から読み直す (読み込みはロックの外で行い、差し替えだけを書き込みロックで行う)
+    pub async fn refresh(&self, db: &DatabaseConnection, refresh: Refresh) -> Result<(IndexStats, u128), DbErr> {
+        let _guard = self.refresh_lock.lock().await;
+        let started = Instant::now();
+
+        match refresh {
+            Refresh::Full => {
+                let index = BitmapIndex::load(db, &LoadFilter::All).await?;
+                *self.index.write().expect("bitmap index lock poisoned") = index;
+            }
+            Refresh::Incremental => {
+                let since = self.index.read().expect("bitmap index lock poisoned").change_xmin;
+                // 先に全 gallery_id を読む (後から削除された行を読み直した分で戻さないため)
+                let current = load_gallery_ids(db).await?;
+                let changed = BitmapIndex::load(db, &LoadFilter::ChangedSince(since)).await?;
+
+                // 更新されたギャラリーは古い検索語から取り除いてから読み直した分を入れる
+                let mut index = self.index.write().expect("bitmap index lock poisoned");
+                let stale = (&index.all - &current) | &changed.all;
+                let change_xmin = changed.change_xmin;
+                index.remove_galleries(&stale);
+                index.merge(changed);
+                index.change_xmin = change_xmin;
+            }
+            Refresh::Galleries(gallery_ids) => {
+                let reloaded = BitmapIndex::load(db, &LoadFilter::GalleryIds(gallery_ids.clone())).await?;
+                let targets: RoaringBitmap = gallery_ids.iter().map(|id| *id as u32).collect();
+
+                let mut index = self.index.write().expect("bitmap index lock poisoned");
+                index.remove_galleries(&targets);
+                index.merge(reloaded);
+            }
+        }
+
+        Ok((self.stats(), started.elapsed().as_millis()))
+    }
+}
+
+/// 現在の全 gallery_id
+async fn load_gallery_ids(db: &DatabaseConnection) -> Result<RoaringBitmap, DbErr> {
+    let stmt = Statement::from_string(DbBackend::Postgres, "SELECT gallery_id FROM galleries");
+    let mut bitmap = RoaringBitmap::new();
+    let mut rows = db.stream_raw(stmt).await?;
+    while let Some(row) = rows.next().await {
+        bitmap.insert(row?.try_get::<i32>("", "gallery_id")? as u32);
+    }
+    Ok(bitmap)
+}
@@ -0,0 +1,116 @@
+use sea_orm::{DbBackend, Statement, Value};
+use crate::domain::cursor::{Keyset, SortKey, SortOrder};
+use crate::search::parser::{Namespace, Query, Term};
+
+/// 検索結果の並び順 (date の降順、同じ日時は gallery_id の降順)
+pub const SORT_KEY: SortKey = SortKey::Date;
+pub const SORT_ORDER: SortOrder = SortOrder::Desc;
+
+/// 構文木を galleries (エイリアス `g`) に対する WHERE 条件に変換する
+/// 値はすべてバインドパラメータとして `values` に追加される
+pub fn compile_condition(query: &Query, values: &mut Vec<Value>) -> String {
+    match query {
+        Query::Term(term) => compile_term(term, values),
+        Query::Not(inner) => format!("NOT ({})", compile_condition(inner, values)),
+        Query::And(items) => join_conditions(items, " AND ", values),
+        Query::Or(items) => join_conditions(items, " OR ", values),
+    }
+}
+
+/// 検索クエリ全体を `SELECT gallery_id` の Statement に変換する (新しい順)
+pub fn build_search_statement(query: &Query, limit: u32, offset: u32) -> Statement {
+    let mut values = Vec::new();
+    let condition = compile_condition(query, &mut values);
+    let sql = format!(
+        "SELECT g.gallery_id FROM galleries g WHERE {} ORDER BY g.date DESC, g.gallery_id DESC LIMIT {} OFFSET {}",
+        condition, limit, offset
+    );
+    Statement::from_sql_and_values(DbBackend::Postgres, sql, values)
+}
+
+/// 検索クエリ全体を (ソートキー, gallery_id) のキーセットページネーションの Statement に変換する
+/// (2列目にカーソルを作るためのソートキーのテキスト表現を返す)
+pub fn build_search_keyset_statement(query: &Query, keyset: &Keyset, limit: u32) -> Statement {
+    let mut values = Vec::new();
+    let condition = compile_condition(query, &mut values);
+    let expr = keyset.sort_by.expr();
+    let direction = keyset.order.keyword();
+
+    let mut sql = format!("SELECT g.gallery_id, ({expr})::text AS sort_key FROM galleries g WHERE ({condition})");
+    if let Some(after) = &keyset.after {
+        let key = bind(&mut values, after.key.clone());
+        values.push(after.gallery_id.into());
+        sql.push_str(&format!(
+            " AND ({expr}, g.gallery_id) {} ({key}::{}, ${})",
+            keyset.order.next_operator(),
+            keyset.sort_by.sql_type(),
+            values.len(),
+        ));
+    }
+    sql.push_str(&format!(" ORDER BY {expr} {direction}, g.gallery_id {direction} LIMIT {limit}"));
+    Statement::from_sql_and_values(DbBackend::Postgres, sql, values)
+}
+
+fn join_conditions(items: &[Query], separator: &str, values: &mut Vec<Value>) -> String {
+    let parts: Vec<String> = items.iter()
+        .map(|item| format!("({})", compile_condition(item, values)))
+        .collect();
+    parts.join(separator)
+}
+
+fn compile_term(term: &Term, values: &mut Vec<Value>) -> String {
+    match term.namespace {
+        Namespace::Female | Namespace::Male | Namespace::Tag => {
+            let (male, female) = term.namespace.tag_flags().unwrap_or_default();
+            let p = bind(values, term.value.clone());
+            format!(
+                "EXISTS (SELECT 1 FROM gallery_tags gt JOIN tags t ON t.id = gt.tag_id \
+                 WHERE gt.gallery_id = g.id AND t.name = {} AND t.male = {} AND t.female = {})",
+                p, male, female
+            )
+        }
+        Namespace::Artist => exists_junction(values, &term.value, "gallery_artists", "artists", "artist_id", "artist"),
+        Namespace::Group => exists_junction(values, &term.value, "gallery_groups", "groups", "group_id", "\"group\""),
+        Namespace::Character => exists_junction(values, &term.value, "gallery_characters", "characters", "character_id", "character"),
+        Namespace::Parody => exists_junction(values, &term.value, "gallery_parodies", "parodies", "parody_id", "parody"),
+        Namespace::Language => {
+            let p = bind(values, term.value.clone());
+            // language_id が NULL でも NOT の結果が NULL にならないよう EXISTS にする
+            format!("EXISTS (SELECT 1 FROM languages l WHERE l.id = g.language_id AND l.name = {})", p)
+        }
+        Namespace::Type => {
+            let p = bind(values, term.value.clone());
+            format!("g.type = {}", p)
+        }
+        Namespace::Title => {
+            let p = bind(values, format!("%{}%", escape_like(&term.value)));
+            format!("(g.title ILIKE {p} OR g.japanese_title ILIKE {p})")
+        }
+    }
+}
+
+fn exists_junction(
+    values: &mut Vec<Value>,
+    value: &str,
+    junction: &str,
+    table: &str,
+    foreign_key: &str,
+    name_column: &str,
+) -> String {
+    let p = bind(values, value.to_string());
+    format!(
+        "EXISTS (SELECT 1 FROM {junction} j JOIN {table} e ON e.id = j.{foreign_key} \
+         WHERE j.gallery_id = g.id AND e.{name_column} = {p})"
+    )
+}
+
+/// 値を追加し、対応するプレースホルダ (`$n`) を返す
+fn bind(values: &mut Vec<Value>, value: String) -> String {
+    values.push(value.into());
+    format!("${}", values.len())
+}
+
+/// LIKE のワイルドカードをエスケープする
+pub fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
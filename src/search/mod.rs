@@ -0,0 +1,5 @@
+pub mod parser;
+pub mod compiler;
+
+pub mod title;
+pub mod bitmap_index;
\ No newline at end of file
@@ -0,0 +1,298 @@
+use std::fmt;
+
+/// 検索クエリの構文木
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Term(Term),
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+/// `namespace:value` 形式の検索語
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Term {
+    pub namespace: Namespace,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Namespace {
+    /// `female:` (tags.female = true)
+    Female,
+    /// `male:` (tags.male = true)
+    Male,
+    /// `tag:` (male / female どちらでもないタグ)
+    Tag,
+    Artist,
+    Group,
+    Character,
+    /// `series:` または `parody:`
+    Parody,
+    Language,
+    Type,
+    /// 名前空間なし (タイトル検索)
+    Title,
+}
+
+impl Namespace {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "female" => Some(Self::Female),
+            "male" => Some(Self::Male),
+            "tag" => Some(Self::Tag),
+            "artist" => Some(Self::Artist),
+            "group" => Some(Self::Group),
+            "character" => Some(Self::Character),
+            "series" | "parody" => Some(Self::Parody),
+            "language" => Some(Self::Language),
+            "type" => Some(Self::Type),
+            _ => None,
+        }
+    }
+
+    /// タグの名前空間に対応する tags の (male, female)
+    /// (両方 true のタグはどの名前空間にも属さない)
+    pub fn tag_flags(&self) -> Option<(bool, bool)> {
+        match self {
+            Self::Female => Some((false, true)),
+            Self::Male => Some((true, false)),
+            Self::Tag => Some((false, false)),
+            _ => None,
+        }
+    }
+
+    /// tags の (male, female) から名前空間を求める (`tag_flags` の逆)
+    pub fn from_tag_flags(male: bool, female: bool) -> Option<Self> {
+        [Self::Female, Self::Male, Self::Tag].into_iter().find(|namespace| namespace.tag_flags() == Some((male, female)))
+    }
+
+    /// 検索クエリで使う名前空間名 (Title は名前空間なし)
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Self::Female => Some("female"),
+            Self::Male => Some("male"),
+            Self::Tag => Some("tag"),
+            Self::Artist => Some("artist"),
+            Self::Group => Some("group"),
+            Self::Character => Some("character"),
+            Self::Parody => Some("series"),
+            Self::Language => Some("language"),
+            Self::Type => Some("type"),
+            Self::Title => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// エラー位置 (クエリ文字列の文字オフセット)
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Or,
+    Not,
+    Term { namespace: Option<String>, value: String, quoted: bool },
+}
+
+/// 検索クエリをパースする
+///
+/// - 空白区切りは AND、`|` または `OR` は OR (AND より強く結合する)
+/// - 先頭の `-` は否定、`( ... )` でグループ化
+/// - 値は `"..."` でクォートでき、クォートしない場合 `_` は空白として扱う
+pub fn parse(input: &str) -> Result<Query, ParseError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser { tokens, pos: 0, input_len: input.chars().count() };
+    let query = parser.parse_and()?;
+    if let Some((position, _)) = parser.peek() {
+        return Err(ParseError { position, message: "Unexpected ')'".to_string() });
+    }
+    Ok(query)
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => { i += 1; }
+            '(' => { tokens.push((i, Token::LParen)); i += 1; }
+            ')' => { tokens.push((i, Token::RParen)); i += 1; }
+            '|' => { tokens.push((i, Token::Or)); i += 1; }
+            '-' => { tokens.push((i, Token::Not)); i += 1; }
+            '"' => {
+                let (value, next) = read_quoted(&chars, i)?;
+                tokens.push((i, Token::Term { namespace: None, value, quoted: true }));
+                i = next;
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '(' | ')' | '|' | '"') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+
+                if word == "OR" {
+                    tokens.push((start, Token::Or));
+                    continue;
+                }
+
+                match word.split_once(':') {
+                    Some((namespace, value)) => {
+                        // namespace:"quoted value"
+                        if value.is_empty() && i < chars.len() && chars[i] == '"' {
+                            let (value, next) = read_quoted(&chars, i)?;
+                            tokens.push((start, Token::Term { namespace: Some(namespace.to_string()), value, quoted: true }));
+                            i = next;
+                        } else {
+                            tokens.push((start, Token::Term { namespace: Some(namespace.to_string()), value: value.to_string(), quoted: false }));
+                        }
+                    }
+                    None => tokens.push((start, Token::Term { namespace: None, value: word, quoted: false })),
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// `"` から始まるクォート文字列を読む (`\"` と `\\` のエスケープに対応)
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), ParseError> {
+    let mut value = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '"' => return Ok((value, i + 1)),
+            '\\' if i + 1 < chars.len() => {
+                value.push(chars[i + 1]);
+                i += 2;
+            }
+            c => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err(ParseError { position: start, message: "Unterminated quoted string".to_string() })
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    input_len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<(usize, &Token)> {
+        self.tokens.get(self.pos).map(|(p, t)| (*p, t))
+    }
+
+    fn next(&mut self) -> Option<(usize, Token)> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_and(&mut self) -> Result<Query, ParseError> {
+        let mut items = Vec::new();
+        while let Some((_, token)) = self.peek() {
+            if *token == Token::RParen {
+                break;
+            }
+            items.push(self.parse_or()?);
+        }
+        match items.len() {
+            0 => Err(ParseError { position: self.current_position(), message: "Empty query".to_string() }),
+            1 => Ok(items.remove(0)),
+            _ => Ok(Query::And(items)),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Query, ParseError> {
+        let mut items = vec![self.parse_unary()?];
+        while let Some((_, Token::Or)) = self.peek() {
+            self.next();
+            items.push(self.parse_unary()?);
+        }
+        if items.len() == 1 {
+            Ok(items.remove(0))
+        } else {
+            Ok(Query::Or(items))
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, ParseError> {
+        let position = self.current_position();
+        match self.next() {
+            Some((_, Token::Not)) => Ok(Query::Not(Box::new(self.parse_unary()?))),
+            Some((_, Token::LParen)) => {
+                let query = self.parse_and()?;
+                match self.next() {
+                    Some((_, Token::RParen)) => Ok(query),
+                    _ => Err(ParseError { position, message: "Unclosed '('".to_string() }),
+                }
+            }
+            Some((position, Token::Term { namespace, value, quoted })) => {
+                let namespace = match namespace {
+                    Some(name) => Namespace::from_name(&name.to_lowercase()).ok_or_else(|| ParseError {
+                        position,
+                        message: format!("Unknown namespace '{}'", name),
+                    })?,
+                    None => Namespace::Title,
+                };
+                let value = normalize_value(&value, quoted);
+                if value.is_empty() {
+                    return Err(ParseError { position, message: "Empty value".to_string() });
+                }
+                Ok(Query::Term(Term { namespace, value }))
+            }
+            Some((position, token)) => Err(ParseError {
+                position,
+                message: format!("Unexpected {}", describe(&token)),
+            }),
+            None => Err(ParseError { position, message: "Unexpected end of query".to_string() }),
+        }
+    }
+
+    fn current_position(&self) -> usize {
+        self.peek().map(|(p, _)| p).unwrap_or(self.input_len)
+    }
+}
+
+/// 名前空間と値から検索語を作る (値の空白は `_` にする)
+pub fn format_term(namespace: &str, value: &str) -> String {
+    format!("{}:{}", namespace, value.replace(' ', "_"))
+}
+
+/// hitomi の表記に合わせて小文字化し、クォートされていない `_` を空白に戻す
+fn normalize_value(value: &str, quoted: bool) -> String {
+    let value = value.trim().to_lowercase();
+    if quoted { value } else { value.replace('_', " ") }
+}
+
+fn describe(token: &Token) -> &'static str {
+    match token {
+        Token::LParen => "'('",
+        Token::RParen => "')'",
+        Token::Or => "'|'",
+        Token::Not => "'-'",
+        Token::Term { .. } => "term",
+    }
+}
@@ -0,0 +1,98 @@
+use sea_orm::{DbBackend, Statement, Value};
+
+/// fuzzy の場合の word_similarity のしきい値 (pg_trgm の既定値 0.6 では1文字の入れ替えも拾えない)
+pub const FUZZY_WORD_SIMILARITY_THRESHOLD: f32 = 0.25;
+
+/// タイトル検索の入力を tsquery に分けたもの
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TitleQuery {
+    /// ASCII の単語 (`websearch_to_tsquery('simple', ...)` に渡す)
+    pub words: String,
+    /// ASCII 以外の単語を bigram に分けた tsquery (japanese_title 用)
+    pub bigrams: Option<String>,
+}
+
+impl TitleQuery {
+    /// ASCII の単語は title の単語として、それ以外 (日本語など) は japanese_title の bigram として探す
+    pub fn parse(input: &str) -> Self {
+        let (ascii, others): (Vec<&str>, Vec<&str>) = input.split_whitespace().partition(|word| word.is_ascii());
+
+        let lexemes: Vec<String> = others.iter()
+            .flat_map(|word| {
+                let bigrams = title_bigrams(word);
+                if bigrams.is_empty() {
+                    // 1文字の場合はその文字から始まる bigram に前方一致させる
+                    vec![format!("{}:*", quote_lexeme(&word.to_lowercase()))]
+                } else {
+                    bigrams.iter().map(|bigram| quote_lexeme(bigram)).collect()
+                }
+            })
+            .collect();
+
+        Self {
+            words: ascii.join(" "),
+            bigrams: (!lexemes.is_empty()).then(|| lexemes.join(" & ")),
+        }
+    }
+}
+
+/// schema.sql の title_bigrams と同じ正規化で bigram に分ける
+pub fn title_bigrams(input: &str) -> Vec<String> {
+    let chars: Vec<char> = input.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    let mut bigrams: Vec<String> = chars.windows(2).map(|pair| pair.iter().collect()).collect();
+    bigrams.sort();
+    bigrams.dedup();
+    bigrams
+}
+
+/// tsquery のリテラルとしてクォートする
+fn quote_lexeme(lexeme: &str) -> String {
+    format!("'{}'", lexeme.replace('\\', "\\\\").replace('\'', "''"))
+}
+
+/// タイトル検索のトランザクションで設定する値
+pub fn local_settings(fuzzy: bool) -> Vec<(&'static str, String)> {
+    if fuzzy {
+        vec![("pg_trgm.word_similarity_threshold", FUZZY_WORD_SIMILARITY_THRESHOLD.to_string())]
+    } else {
+        vec![]
+    }
+}
+
+/// タイトル検索の Statement を作る (関連度の高い順、同じ場合は新しい順)
+///
+/// `fuzzy` の場合は pg_trgm の word_similarity で綴りの揺れも拾い、類似度を関連度に加える。
+/// (しきい値は `local_settings` でトランザクションに設定する)
+pub fn build_title_search_statement(input: &str, fuzzy: bool, limit: u32, offset: u32) -> Statement {
+    let title_query = TitleQuery::parse(input);
+    let mut values: Vec<Value> = Vec::new();
+
+    let mut parts = Vec::new();
+    if !title_query.words.is_empty() {
+        values.push(title_query.words.into());
+        parts.push(format!("websearch_to_tsquery('simple', ${})", values.len()));
+    }
+    if let Some(bigrams) = title_query.bigrams {
+        values.push(bigrams.into());
+        parts.push(format!("${}::tsquery", values.len()));
+    }
+    let tsquery = parts.join(" && ");
+
+    let (condition, rank) = if fuzzy {
+        values.push(input.trim().to_lowercase().into());
+        let p = format!("${}", values.len());
+        (
+            format!("g.title_tsv @@ q.query OR {p} <% g.title OR {p} <% g.japanese_title"),
+            format!("ts_rank(g.title_tsv, q.query) + GREATEST(word_similarity({p}, g.title), word_similarity({p}, COALESCE(g.japanese_title, '')))"),
+        )
+    } else {
+        ("g.title_tsv @@ q.query".to_string(), "ts_rank(g.title_tsv, q.query)".to_string())
+    };
+
+    let sql = format!(
+        "SELECT g.gallery_id FROM galleries g, (SELECT {tsquery} AS query) q \
+         WHERE {condition} \
+         ORDER BY {rank} DESC, g.date DESC, g.gallery_id DESC LIMIT {limit} OFFSET {offset}"
+    );
+    Statement::from_sql_and_values(DbBackend::Postgres, sql, values)
+}